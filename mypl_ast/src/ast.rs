@@ -1,5 +1,7 @@
 use mypl_lex::prelude::*;
 
+use crate::types::Type;
+
 #[derive(Clone, Debug, PartialEq, Copy)]
 pub enum BinOp {
     Add,
@@ -15,6 +17,11 @@ pub enum BinOp {
     Ne,
     Ge,
     Gt,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
 }
 
 impl BinOp {
@@ -33,6 +40,11 @@ impl BinOp {
             BinOp::Ne => "!=", 
             BinOp::Ge => ">=", 
             BinOp::Gt => ">", 
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
         }
     }
 }
@@ -45,7 +57,7 @@ impl std::fmt::Display for BinOp {
             BinOp::Mul => "*",
             BinOp::Div => "/",
             BinOp::Rem => "%",
-            BinOp::And => "&",
+            BinOp::And => "&&",
             BinOp::Or => "||",
             BinOp::Eq => "==",
             BinOp::Lt => "<",
@@ -53,6 +65,11 @@ impl std::fmt::Display for BinOp {
             BinOp::Ne => "!=",
             BinOp::Ge => ">=",
             BinOp::Gt => ">",
+            BinOp::BitAnd => "&",
+            BinOp::BitOr => "|",
+            BinOp::BitXor => "^",
+            BinOp::Shl => "<<",
+            BinOp::Shr => ">>",
         })
     }
 }
@@ -83,28 +100,336 @@ impl UnOp {
     }
 }
 
+/// Whether a range includes its end, `a..=b`, or not, `a..b`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RangeKind {
+    Exclusive,
+    Inclusive,
+}
+
+impl RangeKind {
+    pub fn as_code(&self) -> &str {
+        match self {
+            RangeKind::Exclusive => "..",
+            RangeKind::Inclusive => "..=",
+        }
+    }
+}
+
+/// A segment of an interpolated string
+#[derive(Clone, PartialEq, Debug)]
+pub enum InterpolatedPart {
+    Text(String),
+    Expr(Box<Expr>),
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum ExprKind {
     Binary(BinOp, Box<Expr>, Box<Expr>),
     Unary(UnOp, Box<Expr>),
     Literal(Literal),
+    Interpolated(Vec<InterpolatedPart>),
     Variable(String),
+
+    /// `if cond { .. } else { .. }`, the else branch is a Block, or an If for `else if`
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+
+    /// `{ stmts; value }`, a block that yields the value of its last expression
+    Block(Vec<Stmt>, Box<Expr>),
+
+    Range(Box<Expr>, Box<Expr>, RangeKind),
+
+    /// `callee(args)`
+    Call(Box<Expr>, Vec<Expr>),
+
+    /// `Point { x = 3, y = 4 }`, a record of the named type
+    Record(String, Vec<FieldInit>),
+
+    /// `point.x`, and `Color.Red` for a variant without fields
+    Field(Box<Expr>, String),
+
+    /// `Color.RGB { red = 128, green = 0, blue = 0 }`, a variant of the named union
+    Variant(String, String, Vec<FieldInit>),
+
+    /// `match color { Color.Red => 1, _ => 0 }`, the value of the first arm whose pattern matches
+    Match(Box<Expr>, Vec<MatchArm<Expr>>),
+
+    /// `{ u = 12, v = 32 }`, the value of a field, a record of the type of the field
+    AnonymousRecord(Vec<FieldInit>),
+
+    /// `.Line { length = 3 }` or `.Dot`, the value of a field, a variant of the union type of the field
+    AnonymousVariant(String, Vec<FieldInit>),
+
+    /// The method of the impl of a trait for a type, `Shape`, `Circle` and `area`. The semantic
+    /// analysis puts it in place of the name of a method that is called.
+    Method(String, String, String),
+}
+
+/// A field of a record literal, `x = 3`
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldInit {
+    pub name: String,
+    pub value: Expr,
+    pub span: Span,
+}
+
+/// `pattern => body`, the body is an expression in a match expression, and a block in a match statement
+#[derive(Clone, PartialEq, Debug)]
+pub struct MatchArm<T> {
+    pub pattern: Pattern,
+    pub body: T,
+    pub span: Span,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum PatternKind {
+    /// `_`, matches anything
+    Wildcard,
+
+    /// `x`, matches anything and binds it to the name in the arm
+    Binding(String),
+
+    /// `1`, `'c'`, `"s"` or `true`, matches an equal value
+    Literal(Literal),
+
+    /// `Color.RGB { red = 0, green }`, matches the variant when the patterns of the fields do.
+    /// A field without a pattern binds it by its name, and the fields that are left out match anything.
+    Variant(String, String, Vec<FieldPattern>),
+
+    /// `.Line { length }`, like a variant pattern, of the union of the value that is matched
+    AnonymousVariant(String, Vec<FieldPattern>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+/// A field of a variant pattern, `red = 0`
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldPattern {
+    pub name: String,
+    pub pattern: Pattern,
+    pub span: Span,
+}
+
+impl Pattern {
+    pub fn shift(&mut self, delta: isize) {
+        self.span = self.span.shifted(delta);
+        if let PatternKind::Variant(_, _, fields) | PatternKind::AnonymousVariant(_, fields) = &mut self.kind {
+            fields.iter_mut().for_each(|field| {
+                field.span = field.span.shifted(delta);
+                field.pattern.shift(delta);
+            });
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Expr {
     pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    /// Moves the spans of the expression and its sub expressions by a number of bytes
+    pub fn shift(&mut self, delta: isize) {
+        self.span = self.span.shifted(delta);
+        match &mut self.kind {
+            ExprKind::Binary(_, lhs, rhs) => {
+                lhs.shift(delta);
+                rhs.shift(delta);
+            }
+            ExprKind::Unary(_, expr) => expr.shift(delta),
+            ExprKind::Interpolated(parts) => parts.iter_mut().for_each(|part| match part {
+                InterpolatedPart::Expr(expr) => expr.shift(delta),
+                InterpolatedPart::Text(_) => {}
+            }),
+            ExprKind::If(condition, then_branch, else_branch) => {
+                condition.shift(delta);
+                then_branch.shift(delta);
+                else_branch.shift(delta);
+            }
+            ExprKind::Block(statements, value) => {
+                statements.iter_mut().for_each(|stmt| stmt.shift(delta));
+                value.shift(delta);
+            }
+            ExprKind::Range(start, end, _) => {
+                start.shift(delta);
+                end.shift(delta);
+            }
+            ExprKind::Call(callee, args) => {
+                callee.shift(delta);
+                args.iter_mut().for_each(|arg| arg.shift(delta));
+            }
+            ExprKind::Record(_, fields)
+            | ExprKind::Variant(_, _, fields)
+            | ExprKind::AnonymousRecord(fields)
+            | ExprKind::AnonymousVariant(_, fields) => fields.iter_mut().for_each(|field| {
+                field.span = field.span.shifted(delta);
+                field.value.shift(delta);
+            }),
+            ExprKind::Match(subject, arms) => {
+                subject.shift(delta);
+                arms.iter_mut().for_each(|arm| {
+                    arm.span = arm.span.shifted(delta);
+                    arm.pattern.shift(delta);
+                    arm.body.shift(delta);
+                });
+            }
+            ExprKind::Field(expr, _) => expr.shift(delta),
+            ExprKind::Literal(_) | ExprKind::Variable(_) | ExprKind::Method(..) => {}
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
 pub enum DeclKind {
-    Var(String, Box::<Expr>),
-    Const(String, Box::<Expr>),
+    /// `var x: u32 = 4;`, the type annotation is optional
+    Var(String, Option<Type>, Box::<Expr>),
+    Const(String, Option<Type>, Box::<Expr>),
+    Function(Box<Function>),
+    Record(Box<RecordDecl>),
+    Union(Box<UnionDecl>),
+    Trait(Box<TraitDecl>),
+    Impl(Box<ImplDecl>),
+}
+
+/// A parameter of a function, `value: i8`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Param {
+    pub name: String,
+    pub ty: Type,
+    pub span: Span,
+}
+
+/// `const increment: (value: i8) i8 = { .. }`, a function without a return type yields no value
+#[derive(Clone, PartialEq, Debug)]
+pub struct Function {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub return_type: Option<Type>,
+    pub body: Vec<Stmt>,
+}
+
+impl Function {
+    /// The type of the function, its parameter names left out
+    pub fn ty(&self) -> Type {
+        Type::Function(
+            self.params.iter().map(|param| param.ty.clone()).collect(),
+            self.return_type.clone().map(Box::new),
+        )
+    }
+
+    pub fn shift(&mut self, delta: isize) {
+        self.params.iter_mut().for_each(|param| {
+            param.span = param.span.shifted(delta);
+            param.ty.shift(delta);
+        });
+        if let Some(return_type) = &mut self.return_type {
+            return_type.shift(delta);
+        }
+        self.body.iter_mut().for_each(|stmt| stmt.shift(delta));
+    }
+}
+
+/// A field of a record type, `x: u32`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Field {
+    pub name: String,
+    pub ty: Type,
+    pub span: Span,
+}
+
+impl Field {
+    pub fn shift(&mut self, delta: isize) {
+        self.span = self.span.shifted(delta);
+        self.ty.shift(delta);
+    }
+}
+
+/// `Point = record { x: u32, y: u32 };`, the fields are in the order of the declaration
+#[derive(Clone, PartialEq, Debug)]
+pub struct RecordDecl {
+    pub name: String,
+    pub fields: Vec<Field>,
+}
+
+/// A variant of a union, `Red`, or `RGB: record { red: u8, green: u8, blue: u8 }` with fields
+#[derive(Clone, PartialEq, Debug)]
+pub struct VariantDecl {
+    pub name: String,
+    pub fields: Vec<Field>,
+    pub span: Span,
+}
+
+impl VariantDecl {
+    pub fn shift(&mut self, delta: isize) {
+        self.span = self.span.shifted(delta);
+        self.fields.iter_mut().for_each(|field| field.shift(delta));
+    }
+}
+
+/// `Color = union { Red, Green, Blue, RGB: record { .. } };`
+#[derive(Clone, PartialEq, Debug)]
+pub struct UnionDecl {
+    pub name: String,
+    pub variants: Vec<VariantDecl>,
+}
+
+/// `Shape = trait { area: (Self) f32 };`, the methods are the signatures the types that implement it have,
+/// `Self` is the type that implements it
+#[derive(Clone, PartialEq, Debug)]
+pub struct TraitDecl {
+    pub name: String,
+    pub methods: Vec<Field>,
+}
+
+impl TraitDecl {
+    /// The type of the method in an impl for the type, `Self` replaced by it
+    pub fn method_type(&self, method: &Field, type_name: &str) -> Type {
+        method.ty.replace_named("Self", &Type::Named(type_name.to_string()))
+    }
+}
+
+/// `impl Shape for Circle { area: (circle: Circle) f32 = { .. } }`, the methods of the trait for the named type
+#[derive(Clone, PartialEq, Debug)]
+pub struct ImplDecl {
+    pub trait_name: String,
+    pub type_name: String,
+    pub methods: Vec<Function>,
 }
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Decl {
     pub kind: DeclKind,
+
+    /// The `///` doc comments that precede the declaration, without the slashes
+    pub doc: Option<String>,
+
+    /// The declaration itself, without its doc comments
+    pub span: Span,
+}
+
+impl Decl {
+    /// Moves the spans of the declaration and its expressions by a number of bytes
+    pub fn shift(&mut self, delta: isize) {
+        self.span = self.span.shifted(delta);
+        match &mut self.kind {
+            DeclKind::Var(_, ty, expr) | DeclKind::Const(_, ty, expr) => {
+                if let Some(ty) = ty {
+                    ty.shift(delta);
+                }
+                expr.shift(delta);
+            }
+            DeclKind::Function(function) => function.shift(delta),
+            DeclKind::Record(record) => record.fields.iter_mut().for_each(|field| field.shift(delta)),
+            DeclKind::Union(union) => union.variants.iter_mut().for_each(|variant| variant.shift(delta)),
+            DeclKind::Trait(trait_decl) => trait_decl.methods.iter_mut().for_each(|method| method.shift(delta)),
+            DeclKind::Impl(impl_decl) => impl_decl.methods.iter_mut().for_each(|method| method.shift(delta)),
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -114,10 +439,71 @@ pub enum StmtKind {
     Println(Box<Expr>),
     Decl(Box<Decl>),
     Assign(String, Box<Expr>),
+
+    /// `point.x = 5;`, the variable and the path of fields to the one assigned to
+    FieldAssign(String, Vec<String>, Box<Expr>),
+
+    /// `{ ... }`, its statements run in a scope of their own
+    Block(Vec<Stmt>),
+
+    /// `if cond { .. } else { .. }`, the branches are Blocks, or an If for `else if`
+    If(Box<Expr>, Box<Stmt>, Option<Box<Stmt>>),
+
+    /// `for variable in iterable { .. }`
+    For(String, Box<Expr>, Box<Stmt>),
+    While(Box<Expr>, Box<Stmt>),
+    Break,
+    Continue,
+    Return(Option<Box<Expr>>),
+
+    /// `match color { Color.Red => { .. } }`, runs the block of the first arm whose pattern matches
+    Match(Box<Expr>, Vec<MatchArm<Stmt>>),
 }
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Stmt {
     pub kind: StmtKind,
+    pub span: Span,
 }
 
+impl Stmt {
+    /// Moves the spans of the statement and all of its nodes by a number of bytes
+    pub fn shift(&mut self, delta: isize) {
+        self.span = self.span.shifted(delta);
+        match &mut self.kind {
+            StmtKind::Expr(expr) | StmtKind::Print(expr) | StmtKind::Println(expr) => expr.shift(delta),
+            StmtKind::Decl(decl) => decl.shift(delta),
+            StmtKind::Assign(_, expr) | StmtKind::FieldAssign(_, _, expr) => expr.shift(delta),
+            StmtKind::Block(statements) => statements.iter_mut().for_each(|stmt| stmt.shift(delta)),
+            StmtKind::If(condition, then_branch, else_branch) => {
+                condition.shift(delta);
+                then_branch.shift(delta);
+                if let Some(else_branch) = else_branch {
+                    else_branch.shift(delta);
+                }
+            }
+            StmtKind::For(_, iterable, body) => {
+                iterable.shift(delta);
+                body.shift(delta);
+            }
+            StmtKind::While(condition, body) => {
+                condition.shift(delta);
+                body.shift(delta);
+            }
+            StmtKind::Return(expr) => {
+                if let Some(expr) = expr {
+                    expr.shift(delta);
+                }
+            }
+            StmtKind::Match(subject, arms) => {
+                subject.shift(delta);
+                arms.iter_mut().for_each(|arm| {
+                    arm.span = arm.span.shifted(delta);
+                    arm.pattern.shift(delta);
+                    arm.body.shift(delta);
+                });
+            }
+            StmtKind::Break | StmtKind::Continue => {}
+        }
+    }
+}
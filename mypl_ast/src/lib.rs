@@ -2,12 +2,14 @@ extern crate mypl_lex;
 extern crate anyhow;
 
 mod ast;
+mod types;
 mod ast_formatter;
 mod visitor;
 
 pub mod prelude {
     use crate::*;
     pub use ast::*;
+    pub use types::*;
     pub use ast_formatter::AstFormatter;
     pub use visitor::{ExprVisitor, AcceptExprVisitor, StmtVisitor, AcceptStmtVisitor};
 }
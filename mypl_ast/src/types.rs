@@ -0,0 +1,201 @@
+use crate::ast::{Field, VariantDecl};
+
+/// The builtin types, the numeric ones are keywords and the others are reserved names
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PrimitiveType {
+    U32,
+    U16,
+    U8,
+
+    I32,
+    I16,
+    I8,
+
+    F32,
+    F16,
+
+    Bool,
+    Char,
+    String,
+}
+
+impl PrimitiveType {
+    /// The reserved names that are not keywords
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bool" => Some(PrimitiveType::Bool),
+            "char" => Some(PrimitiveType::Char),
+            "string" => Some(PrimitiveType::String),
+            _ => None,
+        }
+    }
+
+    pub fn as_code(&self) -> &str {
+        use PrimitiveType::*;
+        match self {
+            U32 => "u32",
+            U16 => "u16",
+            U8 => "u8",
+            I32 => "i32",
+            I16 => "i16",
+            I8 => "i8",
+            F32 => "f32",
+            F16 => "f16",
+            Bool => "bool",
+            Char => "char",
+            String => "string",
+        }
+    }
+}
+
+/// A type as it is written in the source, like the `i8` in `value: i8`
+#[derive(Clone, PartialEq, Debug)]
+pub enum Type {
+    Primitive(PrimitiveType),
+
+    /// A type declared by name, like a record
+    Named(String),
+
+    /// The parameter types and the return type of a function, `(i8, i8) i8`
+    Function(Vec<Type>, Option<Box<Type>>),
+
+    /// `record { u: u32, v: u32 }`, a record type without a name
+    Record(Vec<Field>),
+
+    /// `union { Dot, Line: record { length: u32 } }`, a union type without a name
+    Union(Vec<VariantDecl>),
+
+    /// `impl Shape`, any type that implements the trait
+    Impl(String),
+}
+
+impl Type {
+    /// Moves the spans of the fields of anonymous types by a number of bytes
+    pub fn shift(&mut self, delta: isize) {
+        match self {
+            Type::Function(params, return_type) => {
+                params.iter_mut().for_each(|param| param.shift(delta));
+                if let Some(return_type) = return_type {
+                    return_type.shift(delta);
+                }
+            }
+            Type::Record(fields) => fields.iter_mut().for_each(|field| field.shift(delta)),
+            Type::Union(variants) => variants.iter_mut().for_each(|variant| variant.shift(delta)),
+            Type::Primitive(_) | Type::Named(_) | Type::Impl(_) => {}
+        }
+    }
+
+    /// The type with the named type replaced wherever it is in it, like `Self` in the methods of a trait
+    pub fn replace_named(&self, name: &str, with: &Type) -> Type {
+        match self {
+            Type::Named(named) if named == name => with.clone(),
+            Type::Function(params, return_type) => Type::Function(
+                params.iter().map(|param| param.replace_named(name, with)).collect(),
+                return_type.as_ref().map(|return_type| Box::new(return_type.replace_named(name, with))),
+            ),
+            Type::Record(fields) => Type::Record(replace_in_fields(fields, name, with)),
+            Type::Union(variants) => Type::Union(
+                variants
+                    .iter()
+                    .map(|variant| VariantDecl {
+                        fields: replace_in_fields(&variant.fields, name, with),
+                        ..variant.clone()
+                    })
+                    .collect(),
+            ),
+            ty => ty.clone(),
+        }
+    }
+
+    /// The position of the first parameter of type `Self` of a method, the argument calls dispatch on
+    pub fn self_position(&self) -> Option<usize> {
+        match self {
+            Type::Function(params, _) => params.iter().position(|param| matches!(param, Type::Named(name) if name == "Self")),
+            _ => None,
+        }
+    }
+
+    /// Whether the types are the same. Anonymous types are the same when their fields and variants are,
+    /// wherever they are written.
+    pub fn is_same(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Function(params, return_type), Type::Function(other_params, other_return_type)) => {
+                same_types(params, other_params)
+                    && match (return_type, other_return_type) {
+                        (Some(return_type), Some(other)) => return_type.is_same(other),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
+            (Type::Record(fields), Type::Record(other_fields)) => same_fields(fields, other_fields),
+            (Type::Union(variants), Type::Union(other_variants)) => {
+                variants.len() == other_variants.len()
+                    && variants.iter().zip(other_variants).all(|(variant, other)| {
+                        variant.name == other.name && same_fields(&variant.fields, &other.fields)
+                    })
+            }
+            _ => self == other,
+        }
+    }
+}
+
+fn replace_in_fields(fields: &[Field], name: &str, with: &Type) -> Vec<Field> {
+    fields
+        .iter()
+        .map(|field| Field {
+            ty: field.ty.replace_named(name, with),
+            ..field.clone()
+        })
+        .collect()
+}
+
+fn same_types(types: &[Type], others: &[Type]) -> bool {
+    types.len() == others.len() && types.iter().zip(others).all(|(ty, other)| ty.is_same(other))
+}
+
+fn same_fields(fields: &[Field], others: &[Field]) -> bool {
+    fields.len() == others.len()
+        && fields.iter().zip(others).all(|(field, other)| field.name == other.name && field.ty.is_same(&other.ty))
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Primitive(primitive) => f.write_str(primitive.as_code()),
+            Type::Named(name) => f.write_str(name),
+            Type::Impl(trait_name) => write!(f, "impl {}", trait_name),
+            Type::Function(params, return_type) => {
+                let params = params.iter().map(|param| param.to_string()).collect::<Vec<_>>();
+                write!(f, "({})", params.join(", "))?;
+                if let Some(return_type) = return_type {
+                    write!(f, " {}", return_type)?;
+                }
+                Ok(())
+            }
+            Type::Record(fields) => write!(f, "record {}", FieldList(fields)),
+            Type::Union(variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|variant| match variant.fields.is_empty() {
+                        true => variant.name.clone(),
+                        false => format!("{}: record {}", variant.name, FieldList(&variant.fields)),
+                    })
+                    .collect::<Vec<_>>();
+                write!(f, "union {{ {} }}", variants.join(", "))
+            }
+        }
+    }
+}
+
+// `{ u: u32, v: u32 }`
+struct FieldList<'a>(&'a [Field]);
+
+impl std::fmt::Display for FieldList<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = self.0
+            .iter()
+            .map(|field| format!("{}: {}", field.name, field.ty))
+            .collect::<Vec<_>>();
+        write!(f, "{{ {} }}", fields.join(", "))
+    }
+}
@@ -1,28 +1,52 @@
-use mypl_lex::prelude::Literal;
+use mypl_lex::prelude::{Literal, Span};
 use crate::prelude::*;
 
 pub trait AcceptExprVisitor {
     fn accept_expr_visitor<V: ExprVisitor>(&self, visitor: &mut V) -> V::Result;
 }
 
+/// Every visit gets the span of the visited node, so errors can point at the source
 pub trait ExprVisitor {
     type Result;
 
-    fn visit_binary_expr(&mut self, op: &BinOp, lhs: &Expr, rhs: &Expr) -> Self::Result;
-    fn visit_unary_expr(&mut self, op: &UnOp, expr: &Expr) -> Self::Result;
-    fn visit_literal_expr(&mut self, literal: &Literal) -> Self::Result; 
-    fn visit_variable_expr(&mut self, identifier: &String) -> Self::Result;
+    fn visit_binary_expr(&mut self, op: &BinOp, lhs: &Expr, rhs: &Expr, span: Span) -> Self::Result;
+    fn visit_unary_expr(&mut self, op: &UnOp, expr: &Expr, span: Span) -> Self::Result;
+    fn visit_literal_expr(&mut self, literal: &Literal, span: Span) -> Self::Result;
+    fn visit_interpolated_expr(&mut self, parts: &[InterpolatedPart], span: Span) -> Self::Result;
+    fn visit_variable_expr(&mut self, identifier: &str, span: Span) -> Self::Result;
+    fn visit_if_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr, span: Span) -> Self::Result;
+    fn visit_block_expr(&mut self, statements: &[Stmt], value: &Expr, span: Span) -> Self::Result;
+    fn visit_range_expr(&mut self, start: &Expr, end: &Expr, kind: RangeKind, span: Span) -> Self::Result;
+    fn visit_call_expr(&mut self, callee: &Expr, args: &[Expr], span: Span) -> Self::Result;
+    fn visit_record_expr(&mut self, name: &str, fields: &[FieldInit], span: Span) -> Self::Result;
+    fn visit_field_expr(&mut self, expr: &Expr, field: &str, span: Span) -> Self::Result;
+    fn visit_variant_expr(&mut self, union: &str, variant: &str, fields: &[FieldInit], span: Span) -> Self::Result;
+    fn visit_match_expr(&mut self, subject: &Expr, arms: &[MatchArm<Expr>], span: Span) -> Self::Result;
+    fn visit_anonymous_record_expr(&mut self, fields: &[FieldInit], span: Span) -> Self::Result;
+    fn visit_anonymous_variant_expr(&mut self, variant: &str, fields: &[FieldInit], span: Span) -> Self::Result;
+    fn visit_method_expr(&mut self, trait_name: &str, type_name: &str, method: &str, span: Span) -> Self::Result;
 }
 
 impl AcceptExprVisitor for Expr {
     fn accept_expr_visitor<V: ExprVisitor>(&self, visitor: &mut V) -> V::Result {
         use ExprKind::*;
         match &self.kind {
-            Binary(op, lhs, rhs) => visitor.visit_binary_expr(op, lhs, rhs),
-            Unary(op, expr) => visitor.visit_unary_expr(op, expr),
-            Literal(literal) => visitor.visit_literal_expr(literal),
-            Variable(identifier) => visitor.visit_variable_expr(identifier),
-            
+            Binary(op, lhs, rhs) => visitor.visit_binary_expr(op, lhs, rhs, self.span),
+            Unary(op, expr) => visitor.visit_unary_expr(op, expr, self.span),
+            Literal(literal) => visitor.visit_literal_expr(literal, self.span),
+            Interpolated(parts) => visitor.visit_interpolated_expr(parts, self.span),
+            Variable(identifier) => visitor.visit_variable_expr(identifier, self.span),
+            If(condition, then_branch, else_branch) => visitor.visit_if_expr(condition, then_branch, else_branch, self.span),
+            Block(statements, value) => visitor.visit_block_expr(statements, value, self.span),
+            Range(start, end, kind) => visitor.visit_range_expr(start, end, *kind, self.span),
+            Call(callee, args) => visitor.visit_call_expr(callee, args, self.span),
+            Record(name, fields) => visitor.visit_record_expr(name, fields, self.span),
+            Field(expr, field) => visitor.visit_field_expr(expr, field, self.span),
+            Variant(union, variant, fields) => visitor.visit_variant_expr(union, variant, fields, self.span),
+            Match(subject, arms) => visitor.visit_match_expr(subject, arms, self.span),
+            AnonymousRecord(fields) => visitor.visit_anonymous_record_expr(fields, self.span),
+            AnonymousVariant(variant, fields) => visitor.visit_anonymous_variant_expr(variant, fields, self.span),
+            Method(trait_name, type_name, method) => visitor.visit_method_expr(trait_name, type_name, method, self.span),
         }
     }
 }
@@ -34,22 +58,42 @@ pub trait AcceptStmtVisitor {
 pub trait StmtVisitor {
     type Result;
 
-    fn visit_expr_stmt(&mut self, expr: &Expr) -> Self::Result;
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Self::Result;
-    fn visit_println_stmt(&mut self, expr: &Expr) -> Self::Result;
-    fn visit_decl_stmt(&mut self, decl: &Decl) -> Self::Result;
-    fn visit_assign_stmt(&mut self, identifier: &String, expr: &Expr) -> Self::Result;
+    fn visit_expr_stmt(&mut self, expr: &Expr, span: Span) -> Self::Result;
+    fn visit_print_stmt(&mut self, expr: &Expr, span: Span) -> Self::Result;
+    fn visit_println_stmt(&mut self, expr: &Expr, span: Span) -> Self::Result;
+    fn visit_decl_stmt(&mut self, decl: &Decl, span: Span) -> Self::Result;
+    fn visit_assign_stmt(&mut self, identifier: &str, expr: &Expr, span: Span) -> Self::Result;
+    fn visit_field_assign_stmt(&mut self, identifier: &str, fields: &[String], expr: &Expr, span: Span) -> Self::Result;
+    fn visit_block_stmt(&mut self, statements: &[Stmt], span: Span) -> Self::Result;
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>, span: Span) -> Self::Result;
+    fn visit_for_stmt(&mut self, variable: &str, iterable: &Expr, body: &Stmt, span: Span) -> Self::Result;
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt, span: Span) -> Self::Result;
+    fn visit_break_stmt(&mut self, span: Span) -> Self::Result;
+    fn visit_continue_stmt(&mut self, span: Span) -> Self::Result;
+    fn visit_return_stmt(&mut self, expr: Option<&Expr>, span: Span) -> Self::Result;
+    fn visit_match_stmt(&mut self, subject: &Expr, arms: &[MatchArm<Stmt>], span: Span) -> Self::Result;
 }
 
 impl AcceptStmtVisitor for Stmt {
     fn accept_stmt_visitor<V: StmtVisitor>(&self, visitor: &mut V) -> V::Result {
         use StmtKind::*;
         match &self.kind {
-            Expr(expr) => visitor.visit_expr_stmt(expr),
-            Print(expr) => visitor.visit_print_stmt(expr),
-            Println(expr) => visitor.visit_println_stmt(expr),
-            Decl(decl) => visitor.visit_decl_stmt(decl),
-            Assign(ident, expr) => visitor.visit_assign_stmt(ident, expr),
+            Expr(expr) => visitor.visit_expr_stmt(expr, self.span),
+            Print(expr) => visitor.visit_print_stmt(expr, self.span),
+            Println(expr) => visitor.visit_println_stmt(expr, self.span),
+            Decl(decl) => visitor.visit_decl_stmt(decl, self.span),
+            Assign(ident, expr) => visitor.visit_assign_stmt(ident, expr, self.span),
+            FieldAssign(ident, fields, expr) => visitor.visit_field_assign_stmt(ident, fields, expr, self.span),
+            Block(statements) => visitor.visit_block_stmt(statements, self.span),
+            If(condition, then_branch, else_branch) => {
+                visitor.visit_if_stmt(condition, then_branch, else_branch.as_deref(), self.span)
+            }
+            For(variable, iterable, body) => visitor.visit_for_stmt(variable, iterable, body, self.span),
+            While(condition, body) => visitor.visit_while_stmt(condition, body, self.span),
+            Break => visitor.visit_break_stmt(self.span),
+            Continue => visitor.visit_continue_stmt(self.span),
+            Return(expr) => visitor.visit_return_stmt(expr.as_deref(), self.span),
+            Match(subject, arms) => visitor.visit_match_stmt(subject, arms, self.span),
         }
     }
 }
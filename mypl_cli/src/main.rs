@@ -1,6 +1,7 @@
 extern crate mypl_lex;
 extern crate mypl_ast;
 extern crate mypl_parse;
+extern crate mypl_semantic;
 extern crate mypl_interperter;
 
 extern crate anyhow;
@@ -11,20 +12,24 @@ use std::io::Write;
 use mypl_lex::prelude::*;
 use mypl_ast::prelude::*;
 use mypl_parse::prelude::*;
+use mypl_semantic::prelude::*;
 use mypl_interperter::prelude::*;
 
 use anyhow::Result;
 use clap::Parser as ClapParser;
 use std::path::Path;
 
+// The deepest the cli lets calls nest, the stack of the interpreter grows with the limit
+const MAX_CALL_DEPTH: u64 = 10_000;
+
+// The stack for everything but the calls of the program, the parser and the analyzer included
+const BASE_STACK_SIZE: usize = 8 * 1024 * 1024;
+
 #[derive(ClapParser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    // #[arg(short, long)]
-    // input: Vec<String>,
-    
     #[arg(short, long)]
-    input: Option<String>,
+    input: Vec<String>,
 
     #[arg(short = 'T', long, default_value_t = false)]
     show_tokens: bool,
@@ -37,15 +42,38 @@ struct Args {
 
     #[arg(long, default_value_t = false)]
     interpret: bool,
+
+    /// How deep calls may nest before the interpreter stops, up to 10000
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MAX_CALL_DEPTH,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(0..=MAX_CALL_DEPTH),
+    )]
+    max_call_depth: usize,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let mut interperter = Interperter::new();
 
-    if let Some(input) = &args.input {
-        let content = read_file(Path::new(&input))?;
-        execute(&mut interperter, &content, &args);
+    // The interpreter recurses on the native stack, so it runs on a thread with room for the deepest calls
+    let stack_size = BASE_STACK_SIZE + args.max_call_depth * CALL_STACK_SIZE;
+    let cli = std::thread::Builder::new().stack_size(stack_size).spawn(move || run(args))?;
+    match cli.join() {
+        Ok(result) => result,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+fn run(args: Args) -> Result<()> {
+    let mut analyzer = SemanticAnalyzer::new();
+    let mut interperter = Interperter::with_max_call_depth(args.max_call_depth);
+    let mut source_map = SourceMap::new();
+
+    if !args.input.is_empty() {
+        for input in &args.input {
+            let content = read_file(Path::new(&input))?;
+            execute(&mut analyzer, &mut interperter, &mut source_map, input, &content, &args);
+        }
     } else {
         loop {
             print!("> ");
@@ -58,55 +86,93 @@ fn main() -> Result<()> {
                 break;
             }
 
-            execute(&mut interperter, &content, &args);
+            execute(&mut analyzer, &mut interperter, &mut source_map, "<stdin>", &content, &args);
         }
     }
 
     Ok(())
 }
 
-fn execute(interperter: &mut Interperter, content: &str, args: &Args) {
-    let mut normalized_content = content.trim().to_string();
-
-    if !args.disable_auto_semicolon && !normalized_content.ends_with(";") {
-        normalized_content = format!("{};", content);
+fn execute(
+    analyzer: &mut SemanticAnalyzer,
+    interperter: &mut Interperter,
+    source_map: &mut SourceMap,
+    name: &str,
+    content: &str,
+    args: &Args,
+) {
+    // Only trim the end, so spans still point to the original lines and columns
+    let mut normalized_content = content.trim_end().to_string();
+
+    // Blocks end with a brace, not a semicolon
+    if !args.disable_auto_semicolon && !normalized_content.ends_with([';', '}']) {
+        normalized_content.push(';');
     }
 
-    let mut tokenizer = Tokenizer::new(&normalized_content);
+    let file = source_map.add_file(name, normalized_content.as_str());
+    let mut tokenizer = Tokenizer::with_file(&normalized_content, file);
     let mut tokens = Vec::new();
 
-    while let Some(token) = tokenizer.next_token() {
+    for token in tokenizer.by_ref() {
         if args.show_tokens {
             println!("\ttoken: {:#?}", token);
         }
 
-        match token.kind {
-            TokenKind::Comment(..) => continue,
-            _ => tokens.push(token),
+        tokens.push(token);
+    }
+
+    if !tokenizer.errors().is_empty() {
+        for lex_error in tokenizer.errors() {
+            report(source_map, lex_error.span(), "LexError", lex_error);
         }
+        return;
     }
 
-    let mut parser = RecursiveDescentParser::new(&tokens);
+    let mut parser = RecursiveDescentParser::new(tokens);
 
-    match parser.parse() {
-        Err(parse_error) => println!("ParseErrror - {}", parse_error),
-        Ok(statements) => {
-            if args.show_ast {
-                println!("{}", AstFormatter::format_ast(&statements));
-            }
+    let mut output = parser.parse();
 
-            if args.interpret {
-                for stmt in statements {
-                    match interperter.interpret_stmt(&stmt) {
-                        Err(err) => println!("InterperterError - {}", err),
-                        _ => {},
-                    }
-                }
+    for parse_error in &output.errors {
+        report(source_map, parse_error.span(), "ParseError", parse_error);
+    }
+
+    if args.show_ast {
+        println!("{}", AstFormatter::format_ast(&output.statements));
+    }
+
+    // The AST is partial when there are syntax errors, so it is neither checked nor run
+    if output.has_errors() {
+        return;
+    }
+
+    let mut has_semantic_errors = false;
+    for semantic_error in analyzer.analyze(&mut output.statements) {
+        let kind = if semantic_error.is_warning() { "SemanticWarning" } else { "SemanticError" };
+        has_semantic_errors |= !semantic_error.is_warning();
+        report(source_map, semantic_error.span(), kind, &semantic_error);
+    }
+
+    if args.interpret && !has_semantic_errors {
+        // The types of the program are known to all of its statements, like in the semantic analysis
+        if let Err(err) = interperter.declare_types(&output.statements) {
+            report(source_map, err.span(), "InterperterError", &err);
+            return;
+        }
+        for stmt in output.statements {
+            if let Err(err) = interperter.interpret_stmt(&stmt) {
+                report(source_map, err.span(), "InterperterError", &err);
             }
         }
     }
 }
 
+fn report(source_map: &SourceMap, span: &Span, kind: &str, error: &dyn std::fmt::Display) {
+    println!("{}: {} - {}", source_map.format_span(span), kind, error);
+    if let Some(snippet) = source_map.format_snippet(span) {
+        println!("{}", snippet);
+    }
+}
+
 fn read_file(path: impl AsRef<Path>) -> Result<String> {
     std::fs::read_to_string(path.as_ref()).map_err(anyhow::Error::from)
 }
@@ -1,81 +1,200 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
 use anyhow::Result;
+use mypl_ast::prelude::{RecordDecl, Type, UnionDecl};
+use mypl_lex::prelude::Span;
 
-use crate::{expr_eval::Value, prelude::InterperterError};
+use crate::{expr_eval::Value, function::Closure, prelude::InterperterError, type_check::{check_type, Types}};
 
 use crate::symbol::*;
-use InterperterError::{ImmutableAssignment, SymbolNotFound};
+use InterperterError::{
+    ImmutableAssignment, NotARecordType, NotAUnion, NotAValue, SymbolNotFound, UnknownType
+};
 
-pub(crate) struct Environment<'a> {
-    parent: Option<&'a mut Environment<'a>>,
+/// The symbols of a scope. Lookups and assignments that miss go on to the enclosing scope,
+/// which is shared with every scope nested in it.
+pub(crate) struct Environment {
+    parent: Option<Rc<RefCell<Environment>>>,
     symbols: SymbolTable,
+
+    /// The methods of the impls declared in the scope, by the trait and the type they are for
+    impls: HashMap<(String, String), Vec<Rc<Closure>>>,
 }
 
-impl<'a> Default for Environment<'a> {
+impl Default for Environment {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<'a> Environment<'a> {
+impl Environment {
     pub fn new() -> Self {
         Self {
             parent: None,
             symbols: Default::default(),
+            impls: Default::default(),
+        }
+    }
+
+    /// A scope nested in `parent`
+    pub fn with_parent(parent: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            parent: Some(parent),
+            symbols: Default::default(),
+            impls: Default::default(),
         }
     }
 
+    /// The value is checked against the type, when there is one
     pub fn define_variable(
-        &mut self, name: &str, mutability: Mutability, value: Value
+        &mut self, name: &str, mutability: Mutability, ty: Option<Type>, value: Value, span: Span
     ) -> Result<(), InterperterError> {
+        if let Some(ty) = &ty {
+            self.check_value(ty, &value, span)?;
+        }
+
         // We don't care if a parent has that symbol because we want to support shadowing
         self.symbols.insert(name, Symbol {
             mutability,
+            ty,
             kind: SymbolKind::Variable(value),
-        })?;
+        }, span)?;
         Ok(())
     }
 
+    /// Types are declared like constants, and shadowed like them
+    pub fn define_type(&mut self, name: &str, ty: NamedType, span: Span) -> Result<(), InterperterError> {
+        self.symbols.insert(name, Symbol {
+            mutability: Mutability::Immutable,
+            ty: None,
+            kind: SymbolKind::Type(ty),
+        }, span)
+    }
+
+    /// An impl in a nested scope shadows the one of an enclosing scope, the semantic analysis
+    /// reports a trait implemented twice for a type in a scope
+    pub fn define_impl(&mut self, trait_name: &str, type_name: &str, methods: Vec<Rc<Closure>>) {
+        self.impls.insert((trait_name.to_string(), type_name.to_string()), methods);
+    }
+
+    /// The method of the impl of the trait for the type, from the innermost scope that has the impl
+    pub fn get_impl_method(&self, trait_name: &str, type_name: &str, method: &str) -> Option<Rc<Closure>> {
+        match self.impls.get(&(trait_name.to_string(), type_name.to_string())) {
+            Some(methods) => methods.iter().find(|closure| closure.name() == method).cloned(),
+            None => self.parent.as_ref()?.borrow().get_impl_method(trait_name, type_name, method),
+        }
+    }
+
+    /// The value is checked against the annotated type of the variable, in the scope it is declared in
     pub fn assign_to_variable(
-        &mut self, name: &str, new_value: Value
+        &mut self, name: &str, new_value: Value, span: Span
     ) -> Result<(), InterperterError> {
-        let result = self.symbols.update(name, |symbol| match &symbol.mutability {
-            Mutability::Immutable => Err(ImmutableAssignment(name.to_string())),
-            Mutability::Mutable => match &symbol.kind {
-                SymbolKind::Variable(..) => {
-                    symbol.kind = SymbolKind::Variable(new_value.clone());
-                    return Ok(());
-                },
-            },
-        });
+        let symbol = match (self.symbols.get(name, span), &self.parent) {
+            (Ok(symbol), _) => symbol,
+            (Err(SymbolNotFound(..)), Some(parent)) => return parent.borrow_mut().assign_to_variable(name, new_value, span),
+            (Err(err), _) => return Err(err),
+        };
 
-        match result {
-            Err(SymbolNotFound(..)) => {
-                if let Some(parent) = self.parent.as_mut() {
-                    return parent.assign_to_variable(name, new_value);
-                }
+        if symbol.mutability == Mutability::Immutable {
+            return Err(ImmutableAssignment(name.to_string(), span));
+        }
+        if let Some(ty) = &symbol.ty {
+            self.check_value(ty, &new_value, span)?;
+        }
+
+        self.symbols.update(name, span, |symbol| {
+            symbol.kind = SymbolKind::Variable(new_value);
+            Ok(())
+        })
+    }
+
+    /// Checks the value against the type, the records it names are the ones this scope sees
+    pub fn check_value(&self, ty: &Type, value: &Value, span: Span) -> Result<(), InterperterError> {
+        check_type(ty, value, span, self)
+    }
+
+    pub fn get_type(&self, name: &str, span: Span) -> Result<NamedType, InterperterError> {
+        match self.symbols.get(name, span) {
+            Ok(symbol) => match &symbol.kind {
+                SymbolKind::Type(ty) => Ok(ty.clone()),
+                SymbolKind::Variable(..) => Err(UnknownType(name.to_string(), span)),
             },
-            _ => {},
-        };
+            Err(SymbolNotFound(..)) => match &self.parent {
+                Some(parent) => parent.borrow().get_type(name, span),
+                None => Err(UnknownType(name.to_string(), span)),
+            },
+            Err(err) => Err(err),
+        }
+    }
 
-        Ok(())
+    pub fn get_record(&self, name: &str, span: Span) -> Result<Rc<RecordDecl>, InterperterError> {
+        match self.get_type(name, span)? {
+            NamedType::Record(record) => Ok(record),
+            NamedType::Union(..) | NamedType::Trait => Err(NotARecordType(name.to_string(), span)),
+        }
+    }
+
+    pub fn get_union(&self, name: &str, span: Span) -> Result<Rc<UnionDecl>, InterperterError> {
+        match self.get_type(name, span)? {
+            NamedType::Union(union) => Ok(union),
+            NamedType::Record(..) | NamedType::Trait => Err(NotAUnion(name.to_string(), span)),
+        }
     }
 
-    pub fn get_variable_value(&self, name: &str) -> Result<&Value, InterperterError> {
-        match self.symbols.get(name) {
+    pub fn get_variable_value(&self, name: &str, span: Span) -> Result<Value, InterperterError> {
+        match self.symbols.get(name, span) {
             Ok(symbol) => match &symbol.kind {
-                SymbolKind::Variable(value) => Ok(&value),
+                SymbolKind::Variable(value) => Ok(value.clone()),
+                SymbolKind::Type(..) => Err(NotAValue(name.to_string(), span)),
             }
-            Err(SymbolNotFound(err)) => {
+            Err(SymbolNotFound(name, span)) => {
                 // delegate to parent
                 if let Some(parent) = &self.parent {
-                    return parent.get_variable_value(name);
+                    parent.borrow().get_variable_value(&name, span)
                 } else {
                     // Return the original error
-                    return Err(SymbolNotFound(err));
+                    Err(SymbolNotFound(name, span))
                 }
-                
             },
             Err(err) => Err(err),
         }
     }
 }
+
+impl Types for Environment {
+    fn get_type(&self, name: &str, span: Span) -> Result<NamedType, InterperterError> {
+        Environment::get_type(self, name, span)
+    }
+
+    fn has_impl(&self, trait_name: &str, type_name: &str) -> bool {
+        let key = (trait_name.to_string(), type_name.to_string());
+        self.impls.contains_key(&key) || self.parent.as_ref().is_some_and(|parent| parent.borrow().has_impl(trait_name, type_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_scopes() {
+        let span = Span::default();
+        let global = Rc::new(RefCell::new(Environment::new()));
+        global.borrow_mut().define_variable("x", Mutability::Mutable, None, Value::Integer(1), span).unwrap();
+        global.borrow_mut().define_variable("y", Mutability::Immutable, None, Value::Integer(2), span).unwrap();
+
+        let mut inner = Environment::with_parent(global.clone());
+
+        // Assignments reach the enclosing scope
+        inner.assign_to_variable("x", Value::Integer(3), span).unwrap();
+        assert_eq!(global.borrow().get_variable_value("x", span).unwrap(), Value::Integer(3));
+        assert!(matches!(inner.assign_to_variable("y", Value::Integer(3), span), Err(ImmutableAssignment(..))));
+        assert!(matches!(inner.assign_to_variable("z", Value::Integer(3), span), Err(SymbolNotFound(..))));
+
+        // Shadowing leaves the outer variable alone
+        inner.define_variable("y", Mutability::Mutable, None, Value::Bool(true), span).unwrap();
+        inner.assign_to_variable("y", Value::Bool(false), span).unwrap();
+        assert_eq!(inner.get_variable_value("y", span).unwrap(), Value::Bool(false));
+        assert_eq!(global.borrow().get_variable_value("y", span).unwrap(), Value::Integer(2));
+    }
+}
@@ -1,34 +1,154 @@
 use mypl_ast::prelude::*;
+use mypl_lex::prelude::Span;
 use crate::expr_eval::ValueType;
 
 #[derive(Debug, thiserror::Error)]
 pub enum InterperterError {
     #[error("GenericInterperterError({0})")]
-    Generic(String),
+    Generic(String, Span),
 
     #[error("Binary expression type mismatch: {0:?} != {1:?}")]
-    BinaryExprTypeMismatch(ValueType, ValueType),
+    BinaryExprTypeMismatch(ValueType, ValueType, Span),
 
     #[error("Cannot apply binary operator \"{0}\" on types \"{1:?}\" and \"{2:?}\"")]
-    InvalidBinaryApplication(BinOp, ValueType, ValueType),
+    InvalidBinaryApplication(BinOp, ValueType, ValueType, Span),
 
     #[error("Cannot apply unary operator \"{0}\" on type \"{1:?}\"")]
-    InvalidUnaryApplication(UnOp, ValueType),
+    InvalidUnaryApplication(UnOp, ValueType, Span),
+
+    #[error("Division by zero")]
+    DivisionByZero(Span),
+
+    #[error("Integer overflow, the result does not fit in 128 bits")]
+    IntegerOverflow(Span),
+
+    #[error("Cannot shift by {0}, the amount must be between 0 and 127")]
+    InvalidShift(i128, Span),
+
+    #[error("Condition must be of type \"Bool\", found \"{0:?}\"")]
+    InvalidCondition(ValueType, Span),
+
+    #[error("Range bounds must be of type \"Integer\", found \"{0:?}\" and \"{1:?}\"")]
+    InvalidRange(ValueType, ValueType, Span),
+
+    #[error("Cannot iterate over a value of type \"{0:?}\"")]
+    NotIterable(ValueType, Span),
+
+    #[error("Cannot call a value of type \"{0:?}\"")]
+    NotCallable(ValueType, Span),
+
+    #[error("Function \"{0}\" takes {1} arguments, but {2} were given")]
+    ArityMismatch(String, usize, usize, Span),
+
+    #[error("Calls are nested deeper than the limit of {0}")]
+    CallDepthExceeded(usize, Span),
+
+    #[error("Expected a value of type \"{0}\", found \"{1:?}\"")]
+    TypeMismatch(Type, ValueType, Span),
+
+    #[error("{0} does not fit in \"{1}\"")]
+    ValueOutOfRange(String, Type, Span),
+
+    #[error("Type \"{0}\" not found")]
+    UnknownType(String, Span),
+
+    #[error("\"{0}\" is a type, not a value")]
+    NotAValue(String, Span),
+
+    #[error("Type \"{0}\" is not a record")]
+    NotARecordType(String, Span),
+
+    #[error("Type \"{0}\" is not a union")]
+    NotAUnion(String, Span),
+
+    #[error("Union \"{0}\" has no variant \"{1}\"")]
+    UnknownVariant(String, String, Span),
+
+    #[error("No pattern matches {0}")]
+    NoMatchingArm(String, Span),
+
+    #[error("Record \"{0}\" has no field \"{1}\"")]
+    UnknownField(String, String, Span),
+
+    #[error("Record \"{0}\" is missing the field \"{1}\"")]
+    MissingField(String, String, Span),
+
+    #[error("Field \"{0}\" is given more than once")]
+    DuplicateField(String, Span),
+
+    #[error("Variant \"{0}\" is declared more than once")]
+    DuplicateVariant(String, Span),
+
+    #[error("Type \"{0}\" is not a trait")]
+    NotATrait(String, Span),
+
+    #[error("Method \"{0}\" has no parameter of type \"Self\" to dispatch on")]
+    MethodWithoutSelf(String, Span),
+
+    #[error("Method \"{0}\" is called without the semantic analysis, which resolves it to the method of an impl")]
+    UnresolvedMethod(String, Span),
+
+    #[error("Cannot access a field of a value of type \"{0:?}\"")]
+    NotARecord(ValueType, Span),
+
+    #[error("Cannot infer the type of an anonymous literal, it has to be the value of a field of a record or union type")]
+    CannotInferType(Span),
 
     #[error("Environment value named \"{0}\" is not found")]
-    EnvironmentValueNotFound(String),
+    EnvironmentValueNotFound(String, Span),
 
     #[error("Environment already contains a value named \"{0}\"")]
-    EnvironmentValueAlreadyExists(String),
+    EnvironmentValueAlreadyExists(String, Span),
 
     #[error("Cannot assign to an immutable variable \"{0}\"")]
-    ImmutableAssignment(String),
+    ImmutableAssignment(String, Span),
 
     #[error("Symbol \"{0}\" not found")]
-    SymbolNotFound(String),
+    SymbolNotFound(String, Span),
 
     #[error("Symbol \"{0}\" already exists")]
-    SymbolAlreadyExists(String),
+    SymbolAlreadyExists(String, Span),
 }
 
-
+impl InterperterError {
+    pub fn span(&self) -> &Span {
+        use InterperterError::*;
+        match self {
+            Generic(_, span) => span,
+            BinaryExprTypeMismatch(_, _, span) => span,
+            InvalidBinaryApplication(_, _, _, span) => span,
+            InvalidUnaryApplication(_, _, span) => span,
+            DivisionByZero(span) => span,
+            IntegerOverflow(span) => span,
+            InvalidShift(_, span) => span,
+            InvalidCondition(_, span) => span,
+            InvalidRange(_, _, span) => span,
+            NotIterable(_, span) => span,
+            NotCallable(_, span) => span,
+            ArityMismatch(_, _, _, span) => span,
+            CallDepthExceeded(_, span) => span,
+            TypeMismatch(_, _, span) => span,
+            ValueOutOfRange(_, _, span) => span,
+            UnknownType(_, span) => span,
+            NotAValue(_, span) => span,
+            NotARecordType(_, span) => span,
+            NotAUnion(_, span) => span,
+            UnknownVariant(_, _, span) => span,
+            NoMatchingArm(_, span) => span,
+            UnknownField(_, _, span) => span,
+            MissingField(_, _, span) => span,
+            DuplicateField(_, span) => span,
+            DuplicateVariant(_, span) => span,
+            NotATrait(_, span) => span,
+            MethodWithoutSelf(_, span) => span,
+            UnresolvedMethod(_, span) => span,
+            NotARecord(_, span) => span,
+            CannotInferType(span) => span,
+            EnvironmentValueNotFound(_, span) => span,
+            EnvironmentValueAlreadyExists(_, span) => span,
+            ImmutableAssignment(_, span) => span,
+            SymbolNotFound(_, span) => span,
+            SymbolAlreadyExists(_, span) => span,
+        }
+    }
+}
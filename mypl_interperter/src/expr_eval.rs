@@ -1,22 +1,120 @@
-use mypl_ast::prelude::BinOp;
+use std::rc::Rc;
 
-use crate::prelude::InterperterError;
+use mypl_ast::prelude::{BinOp, RangeKind};
+use mypl_lex::prelude::Span;
+
+use crate::{function::{Closure, Method}, prelude::InterperterError};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
     String(String),
+    Char(char),
     Float(f64),
     Integer(i128),
     Bool(bool),
+    Range(i128, i128, RangeKind),
+    Function(Rc<Closure>),
+
+    /// A method of a trait, `area` of `Shape = trait { area: (Self) f32 }`
+    Method(Rc<Method>),
+    Record(Record),
+    Variant(Variant),
+
+    /// What a function without a return value yields
+    Unit,
+}
+
+/// A record value, its fields are in the order of the declaration of its type.
+/// Records are values, assigning one copies it, and they are equal when their fields are.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Record {
+    /// The name of the type, None for a record of an anonymous type
+    pub name: Option<String>,
+    pub fields: Vec<(String, Value)>,
+}
+
+impl Record {
+    /// The name of the type in errors
+    pub fn type_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("record")
+    }
+
+    pub fn get(&self, field: &str) -> Option<&Value> {
+        self.fields.iter().find(|(name, _)| name == field).map(|(_, value)| value)
+    }
+
+    pub fn get_mut(&mut self, field: &str) -> Option<&mut Value> {
+        self.fields.iter_mut().find(|(name, _)| name == field).map(|(_, value)| value)
+    }
+}
+
+// The literal that makes the record, `Point { x = 3, y = 4 }`, or `{ u = 12, v = 32 }` without a name
+impl std::fmt::Display for Record {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "{} ", name)?;
+        }
+        write_fields(f, &self.fields)
+    }
+}
+
+/// A variant of a union along with its fields, which are in the order of the declaration
+#[derive(Debug, PartialEq, Clone)]
+pub struct Variant {
+    /// The name of the union, None for a variant of an anonymous union
+    pub union: Option<String>,
+    pub name: String,
+    pub fields: Vec<(String, Value)>,
+}
+
+// `Color.Red`, or `Color.RGB { red = 128, green = 0, blue = 0 }` with fields, and `.Dot` without a union name
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.union.as_deref().unwrap_or_default(), self.name)?;
+        if self.fields.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str(" ")?;
+        write_fields(f, &self.fields)
+    }
+}
+
+// The fields are written like in a literal, so strings and chars in them are quoted
+fn write_fields(f: &mut std::fmt::Formatter<'_>, fields: &[(String, Value)]) -> std::fmt::Result {
+    let fields = fields
+        .iter()
+        .map(|(name, value)| match value {
+            Value::String(val) => format!("{} = {:?}", name, val),
+            Value::Char(val) => format!("{} = {:?}", name, val),
+            value => format!("{} = {}", name, value),
+        })
+        .collect::<Vec<_>>();
+    write!(f, "{{ {} }}", fields.join(", "))
 }
 
 impl Value {
     pub fn get_type(&self) -> ValueType {
         match self {
             Value::String(_) => ValueType::String, 
+            Value::Char(_) => ValueType::Char,
             Value::Float(_) => ValueType::Float,
             Value::Integer(_) => ValueType::Integer,
             Value::Bool(_) => ValueType::Bool,
+            Value::Range(..) => ValueType::Range,
+            Value::Function(..) | Value::Method(..) => ValueType::Function,
+            Value::Record(..) => ValueType::Record,
+            Value::Variant(..) => ValueType::Variant,
+            Value::Unit => ValueType::Unit,
+        }
+    }
+
+    /// The name of the declared type of a record or a variant, the type the impls of traits are for
+    pub fn named_type(&self) -> Option<&str> {
+        match self {
+            Value::Record(record) => record.name.as_deref(),
+            Value::Variant(variant) => variant.union.as_deref(),
+            _ => None,
         }
     }
 
@@ -26,6 +124,13 @@ impl Value {
             _ => panic!("attempted to unwrap_string, but is: {:?}", self),
         }
     }
+
+    pub fn unwrap_char(&self) -> &char {
+        match self {
+            Value::Char(val) => val,
+            _ => panic!("attempted to unwrap_char, but is: {:?}", self),
+        }
+    }
     
     pub fn unwrap_float(&self) -> &f64 {
         match self {
@@ -49,46 +154,105 @@ impl Value {
     }
 }
 
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::String(val) => write!(f, "{}", val),
+            Value::Char(val) => write!(f, "{}", val),
+            Value::Float(val) => write!(f, "{}", val),
+            Value::Integer(val) => write!(f, "{}", val),
+            Value::Bool(val) => write!(f, "{}", val),
+            Value::Range(start, end, kind) => write!(f, "{}{}{}", start, kind.as_code(), end),
+            Value::Function(closure) => write!(f, "{}", closure),
+            Value::Method(method) => write!(f, "{}", method),
+            Value::Record(record) => write!(f, "{}", record),
+            Value::Variant(variant) => write!(f, "{}", variant),
+            Value::Unit => write!(f, "()"),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum ValueType {
     String,
+    Char,
     Float,
     Integer,
     Bool,
+    Range,
+    Function,
+    Record,
+    Variant,
+    Unit,
 }
 
 pub(crate) struct ExprEval;
 
 impl ExprEval {
-    pub fn bin_string_string(op: &BinOp, lhs: &Value, rhs: &Value) -> Result<Value, InterperterError> {
+    pub fn bin_string_string(op: &BinOp, lhs: &Value, rhs: &Value, span: Span) -> Result<Value, InterperterError> {
         let lhs = lhs.unwrap_string();
         let rhs = rhs.unwrap_string();
         match op {
             BinOp::Eq => Ok(Value::Bool(lhs == rhs)),
             BinOp::Ne => Ok(Value::Bool(lhs != rhs)),
-            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::String, ValueType::String)),
+            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::String, ValueType::String, span)),
         }
     }
 
-    pub fn bin_integer_integer(op: &BinOp, lhs: &Value, rhs: &Value) -> Result<Value, InterperterError> {
+    pub fn bin_char_char(op: &BinOp, lhs: &Value, rhs: &Value, span: Span) -> Result<Value, InterperterError> {
+        let lhs = lhs.unwrap_char();
+        let rhs = rhs.unwrap_char();
+        match op {
+            BinOp::Eq => Ok(Value::Bool(lhs == rhs)),
+            BinOp::Lt => Ok(Value::Bool(lhs < rhs)),
+            BinOp::Le => Ok(Value::Bool(lhs <= rhs)),
+            BinOp::Ne => Ok(Value::Bool(lhs != rhs)),
+            BinOp::Ge => Ok(Value::Bool(lhs >= rhs)),
+            BinOp::Gt => Ok(Value::Bool(lhs > rhs)),
+            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::Char, ValueType::Char, span)),
+        }
+    }
+
+    pub fn bin_integer_integer(op: &BinOp, lhs: &Value, rhs: &Value, span: Span) -> Result<Value, InterperterError> {
         let lhs = lhs.unwrap_integer();
         let rhs = rhs.unwrap_integer();
         match op {
-            BinOp::Add => Ok(Value::Integer(lhs + rhs)),
-            BinOp::Sub => Ok(Value::Integer(lhs - rhs)),
-            BinOp::Mul => Ok(Value::Integer(lhs * rhs)),
+            BinOp::Add => Self::checked(lhs.checked_add(*rhs), span),
+            BinOp::Sub => Self::checked(lhs.checked_sub(*rhs), span),
+            BinOp::Mul => Self::checked(lhs.checked_mul(*rhs), span),
             BinOp::Div => Ok(Value::Float(*lhs as f64 / *rhs as f64)),
+            // The only other remainder that overflows is `i128::MIN % -1`
+            BinOp::Rem if *rhs == 0 => Err(InterperterError::DivisionByZero(span)),
+            BinOp::Rem => Self::checked(lhs.checked_rem(*rhs), span),
+            BinOp::BitAnd => Ok(Value::Integer(lhs & rhs)),
+            BinOp::BitOr => Ok(Value::Integer(lhs | rhs)),
+            BinOp::BitXor => Ok(Value::Integer(lhs ^ rhs)),
+            BinOp::Shl => Self::shift_amount(*rhs, span).map(|amount| Value::Integer(lhs << amount)),
+            BinOp::Shr => Self::shift_amount(*rhs, span).map(|amount| Value::Integer(lhs >> amount)),
             BinOp::Eq => Ok(Value::Bool(lhs == rhs)),
             BinOp::Lt => Ok(Value::Bool(lhs < rhs)),
             BinOp::Le => Ok(Value::Bool(lhs <= rhs)),
             BinOp::Ne => Ok(Value::Bool(lhs != rhs)),
-            BinOp::Ge => Ok(Value::Bool(lhs > rhs)),
-            BinOp::Gt => Ok(Value::Bool(lhs >= rhs)),
-            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::Integer, ValueType::Integer)),
+            BinOp::Ge => Ok(Value::Bool(lhs >= rhs)),
+            BinOp::Gt => Ok(Value::Bool(lhs > rhs)),
+            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::Integer, ValueType::Integer, span)),
         }
     }
 
-    pub fn bin_float_float(op: &BinOp, lhs: &Value, rhs: &Value) -> Result<Value, InterperterError> {
+    // An integer that does not fit in 128 bits is an error
+    pub(crate) fn checked(result: Option<i128>, span: Span) -> Result<Value, InterperterError> {
+        result.map(Value::Integer).ok_or(InterperterError::IntegerOverflow(span))
+    }
+
+    // Shifting by the width of the integer or more is an error, like shifting by a negative amount
+    fn shift_amount(amount: i128, span: Span) -> Result<u32, InterperterError> {
+        u32::try_from(amount)
+            .ok()
+            .filter(|amount| *amount < i128::BITS)
+            .ok_or(InterperterError::InvalidShift(amount, span))
+    }
+
+    pub fn bin_float_float(op: &BinOp, lhs: &Value, rhs: &Value, span: Span) -> Result<Value, InterperterError> {
         let lhs = lhs.unwrap_float();
         let rhs = rhs.unwrap_float();
         match op {
@@ -96,27 +260,70 @@ impl ExprEval {
             BinOp::Sub => Ok(Value::Float(lhs - rhs)),
             BinOp::Mul => Ok(Value::Float(lhs * rhs)),
             BinOp::Div => Ok(Value::Float(lhs / rhs)),
+            BinOp::Rem => Ok(Value::Float(lhs % rhs)),
             BinOp::Eq => Ok(Value::Bool(lhs == rhs)),
             BinOp::Lt => Ok(Value::Bool(lhs < rhs)),
             BinOp::Le => Ok(Value::Bool(lhs <= rhs)),
             BinOp::Ne => Ok(Value::Bool(lhs != rhs)),
-            BinOp::Ge => Ok(Value::Bool(lhs > rhs)),
-            BinOp::Gt => Ok(Value::Bool(lhs >= rhs)),
-            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::Float, ValueType::Float)),
+            BinOp::Ge => Ok(Value::Bool(lhs >= rhs)),
+            BinOp::Gt => Ok(Value::Bool(lhs > rhs)),
+            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::Float, ValueType::Float, span)),
         }
     }
 
-    pub fn bin_bool_bool(op: &BinOp, lhs: &Value, rhs: &Value) -> Result<Value, InterperterError> {
+    pub fn bin_bool_bool(op: &BinOp, lhs: &Value, rhs: &Value, span: Span) -> Result<Value, InterperterError> {
         let lhs = *lhs.unwrap_bool();
         let rhs = *rhs.unwrap_bool();
         match op {
             BinOp::And => Ok(Value::Bool(lhs && rhs)),
             BinOp::Or  => Ok(Value::Bool(lhs || rhs)),
+            BinOp::BitAnd => Ok(Value::Bool(lhs & rhs)),
+            BinOp::BitOr => Ok(Value::Bool(lhs | rhs)),
+            BinOp::BitXor => Ok(Value::Bool(lhs ^ rhs)),
             BinOp::Eq  => Ok(Value::Bool(lhs == rhs)),
             BinOp::Ne  => Ok(Value::Bool(lhs != rhs)),
-            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::Bool, ValueType::Bool)),
+            _ => Err(InterperterError::InvalidBinaryApplication(*op, ValueType::Bool, ValueType::Bool, span)),
+        }
+    }
+
+    // For the types that are only compared, a function is only equal to itself, and records and
+    // variants are compared field by field
+    pub fn bin_equality(op: &BinOp, lhs: &Value, rhs: &Value, span: Span) -> Result<Value, InterperterError> {
+        match op {
+            BinOp::Eq => Ok(Value::Bool(lhs == rhs)),
+            BinOp::Ne => Ok(Value::Bool(lhs != rhs)),
+            _ => Err(InterperterError::InvalidBinaryApplication(*op, lhs.get_type(), rhs.get_type(), span)),
         }
     }
 }
 
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mypl_ast::prelude::{Expr, ExprKind, UnOp};
+    use mypl_lex::prelude::Literal;
+    use crate::interperter::Interperter;
+
+    fn integer(op: BinOp, lhs: i128, rhs: i128) -> Result<Value, InterperterError> {
+        ExprEval::bin_integer_integer(&op, &Value::Integer(lhs), &Value::Integer(rhs), Span::default())
+    }
+
+    #[test]
+    fn report_integer_overflow() {
+        use InterperterError::*;
+        assert!(matches!(integer(BinOp::Add, i128::MAX, 1), Err(IntegerOverflow(..))));
+        assert!(matches!(integer(BinOp::Sub, i128::MIN, 1), Err(IntegerOverflow(..))));
+        assert!(matches!(integer(BinOp::Mul, i128::MAX, 2), Err(IntegerOverflow(..))));
+        assert_eq!(integer(BinOp::Add, i128::MAX - 1, 1).unwrap(), Value::Integer(i128::MAX));
+
+        // The remainder overflows when the quotient does, and a zero divisor is still a division by zero
+        assert!(matches!(integer(BinOp::Rem, i128::MIN, -1), Err(IntegerOverflow(..))));
+        assert!(matches!(integer(BinOp::Rem, i128::MAX, 0), Err(DivisionByZero(..))));
+
+        let literal = Expr { kind: ExprKind::Literal(Literal::Integer(i128::MIN, None)), span: Span::default() };
+        let negated = Expr { kind: ExprKind::Unary(UnOp::Neg, Box::new(literal)), span: Span::default() };
+        assert!(matches!(Interperter::new().evaluate_expr(&negated), Err(IntegerOverflow(..))));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{cell::RefCell, rc::Rc};
+
+use mypl_ast::prelude::{Field, Function, TraitDecl, Type};
+
+use crate::environment::Environment;
+
+/// A function value, the function along with the scope it is declared in.
+/// Its body runs in a scope nested in that one, which is what makes recursion work.
+pub struct Closure {
+    pub(crate) function: Rc<Function>,
+    pub(crate) env: Rc<RefCell<Environment>>,
+}
+
+impl Closure {
+    pub fn name(&self) -> &str {
+        &self.function.name
+    }
+}
+
+// Functions are equal only to themselves
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.function, &other.function) && Rc::ptr_eq(&self.env, &other.env)
+    }
+}
+
+impl std::fmt::Debug for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Closure({})", self.name())
+    }
+}
+
+/// A method of a trait as a value. It is not called itself, the semantic analysis resolves every
+/// call to it to the method of an impl, values carry no methods of their own.
+#[derive(Debug, PartialEq)]
+pub struct Method {
+    pub(crate) trait_decl: Rc<TraitDecl>,
+    pub(crate) index: usize,
+}
+
+impl Method {
+    pub fn name(&self) -> &str {
+        &self.signature().name
+    }
+
+    pub fn trait_name(&self) -> &str {
+        &self.trait_decl.name
+    }
+
+    pub fn ty(&self) -> &Type {
+        &self.signature().ty
+    }
+
+    fn signature(&self) -> &Field {
+        &self.trait_decl.methods[self.index]
+    }
+}
+
+// `Shape.area: (Self) f32`
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}: {}", self.trait_name(), self.name(), self.ty())
+    }
+}
+
+// The signature, `increment: (value: i8) i8`
+impl std::fmt::Display for Closure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let params = self.function.params
+            .iter()
+            .map(|param| format!("{}: {}", param.name, param.ty))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        write!(f, "{}: ({})", self.name(), params)?;
+        if let Some(return_type) = &self.function.return_type {
+            write!(f, " {}", return_type)?;
+        }
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::{cell::RefCell, rc::Rc};
+
 use mypl_lex::prelude::*;
 use mypl_ast::prelude::*;
 
@@ -6,20 +8,55 @@ use anyhow::Result;
 use crate::{
     error::InterperterError,
     expr_eval::{
-        ExprEval, Value, ValueType
+        ExprEval, Record, Value, ValueType, Variant
     },
-    symbol::Mutability,
+    symbol::{Mutability, NamedType},
     environment::Environment,
+    function::{Closure, Method},
 };
 
-pub struct Interperter<'a> {
-    global_env: Environment<'a>,
+/// How deep calls nest by default, before the interpreter gives up on a runaway recursion
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 200;
+
+/// The most native stack a call of the program takes in the interpreter. Running calls as deep as
+/// the limit takes the limit times this, on top of what the program needs around them.
+pub const CALL_STACK_SIZE: usize = 64 * 1024;
+
+/// How running a statement goes on, loops stop at a `break` and go on at a `continue`.
+/// A `return` leaves every statement up to the body of the function.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+pub struct Interperter {
+    // The innermost scope, the global one when no block is running
+    env: Rc<RefCell<Environment>>,
+
+    // How many calls are running, and how many may run
+    call_depth: usize,
+    max_call_depth: usize,
 }
 
-impl<'a> Interperter<'a> {
+impl Default for Interperter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interperter {
     pub fn new() -> Self {
+        Self::with_max_call_depth(DEFAULT_MAX_CALL_DEPTH)
+    }
+
+    pub fn with_max_call_depth(max_call_depth: usize) -> Self {
         Self {
-            global_env: Default::default(),
+            env: Default::default(),
+            call_depth: 0,
+            max_call_depth,
         }
     }
 
@@ -27,122 +64,976 @@ impl<'a> Interperter<'a> {
         expr.accept_expr_visitor(self)
     }
 
+    /// Declares the types, traits and impls of a list of statements before it runs, they are known
+    /// to the whole list like in the semantic analysis
+    pub fn declare_types(&mut self, statements: &[Stmt]) -> Result<(), InterperterError> {
+        for stmt in statements {
+            let StmtKind::Decl(decl) = &stmt.kind else {
+                continue;
+            };
+            let span = stmt.span;
+            match &decl.kind {
+                DeclKind::Record(record) => {
+                    check_unique_fields(&record.fields)?;
+                    let ty = NamedType::Record(Rc::new((**record).clone()));
+                    self.env.borrow_mut().define_type(&record.name, ty, span)?;
+                },
+                DeclKind::Union(union) => {
+                    check_unique_variants(&union.variants)?;
+                    let ty = NamedType::Union(Rc::new((**union).clone()));
+                    self.env.borrow_mut().define_type(&union.name, ty, span)?;
+                },
+                DeclKind::Trait(trait_decl) => {
+                    if let Some(method) = trait_decl.methods.iter().find(|method| method.ty.self_position().is_none()) {
+                        return Err(InterperterError::MethodWithoutSelf(method.name.clone(), method.span));
+                    }
+
+                    // The methods are values in the scope of the trait, the semantic analysis resolves calls to them
+                    let trait_decl = Rc::new((**trait_decl).clone());
+                    let mut env = self.env.borrow_mut();
+                    env.define_type(&trait_decl.name, NamedType::Trait, span)?;
+                    for (index, method) in trait_decl.methods.iter().enumerate() {
+                        let value = Value::Method(Rc::new(Method { trait_decl: trait_decl.clone(), index }));
+                        env.define_variable(&method.name, Mutability::Immutable, None, value, method.span)?;
+                    }
+                },
+                DeclKind::Impl(impl_decl) => {
+                    // The impl is checked against its trait and its type by the semantic analysis
+                    let methods = impl_decl.methods
+                        .iter()
+                        .map(|method| Rc::new(Closure {
+                            function: Rc::new(method.clone()),
+                            env: self.env.clone(),
+                        }))
+                        .collect();
+                    self.env.borrow_mut().define_impl(&impl_decl.trait_name, &impl_decl.type_name, methods);
+                },
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
     pub fn interpret_stmt(&mut self, stmt: &Stmt) -> Result<(), InterperterError> {
-        stmt.accept_stmt_visitor(self)?;
+        // The parser only accepts `break` and `continue` in loops and `return` in functions,
+        // so the flow is always Next here
+        self.execute(stmt)?;
         Ok(())
     }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<Flow, InterperterError> {
+        stmt.accept_stmt_visitor(self)
+    }
+
+    // Runs `f` in `env`, the current scope is restored afterwards, even on errors
+    fn in_scope<T>(
+        &mut self, env: Environment, f: impl FnOnce(&mut Self) -> Result<T, InterperterError>
+    ) -> Result<T, InterperterError> {
+        let previous = std::mem::replace(&mut self.env, Rc::new(RefCell::new(env)));
+        let result = f(self);
+        self.env = previous;
+        result
+    }
+
+    // Stops at the first statement that breaks or continues a loop
+    fn execute_block(&mut self, statements: &[Stmt], env: Environment) -> Result<Flow, InterperterError> {
+        self.in_scope(env, |interperter| {
+            interperter.declare_types(statements)?;
+            for stmt in statements {
+                let flow = interperter.execute(stmt)?;
+                if flow != Flow::Next {
+                    return Ok(flow);
+                }
+            }
+            Ok(Flow::Next)
+        })
+    }
+
+    fn call(&mut self, closure: &Closure, args: Vec<Value>, span: Span) -> Result<Value, InterperterError> {
+        let function = &closure.function;
+        if args.len() != function.params.len() {
+            return Err(InterperterError::ArityMismatch(function.name.clone(), function.params.len(), args.len(), span));
+        }
+
+        if self.call_depth >= self.max_call_depth {
+            return Err(InterperterError::CallDepthExceeded(self.max_call_depth, span));
+        }
+
+        // The call frame, parameters are immutable like every other definition without `var`
+        let mut env = Environment::with_parent(closure.env.clone());
+        for (param, value) in function.params.iter().zip(args) {
+            env.define_variable(&param.name, Mutability::Immutable, Some(param.ty.clone()), value, span)?;
+        }
+
+        self.call_depth += 1;
+        let flow = self.execute_block(&function.body, env);
+        self.call_depth -= 1;
+
+        let value = match flow? {
+            Flow::Return(value) => value,
+            _ => Value::Unit,
+        };
+
+        if let Some(return_type) = &function.return_type {
+            closure.env.borrow().check_value(return_type, &value, span)?;
+        }
+        Ok(value)
+    }
+
+    // Evaluates the fields of a literal of `type_name`, in the order they are written in.
+    // They are returned in the order of the declaration, every field has to be given exactly once.
+    fn evaluate_fields(
+        &mut self, type_name: &str, field_types: &[Field], fields: &[FieldInit], span: Span
+    ) -> Result<Vec<(String, Value)>, InterperterError> {
+        let mut values = vec![None; field_types.len()];
+        for field in fields {
+            let index = field_types
+                .iter()
+                .position(|field_type| field_type.name == field.name)
+                .ok_or_else(|| InterperterError::UnknownField(type_name.to_string(), field.name.clone(), field.span))?;
+            if values[index].is_some() {
+                return Err(InterperterError::DuplicateField(field.name.clone(), field.span));
+            }
+
+            let value = self.evaluate_typed(&field.value, &field_types[index].ty)?;
+            self.env.borrow().check_value(&field_types[index].ty, &value, field.value.span)?;
+            values[index] = Some(value);
+        }
+
+        field_types
+            .iter()
+            .zip(values)
+            .map(|(field_type, value)| match value {
+                Some(value) => Ok((field_type.name.clone(), value)),
+                None => Err(InterperterError::MissingField(type_name.to_string(), field_type.name.clone(), span)),
+            })
+            .collect()
+    }
+
+    // Evaluates a value of the type, of a field or of an annotated declaration, an anonymous literal takes the type
+    fn evaluate_typed(&mut self, expr: &Expr, ty: &Type) -> Result<Value, InterperterError> {
+        match (&expr.kind, ty) {
+            (ExprKind::AnonymousRecord(fields), Type::Named(name)) => self.visit_record_expr(name, fields, expr.span),
+            (ExprKind::AnonymousRecord(fields), Type::Record(field_types)) => {
+                let fields = self.evaluate_fields("record", field_types, fields, expr.span)?;
+                Ok(Value::Record(Record { name: None, fields }))
+            }
+            (ExprKind::AnonymousVariant(variant, fields), Type::Named(name)) => {
+                self.visit_variant_expr(name, variant, fields, expr.span)
+            }
+            (ExprKind::AnonymousVariant(variant, fields), Type::Union(variants)) => {
+                self.make_variant(None, variants, variant, fields, expr.span)
+            }
+            _ => self.evaluate_expr(expr),
+        }
+    }
+
+    // The value of a declaration, an anonymous literal takes the annotated type
+    fn evaluate_initializer(&mut self, expr: &Expr, ty: Option<&Type>) -> Result<Value, InterperterError> {
+        match ty {
+            Some(ty) => self.evaluate_typed(expr, ty),
+            None => self.evaluate_expr(expr),
+        }
+    }
+
+    // A variant of the union named `union`, or of an anonymous union without one
+    fn make_variant(
+        &mut self, union: Option<&str>, variants: &[VariantDecl], variant: &str, fields: &[FieldInit], span: Span
+    ) -> Result<Value, InterperterError> {
+        let variant_type = variants
+            .iter()
+            .find(|variant_type| variant_type.name == variant)
+            .ok_or_else(|| InterperterError::UnknownVariant(union.unwrap_or("union").to_string(), variant.to_string(), span))?;
+
+        let type_name = format!("{}.{}", union.unwrap_or_default(), variant);
+        let fields = self.evaluate_fields(&type_name, &variant_type.fields, fields, span)?;
+        Ok(Value::Variant(Variant {
+            union: union.map(str::to_string),
+            name: variant.to_string(),
+            fields,
+        }))
+    }
+
+    // Whether the value matches the pattern, the names the pattern binds are added to `bindings`
+    fn match_pattern(&self, pattern: &Pattern, value: &Value, bindings: &mut Vec<(String, Value)>) -> Result<bool, InterperterError> {
+        match &pattern.kind {
+            PatternKind::Wildcard => Ok(true),
+            PatternKind::Binding(name) => {
+                bindings.push((name.clone(), value.clone()));
+                Ok(true)
+            }
+            PatternKind::Literal(literal) => Ok(literal_value(literal) == *value),
+            PatternKind::Variant(union, variant, fields) => {
+                // The pattern has to name a variant and its fields, even when the value is another one
+                let union_type = self.env.borrow().get_union(union, pattern.span)?;
+                let variant_type = union_type.variants
+                    .iter()
+                    .find(|variant_type| variant_type.name == *variant)
+                    .ok_or_else(|| InterperterError::UnknownVariant(union.clone(), variant.clone(), pattern.span))?;
+                for field in fields {
+                    if !variant_type.fields.iter().any(|field_type| field_type.name == field.name) {
+                        let type_name = format!("{}.{}", union, variant);
+                        return Err(InterperterError::UnknownField(type_name, field.name.clone(), field.span));
+                    }
+                }
+
+                let Value::Variant(value) = value else {
+                    return Ok(false);
+                };
+                if value.union.as_ref() != Some(union) || value.name != *variant {
+                    return Ok(false);
+                }
+                self.match_fields(fields, value, bindings)
+            }
+            PatternKind::AnonymousVariant(variant, fields) => {
+                let Value::Variant(value) = value else {
+                    return Ok(false);
+                };
+                if value.name != *variant {
+                    return Ok(false);
+                }
+
+                // The fields are only known from the value
+                if let Some(field) = fields.iter().find(|field| value.fields.iter().all(|(name, _)| *name != field.name)) {
+                    let type_name = format!("{}.{}", value.union.as_deref().unwrap_or_default(), variant);
+                    return Err(InterperterError::UnknownField(type_name, field.name.clone(), field.span));
+                }
+                self.match_fields(fields, value, bindings)
+            }
+        }
+    }
+
+    // Whether the fields of the variant match the patterns of the fields
+    fn match_fields(&self, fields: &[FieldPattern], value: &Variant, bindings: &mut Vec<(String, Value)>) -> Result<bool, InterperterError> {
+        for field in fields {
+            let Some((_, field_value)) = value.fields.iter().find(|(name, _)| *name == field.name) else {
+                return Ok(false);
+            };
+            if !self.match_pattern(&field.pattern, field_value, bindings)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    // The first arm whose pattern matches the value, along with the scope of the names it binds
+    fn match_arm<'a, T>(&self, value: &Value, arms: &'a [MatchArm<T>], span: Span) -> Result<(&'a T, Environment), InterperterError> {
+        for arm in arms {
+            let mut bindings = Vec::new();
+            if self.match_pattern(&arm.pattern, value, &mut bindings)? {
+                let mut env = Environment::with_parent(self.env.clone());
+                for (name, value) in bindings {
+                    env.define_variable(&name, Mutability::Immutable, None, value, arm.pattern.span)?;
+                }
+                return Ok((&arm.body, env));
+            }
+        }
+
+        Err(InterperterError::NoMatchingArm(value.to_string(), span))
+    }
+
+    // Assigns to the field at the end of the path, in the record `target`. A record of an anonymous type
+    // gets its field types from the field it is the value of, the value is checked against them if it has them.
+    fn assign_field(
+        &self, target: &mut Value, field_types: Option<Vec<Field>>, path: &[String], value: Value, span: Span
+    ) -> Result<(), InterperterError> {
+        let Value::Record(record) = target else {
+            return Err(InterperterError::NotARecord(target.get_type(), span));
+        };
+        let (field, path) = path.split_first().expect("a field assignment has a field");
+
+        let field_types = match &record.name {
+            Some(name) => Some(self.env.borrow().get_record(name, span)?.fields.clone()),
+            None => field_types,
+        };
+        let type_name = record.type_name().to_string();
+        let unknown_field = || InterperterError::UnknownField(type_name.clone(), field.clone(), span);
+        let field_type = field_types
+            .map(|field_types| field_types.into_iter().find(|field_type| field_type.name == *field).ok_or_else(unknown_field))
+            .transpose()?
+            .map(|field_type| field_type.ty);
+        let target = record.get_mut(field).ok_or_else(unknown_field)?;
+
+        if path.is_empty() {
+            if let Some(ty) = &field_type {
+                self.env.borrow().check_value(ty, &value, span)?;
+            }
+            *target = value;
+            Ok(())
+        } else {
+            let field_types = match field_type {
+                Some(Type::Record(field_types)) => Some(field_types),
+                _ => None,
+            };
+            self.assign_field(target, field_types, path, value, span)
+        }
+    }
+
+    fn evaluate_condition(&mut self, condition: &Expr) -> Result<bool, InterperterError> {
+        match self.evaluate_expr(condition)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(InterperterError::InvalidCondition(value.get_type(), condition.span)),
+        }
+    }
 }
 
-impl<'a> ExprVisitor for Interperter<'a> {
+impl ExprVisitor for Interperter {
     type Result = Result<Value, InterperterError>;
 
-    fn visit_binary_expr(&mut self, op: &BinOp, lhs: &Expr, rhs: &Expr) -> Self::Result {
+    fn visit_binary_expr(&mut self, op: &BinOp, lhs: &Expr, rhs: &Expr, span: Span) -> Self::Result {
         use InterperterError::*;
 
-        let lhs_val = self.evaluate_expr(&lhs)?;
-        let rhs_val = self.evaluate_expr(&rhs)?;
+        let lhs_val = self.evaluate_expr(lhs)?;
+
+        // `&&` and `||` short circuit, the right hand side is only evaluated when needed
+        match (op, &lhs_val) {
+            (BinOp::And, Value::Bool(false)) => return Ok(Value::Bool(false)),
+            (BinOp::Or, Value::Bool(true)) => return Ok(Value::Bool(true)),
+            _ => {}
+        }
+
+        let rhs_val = self.evaluate_expr(rhs)?;
 
         let lhs_type = lhs_val.get_type();
         let rhs_type = rhs_val.get_type();
 
         // TODO: improve. Language features such as coercion, inference.
         if lhs_type != rhs_type {
-            return Err(BinaryExprTypeMismatch(lhs_type, rhs_type));
+            return Err(BinaryExprTypeMismatch(lhs_type, rhs_type, span));
         }
 
         // Remember that for now, both types are the same
         match lhs_type {
-            ValueType::String => ExprEval::bin_string_string(op, &lhs_val, &rhs_val),
-            ValueType::Float => ExprEval::bin_float_float(op, &lhs_val, &rhs_val),
-            ValueType::Integer=> ExprEval::bin_integer_integer(op, &lhs_val, &rhs_val),
-            ValueType::Bool =>  ExprEval::bin_bool_bool(op, &lhs_val, &rhs_val),
+            ValueType::String => ExprEval::bin_string_string(op, &lhs_val, &rhs_val, span),
+            ValueType::Char => ExprEval::bin_char_char(op, &lhs_val, &rhs_val, span),
+            ValueType::Float => ExprEval::bin_float_float(op, &lhs_val, &rhs_val, span),
+            ValueType::Integer=> ExprEval::bin_integer_integer(op, &lhs_val, &rhs_val, span),
+            ValueType::Bool =>  ExprEval::bin_bool_bool(op, &lhs_val, &rhs_val, span),
+            ValueType::Range | ValueType::Function | ValueType::Record | ValueType::Variant | ValueType::Unit => {
+                ExprEval::bin_equality(op, &lhs_val, &rhs_val, span)
+            }
         }
     }
 
-    fn visit_unary_expr(&mut self, op: &UnOp, expr: &Expr) -> Self::Result {
+    fn visit_unary_expr(&mut self, op: &UnOp, expr: &Expr, span: Span) -> Self::Result {
         use InterperterError::*;
-        let expr_val = self.evaluate_expr(&expr)?;
+        let expr_val = self.evaluate_expr(expr)?;
         match expr_val {
-            Value::String(_) => Err(InvalidUnaryApplication(*op, ValueType::String)),
+            Value::String(_) => Err(InvalidUnaryApplication(*op, ValueType::String, span)),
+            Value::Char(_) => Err(InvalidUnaryApplication(*op, ValueType::Char, span)),
+            Value::Range(..) | Value::Function(..) | Value::Method(..) | Value::Record(..) | Value::Variant(..) | Value::Unit => Err(InvalidUnaryApplication(*op, expr_val.get_type(), span)),
             Value::Bool(val) => match op {
                 UnOp::Not => Ok(Value::Bool(!val)),
-                UnOp::Neg => Err(InvalidUnaryApplication(*op, ValueType::Bool)),
+                UnOp::Neg => Err(InvalidUnaryApplication(*op, ValueType::Bool, span)),
             }
             Value::Integer(val) => match op {
-                UnOp::Not => Err(InvalidUnaryApplication(*op, ValueType::Integer)),
-                UnOp::Neg => Ok(Value::Integer(-1 * val)),
+                UnOp::Not => Err(InvalidUnaryApplication(*op, ValueType::Integer, span)),
+                UnOp::Neg => ExprEval::checked(val.checked_neg(), span),
             }
             Value::Float(val) => match op {
-                UnOp::Not => Err(InvalidUnaryApplication(*op, ValueType::Float)),
-                UnOp::Neg => Ok(Value::Float(-1.0 * val)),
+                UnOp::Not => Err(InvalidUnaryApplication(*op, ValueType::Float, span)),
+                UnOp::Neg => Ok(Value::Float(-val)),
+            }
+        }
+    }
+
+    fn visit_literal_expr(&mut self, literal: &Literal, _span: Span) -> Self::Result {
+        Ok(literal_value(literal))
+    }
+
+    fn visit_interpolated_expr(&mut self, parts: &[InterpolatedPart], _span: Span) -> Self::Result {
+        let mut out = String::new();
+        for part in parts {
+            match part {
+                InterpolatedPart::Text(text) => out.push_str(text),
+                InterpolatedPart::Expr(expr) => out.push_str(&self.evaluate_expr(expr)?.to_string()),
             }
         }
+        Ok(Value::String(out))
+    }
+
+    fn visit_variable_expr(&mut self, identifier: &str, span: Span) -> Self::Result {
+        self.env.borrow().get_variable_value(identifier, span)
+    }
+
+    fn visit_if_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr, _span: Span) -> Self::Result {
+        if self.evaluate_condition(condition)? {
+            self.evaluate_expr(then_branch)
+        } else {
+            self.evaluate_expr(else_branch)
+        }
     }
 
-    fn visit_literal_expr(&mut self, literal: &Literal) -> Self::Result {
-        Ok(match literal {
-            Literal::String(val) => Value::String(val.clone()), 
-            Literal::Bool(val) => Value::Bool(val.clone()),
-            Literal::Integer(val) => Value::Integer(val.clone()),
-            Literal::Float(val) => Value::Float(val.clone()),
+    fn visit_block_expr(&mut self, statements: &[Stmt], value: &Expr, _span: Span) -> Self::Result {
+        let env = Environment::with_parent(self.env.clone());
+        self.in_scope(env, |interperter| {
+            interperter.declare_types(statements)?;
+            statements.iter().try_for_each(|stmt| interperter.interpret_stmt(stmt))?;
+            interperter.evaluate_expr(value)
         })
     }
 
-    fn visit_variable_expr(&mut self, identifier: &String) -> Self::Result {
-        Ok(self.global_env
-               .get_variable_value(identifier)?
-               .clone())
+    fn visit_call_expr(&mut self, callee: &Expr, args: &[Expr], span: Span) -> Self::Result {
+        let callee_value = self.evaluate_expr(callee)?;
+        if !matches!(callee_value, Value::Function(..) | Value::Method(..)) {
+            return Err(InterperterError::NotCallable(callee_value.get_type(), callee.span));
+        }
+
+        let args = args
+            .iter()
+            .map(|arg| self.evaluate_expr(arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let closure = match callee_value {
+            Value::Function(closure) => closure,
+            Value::Method(method) => return Err(InterperterError::UnresolvedMethod(method.name().to_string(), callee.span)),
+            _ => unreachable!("the callee is a function or a method"),
+        };
+        self.call(&closure, args, span)
     }
-}
 
-impl<'a> StmtVisitor for Interperter<'a> {
-    type Result = Result<(), InterperterError>;
+    fn visit_record_expr(&mut self, name: &str, fields: &[FieldInit], span: Span) -> Self::Result {
+        let record_type = self.env.borrow().get_record(name, span)?;
+        let fields = self.evaluate_fields(name, &record_type.fields, fields, span)?;
 
-    fn visit_expr_stmt(&mut self, expr: &Expr) -> Self::Result {
-        let val = self.evaluate_expr(&expr)?;
-        println!("{:?}", val);
+        Ok(Value::Record(Record {
+            name: Some(name.to_string()),
+            fields,
+        }))
+    }
 
-        Ok(())
+    fn visit_field_expr(&mut self, expr: &Expr, field: &str, span: Span) -> Self::Result {
+        // `Color.Red`, a variant without fields
+        let union_type = match &expr.kind {
+            ExprKind::Variable(name) => match self.env.borrow().get_type(name, expr.span) {
+                Ok(NamedType::Union(union_type)) => Some(union_type),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(union_type) = union_type {
+            return self.make_variant(Some(&union_type.name), &union_type.variants, field, &[], span);
+        }
+
+        match self.evaluate_expr(expr)? {
+            Value::Record(record) => record
+                .get(field)
+                .cloned()
+                .ok_or_else(|| InterperterError::UnknownField(record.type_name().to_string(), field.to_string(), span)),
+            value => Err(InterperterError::NotARecord(value.get_type(), expr.span)),
+        }
     }
-    
-    fn visit_println_stmt(&mut self, expr: &Expr) -> Self::Result {
-        match self.evaluate_expr(&expr)? {
-            Value::String(val) => println!("{}", val), 
-            Value::Float(val) => println!("{}", val), 
-            Value::Integer(val) => println!("{}", val), 
-            Value::Bool(val) => println!("{}", val), 
+
+    fn visit_variant_expr(&mut self, union: &str, variant: &str, fields: &[FieldInit], span: Span) -> Self::Result {
+        let union_type = self.env.borrow().get_union(union, span)?;
+        self.make_variant(Some(union), &union_type.variants, variant, fields, span)
+    }
+
+    // An anonymous literal is only evaluated with the type of the field it is the value of
+    fn visit_anonymous_record_expr(&mut self, _fields: &[FieldInit], span: Span) -> Self::Result {
+        Err(InterperterError::CannotInferType(span))
+    }
+
+    fn visit_anonymous_variant_expr(&mut self, _variant: &str, _fields: &[FieldInit], span: Span) -> Self::Result {
+        Err(InterperterError::CannotInferType(span))
+    }
+
+    // The semantic analysis resolved the call to the method of the impl
+    fn visit_method_expr(&mut self, trait_name: &str, type_name: &str, method: &str, span: Span) -> Self::Result {
+        self.env
+            .borrow()
+            .get_impl_method(trait_name, type_name, method)
+            .map(Value::Function)
+            .ok_or_else(|| InterperterError::SymbolNotFound(format!("{method} of the impl of {trait_name} for {type_name}"), span))
+    }
+
+    fn visit_match_expr(&mut self, subject: &Expr, arms: &[MatchArm<Expr>], span: Span) -> Self::Result {
+        let value = self.evaluate_expr(subject)?;
+        let (body, env) = self.match_arm(&value, arms, span)?;
+        self.in_scope(env, |interperter| interperter.evaluate_expr(body))
+    }
+
+    fn visit_range_expr(&mut self, start: &Expr, end: &Expr, kind: RangeKind, span: Span) -> Self::Result {
+        match (self.evaluate_expr(start)?, self.evaluate_expr(end)?) {
+            (Value::Integer(start), Value::Integer(end)) => Ok(Value::Range(start, end, kind)),
+            (start, end) => Err(InterperterError::InvalidRange(start.get_type(), end.get_type(), span)),
         }
-        Ok(())
     }
+}
 
-    fn visit_print_stmt(&mut self, expr: &Expr) -> Self::Result {
-        match self.evaluate_expr(&expr)? {
-            Value::String(val) => print!("{}", val), 
-            Value::Float(val) => print!("{}", val), 
-            Value::Integer(val) => print!("{}", val), 
-            Value::Bool(val) => print!("{}", val), 
+impl StmtVisitor for Interperter {
+    type Result = Result<Flow, InterperterError>;
+
+    fn visit_expr_stmt(&mut self, expr: &Expr, _span: Span) -> Self::Result {
+        // Calls of functions without a return value are run for their effects only
+        let val = self.evaluate_expr(expr)?;
+        if val != Value::Unit {
+            println!("{:?}", val);
         }
-        Ok(())
+
+        Ok(Flow::Next)
+    }
+    
+    fn visit_println_stmt(&mut self, expr: &Expr, _span: Span) -> Self::Result {
+        println!("{}", self.evaluate_expr(expr)?);
+        Ok(Flow::Next)
     }
 
-    fn visit_decl_stmt(&mut self, decl: &Decl) -> Self::Result {
+    fn visit_print_stmt(&mut self, expr: &Expr, _span: Span) -> Self::Result {
+        print!("{}", self.evaluate_expr(expr)?);
+        Ok(Flow::Next)
+    }
+
+    fn visit_decl_stmt(&mut self, decl: &Decl, span: Span) -> Self::Result {
         match &decl.kind {
-            DeclKind::Const(identifier, expr) => {
-                let val = self.evaluate_expr(expr)?;
-                self.global_env.define_variable(identifier, Mutability::Immutable, val)?;
-                Ok(())
+            DeclKind::Const(identifier, ty, expr) => {
+                let val = self.evaluate_initializer(expr, ty.as_ref())?;
+                self.env.borrow_mut().define_variable(identifier, Mutability::Immutable, ty.clone(), val, span)?;
             },
-            DeclKind::Var(identifier, expr) => {
-                let val = self.evaluate_expr(expr)?;
-                self.global_env.define_variable(identifier, Mutability::Mutable, val)?;
-                Ok(())
+            DeclKind::Var(identifier, ty, expr) => {
+                let val = self.evaluate_initializer(expr, ty.as_ref())?;
+                self.env.borrow_mut().define_variable(identifier, Mutability::Mutable, ty.clone(), val, span)?;
            },
+            DeclKind::Function(function) => {
+                // The function sees the scope it is declared in, itself included
+                let closure = Closure {
+                    function: Rc::new((**function).clone()),
+                    env: self.env.clone(),
+                };
+                let val = Value::Function(Rc::new(closure));
+                self.env.borrow_mut().define_variable(&function.name, Mutability::Immutable, None, val, span)?;
+            },
+            // Declared with the other types of the list of statements, before it runs
+            DeclKind::Record(_) | DeclKind::Union(_) | DeclKind::Trait(_) | DeclKind::Impl(_) => {},
         }
+        Ok(Flow::Next)
     }
 
-    fn visit_assign_stmt(&mut self, identifier: &String, expr: &Expr) -> Self::Result {
+    fn visit_assign_stmt(&mut self, identifier: &str, expr: &Expr, span: Span) -> Self::Result {
         let value = self.evaluate_expr(expr)?;
-        self.global_env.assign_to_variable(identifier, value)?;
-        Ok(())
+        self.env.borrow_mut().assign_to_variable(identifier, value, span)?;
+        Ok(Flow::Next)
+    }
+
+    // The record is copied out of the variable, and the copy with the new field is assigned back
+    fn visit_field_assign_stmt(&mut self, identifier: &str, fields: &[String], expr: &Expr, span: Span) -> Self::Result {
+        let value = self.evaluate_expr(expr)?;
+        let mut record = self.env.borrow().get_variable_value(identifier, span)?;
+        self.assign_field(&mut record, None, fields, value, span)?;
+        self.env.borrow_mut().assign_to_variable(identifier, record, span)?;
+        Ok(Flow::Next)
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt], _span: Span) -> Self::Result {
+        let env = Environment::with_parent(self.env.clone());
+        self.execute_block(statements, env)
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>, _span: Span) -> Self::Result {
+        if self.evaluate_condition(condition)? {
+            self.execute(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(Flow::Next)
+        }
+    }
+
+    fn visit_for_stmt(&mut self, variable: &str, iterable: &Expr, body: &Stmt, span: Span) -> Self::Result {
+        let values: Box<dyn Iterator<Item = i128>> = match self.evaluate_expr(iterable)? {
+            Value::Range(start, end, RangeKind::Exclusive) => Box::new(start..end),
+            Value::Range(start, end, RangeKind::Inclusive) => Box::new(start..=end),
+            value => return Err(InterperterError::NotIterable(value.get_type(), iterable.span)),
+        };
+
+        // Every iteration binds the loop variable in a fresh scope
+        for value in values {
+            let mut env = Environment::with_parent(self.env.clone());
+            env.define_variable(variable, Mutability::Immutable, None, Value::Integer(value), span)?;
+
+            match self.in_scope(env, |interperter| interperter.execute(body))? {
+                Flow::Break => break,
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+                Flow::Next | Flow::Continue => {}
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt, _span: Span) -> Self::Result {
+        while self.evaluate_condition(condition)? {
+            match self.execute(body)? {
+                Flow::Break => break,
+                Flow::Return(value) => return Ok(Flow::Return(value)),
+                Flow::Next | Flow::Continue => {}
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn visit_break_stmt(&mut self, _span: Span) -> Self::Result {
+        Ok(Flow::Break)
+    }
+
+    fn visit_continue_stmt(&mut self, _span: Span) -> Self::Result {
+        Ok(Flow::Continue)
+    }
+
+    fn visit_return_stmt(&mut self, expr: Option<&Expr>, _span: Span) -> Self::Result {
+        let value = match expr {
+            Some(expr) => self.evaluate_expr(expr)?,
+            None => Value::Unit,
+        };
+        Ok(Flow::Return(value))
+    }
+
+    fn visit_match_stmt(&mut self, subject: &Expr, arms: &[MatchArm<Stmt>], span: Span) -> Self::Result {
+        let value = self.evaluate_expr(subject)?;
+        let (body, env) = self.match_arm(&value, arms, span)?;
+        self.in_scope(env, |interperter| interperter.execute(body))
+    }
+}
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::String(val) => Value::String(val.clone()),
+        Literal::Char(val) => Value::Char(*val),
+        Literal::Bool(val) => Value::Bool(*val),
+        Literal::Integer(val, _) => Value::Integer(*val),
+        Literal::Float(val, _) => Value::Float(*val),
+    }
+}
+
+// The names of the fields are unique, and so are the ones in the anonymous types of the fields
+fn check_unique_fields(fields: &[Field]) -> Result<(), InterperterError> {
+    for (index, field) in fields.iter().enumerate() {
+        if fields[..index].iter().any(|other| other.name == field.name) {
+            return Err(InterperterError::DuplicateField(field.name.clone(), field.span));
+        }
+        match &field.ty {
+            Type::Record(fields) => check_unique_fields(fields)?,
+            Type::Union(variants) => check_unique_variants(variants)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_unique_variants(variants: &[VariantDecl]) -> Result<(), InterperterError> {
+    for (index, variant) in variants.iter().enumerate() {
+        if variants[..index].iter().any(|other| other.name == variant.name) {
+            return Err(InterperterError::DuplicateVariant(variant.name.clone(), variant.span));
+        }
+        check_unique_fields(&variant.fields)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mypl_parse::prelude::*;
+    use mypl_semantic::prelude::*;
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap()
+    }
+
+    // Runs the program, then evaluates the expression statement that ends it
+    fn run(interperter: &mut Interperter, source: &str) -> Result<Value, InterperterError> {
+        let mut statements = parse(source);
+        let Some(Stmt { kind: StmtKind::Expr(expr), .. }) = statements.pop() else {
+            panic!("the program should end with an expression statement");
+        };
+
+        interperter.declare_types(&statements)?;
+        for stmt in &statements {
+            interperter.interpret_stmt(stmt)?;
+        }
+        interperter.evaluate_expr(&expr)
+    }
+
+    #[test]
+    fn call_functions() {
+        let source = "
+            const increment: (value: i8) i8 = { return value + 1; }
+            decrement: (value: i8) i8 = { return value - 1; }
+            var x = 0;
+            x = increment(increment(x));
+            x = decrement(x);
+            x;
+        ";
+        assert_eq!(run(&mut Interperter::new(), source).unwrap(), Value::Integer(1));
+
+        // Closures keep the scope they were declared in
+        let source = "
+            var count = 10;
+            const next: () u32 = { count = count + 1; return count; }
+            next() + next();
+        ";
+        assert_eq!(run(&mut Interperter::new(), source).unwrap(), Value::Integer(23));
+
+        let source = "const f: (a: u32, b: u32) u32 = { return a; }\nf(1);";
+        assert!(matches!(run(&mut Interperter::new(), source), Err(InterperterError::ArityMismatch(_, 2, 1, _))));
+    }
+
+    #[test]
+    fn recursion_depth_limit() {
+        let source = "
+            const sum: (n: u32) u32 = {
+                if n == 0 { return 0; }
+                return n + sum(n - 1);
+            }
+            sum(50);
+        ";
+        assert_eq!(run(&mut Interperter::new(), source).unwrap(), Value::Integer(1275));
+        assert!(matches!(
+            run(&mut Interperter::with_max_call_depth(20), source),
+            Err(InterperterError::CallDepthExceeded(20, _)),
+        ));
+
+        // The depth unwinds after an error, the interpreter can go on
+        let mut interperter = Interperter::with_max_call_depth(20);
+        assert!(run(&mut interperter, source).is_err());
+        assert_eq!(run(&mut interperter, "sum(10);").unwrap(), Value::Integer(55));
+    }
+
+    #[test]
+    fn return_from_loops() {
+        let source = "
+            const find: (limit: u32) u32 = {
+                var i = 0;
+                while true {
+                    for j in i..limit {
+                        if j * j > limit { return j; }
+                    }
+                    i = i + 1;
+                }
+            }
+            find(50);
+        ";
+        assert_eq!(run(&mut Interperter::new(), source).unwrap(), Value::Integer(8));
+    }
+
+    #[test]
+    fn check_annotated_types() {
+        let mut interperter = Interperter::new();
+        let source = "
+            var x: u8 = 255;
+            const increment: (value: u8) u8 = { return value + 1; }
+            const f: (u8) u8 = increment;
+            f(x - 1);
+        ";
+        assert_eq!(run(&mut interperter, source).unwrap(), Value::Integer(255));
+
+        // Assignments, arguments and return values are checked too
+        assert!(matches!(run(&mut interperter, "x = 256;\nx;"), Err(InterperterError::ValueOutOfRange(..))));
+        assert!(matches!(run(&mut interperter, "x = 'c';\nx;"), Err(InterperterError::TypeMismatch(..))));
+        assert!(matches!(run(&mut interperter, "f(-1);"), Err(InterperterError::ValueOutOfRange(..))));
+        assert!(matches!(run(&mut interperter, "f(x);"), Err(InterperterError::ValueOutOfRange(..))));
+        assert_eq!(run(&mut interperter, "x;").unwrap(), Value::Integer(255));
+
+        let source = "const g: (u8) bool = increment;\ng;";
+        assert!(matches!(run(&mut interperter, source), Err(InterperterError::TypeMismatch(_, ValueType::Function, _))));
+    }
+
+    #[test]
+    fn construct_records() {
+        let mut interperter = Interperter::new();
+        let source = "
+            Point = record { x: u32, y: u32 };
+            Segment = record { start: Point, end: Point };
+            var segment = Segment { end = Point { x = 3, y = 4 }, start = Point { y = 0, x = 0 } };
+            const copy = segment;
+            segment.end.x = 5;
+            segment.end.x + copy.end.x;
+        ";
+        assert_eq!(run(&mut interperter, source).unwrap(), Value::Integer(8));
+
+        // Records are equal when their fields are, whatever order the literal has them in
+        assert_eq!(run(&mut interperter, "copy.end == Point { y = 4, x = 3 };").unwrap(), Value::Bool(true));
+        assert_eq!(run(&mut interperter, "segment == copy;").unwrap(), Value::Bool(false));
+
+        use InterperterError::*;
+        assert!(matches!(run(&mut interperter, "Point { x = 1, z = 2 };"), Err(UnknownField(_, field, _)) if field == "z"));
+        assert!(matches!(run(&mut interperter, "Point { x = 1 };"), Err(MissingField(_, field, _)) if field == "y"));
+        assert!(matches!(run(&mut interperter, "Point { x = 1, x = 2 };"), Err(DuplicateField(..))));
+        assert!(matches!(run(&mut interperter, "copy.middle;"), Err(UnknownField(..))));
+        assert!(matches!(run(&mut interperter, "segment.end.x = -1;\n1;"), Err(ValueOutOfRange(..))));
+        assert!(matches!(run(&mut interperter, "copy.end.x = 1;\n1;"), Err(ImmutableAssignment(..))));
+        assert!(matches!(run(&mut interperter, "Segment { start = 1, end = 2 };"), Err(TypeMismatch(..))));
+        assert!(matches!(run(&mut interperter, "segment.end.x.y;"), Err(NotARecord(ValueType::Integer, _))));
+        assert!(matches!(run(&mut interperter, "Point;"), Err(NotAValue(..))));
+    }
+
+    #[test]
+    fn match_unions() {
+        let mut interperter = Interperter::new();
+        let source = "
+            Shape = union {
+                Empty,
+                Circle: record { radius: u32 },
+                Rectangle: record { width: u32, height: u32 },
+            };
+            const area: (shape: Shape) u32 = {
+                return match shape {
+                    Shape.Circle { radius = 0 } => 0,
+                    Shape.Circle { radius } => 3 * radius * radius,
+                    Shape.Rectangle { width, height = h } => width * h,
+                    _ => 0,
+                };
+            }
+            area(Shape.Circle { radius = 2 }) + area(Shape.Rectangle { width = 2, height = 3 }) + area(Shape.Empty);
+        ";
+        assert_eq!(run(&mut interperter, source).unwrap(), Value::Integer(18));
+        assert_eq!(run(&mut interperter, "Shape.Circle { radius = 1 } == Shape.Circle { radius = 1 };").unwrap(), Value::Bool(true));
+        assert_eq!(run(&mut interperter, "Shape.Empty != Shape.Circle { radius = 1 };").unwrap(), Value::Bool(true));
+
+        // Literal patterns, and the names an arm binds are gone after it
+        let source = "var x = 0;\nmatch 2 { 1 => { x = 1; } n => { x = n * 10; } }\nx;";
+        assert_eq!(run(&mut interperter, source).unwrap(), Value::Integer(20));
+        assert!(matches!(run(&mut interperter, "n;"), Err(InterperterError::SymbolNotFound(..))));
+
+        use InterperterError::*;
+        assert!(matches!(run(&mut interperter, "(match 1 { 2 => 2 });"), Err(NoMatchingArm(..))));
+        assert!(matches!(run(&mut interperter, "Shape.Square;"), Err(UnknownVariant(..))));
+        assert!(matches!(run(&mut interperter, "Shape.Circle;"), Err(MissingField(..))));
+        assert!(matches!(run(&mut interperter, "(match 1 { Shape.Circle { size } => 1, _ => 0 });"), Err(UnknownField(..))));
+        assert!(matches!(run(&mut interperter, "const s: Shape = 1;\n1;"), Err(TypeMismatch(..))));
+        assert!(matches!(run(&mut interperter, "U = union { A, A };\n1;"), Err(DuplicateVariant(..))));
+    }
+
+    #[test]
+    fn construct_anonymous_types() {
+        let mut interperter = Interperter::new();
+        let source = "
+            Point = record { x: u32, y: u32 };
+            Vertex = record {
+                point: Point,
+                texture: record { u: u32, v: u32 },
+                kind: union { Corner, Edge: record { length: u32 } },
+            };
+            var vertex = Vertex { point = { x = 1, y = 2 }, texture = { u = 3, v = 4 }, kind = .Edge { length = 5 } };
+            vertex.texture.v = 6;
+            vertex.point.x + vertex.texture.v + match vertex.kind { .Edge { length } => length, _ => 0 };
+        ";
+        assert_eq!(run(&mut interperter, source).unwrap(), Value::Integer(12));
+        assert_eq!(
+            run(&mut interperter, "vertex;").unwrap().to_string(),
+            "Vertex { point = Point { x = 1, y = 2 }, texture = { u = 3, v = 6 }, kind = .Edge { length = 5 } }",
+        );
+
+        // The types are structural, a named record has an anonymous type with the same fields and back
+        assert_eq!(run(&mut interperter, "const p: record { x: u32, y: u32 } = vertex.point;\np == vertex.point;").unwrap(), Value::Bool(true));
+        assert!(run(&mut interperter, "const t: Point = Point { x = vertex.texture.u, y = 0 };\nt.x;").is_ok());
+
+        use InterperterError::*;
+        assert!(matches!(run(&mut interperter, "vertex.texture.u = -1;\n1;"), Err(ValueOutOfRange(..))));
+        assert!(matches!(run(&mut interperter, "vertex.texture.w = 1;\n1;"), Err(UnknownField(..))));
+        assert!(matches!(run(&mut interperter, "Point { x = { y = 1 }, y = 2 };"), Err(CannotInferType(..))));
+        assert!(matches!(run(&mut interperter, "Vertex { point = { x = 1 }, texture = { u = 1, v = 1 }, kind = .Corner };"), Err(MissingField(..))));
+        assert!(matches!(run(&mut interperter, "Vertex { point = vertex.point, texture = vertex.texture, kind = .Face };"), Err(UnknownVariant(..))));
+        assert!(matches!(run(&mut interperter, "const r: record { a: u8 } = vertex.texture;\n1;"), Err(TypeMismatch(..))));
+        assert!(matches!(run(&mut interperter, "R = record { a: record { b: u8, b: u8 } };\n1;"), Err(DuplicateField(..))));
+    }
+
+    #[test]
+    fn infer_anonymous_literals_from_annotations() {
+        let mut interperter = Interperter::new();
+        let source = "
+            Point = record { x: u32, y: u32 };
+            Kind = union { Corner, Edge: record { length: u32 } };
+            const point: Point = { x = 4, y = 3 };
+            var kind: Kind = .Edge { length = 5 };
+            const texture: record { u: u32, v: u32 } = { u = 1, v = 2 };
+            point.x + texture.v + match kind { Kind.Edge { length } => length, _ => 0 };
+        ";
+        assert_eq!(run(&mut interperter, source).unwrap(), Value::Integer(11));
+        assert_eq!(run(&mut interperter, "point;").unwrap().to_string(), "Point { x = 4, y = 3 }");
+        assert_eq!(run(&mut interperter, "texture;").unwrap().to_string(), "{ u = 1, v = 2 }");
+
+        use InterperterError::*;
+        assert!(matches!(run(&mut interperter, "const p: Point = { x = 1 };\n1;"), Err(MissingField(..))));
+        assert!(matches!(run(&mut interperter, "const k: Kind = .Face;\n1;"), Err(UnknownVariant(..))));
+        assert!(matches!(run(&mut interperter, "const n: u32 = { x = 1 };\n1;"), Err(CannotInferType(..))));
+    }
+
+    // Runs the program like `run`, after the semantic analysis that resolves its calls to methods
+    fn run_analyzed(interperter: &mut Interperter, analyzer: &mut SemanticAnalyzer, source: &str) -> Result<Value, InterperterError> {
+        let mut statements = parse(source);
+        assert!(analyzer.analyze(&mut statements).is_empty());
+        let Some(Stmt { kind: StmtKind::Expr(expr), .. }) = statements.pop() else {
+            panic!("the program should end with an expression statement");
+        };
+
+        interperter.declare_types(&statements)?;
+        for stmt in &statements {
+            interperter.interpret_stmt(stmt)?;
+        }
+        interperter.evaluate_expr(&expr)
+    }
+
+    #[test]
+    fn dispatch_trait_methods() {
+        let mut interperter = Interperter::new();
+        let mut analyzer = SemanticAnalyzer::new();
+        let source = "
+            Shape = trait { area: (Self) u32, name: (string, Self) string };
+            Square = record { side: u32 };
+            Line = union { Flat, Long: record { length: u32 } };
+            impl Shape for Square {
+                area: (square: Square) u32 = { return square.side * square.side; }
+                name: (prefix: string, square: Square) string = { return \"{prefix} square\"; }
+            }
+            impl Shape for Line {
+                area: (line: Line) u32 = { return 0; }
+                name: (prefix: string, line: Line) string = { return \"{prefix} line\"; }
+            }
+            const total: (a: impl Shape, b: impl Shape) u32 = { return area(a) + area(b); }
+            total(Square { side = 3 }, Line.Long { length = 4 });
+        ";
+        assert_eq!(run_analyzed(&mut interperter, &mut analyzer, source).unwrap(), Value::Integer(9));
+
+        // The argument in the position of `Self` picks the impl, and the instance of an earlier
+        // program is called again
+        let value = run_analyzed(&mut interperter, &mut analyzer, "name(\"a\", Line.Flat);").unwrap();
+        assert_eq!(value, Value::String("a line".to_string()));
+        let value = run_analyzed(&mut interperter, &mut analyzer, "total(Square { side = 1 }, Line.Flat) + total(Line.Flat, Line.Flat);").unwrap();
+        assert_eq!(value, Value::Integer(1));
+        let instance = interperter.env.borrow().get_variable_value("total<Square, Line>", Span::default());
+        assert!(matches!(instance, Ok(Value::Function(..))));
+
+        use InterperterError::*;
+        assert!(matches!(run(&mut interperter, "area(Square { side = 1 });"), Err(UnresolvedMethod(..))));
+        assert!(matches!(run(&mut interperter, "const f: (shape: impl Line) = { }\nf(Line.Flat);"), Err(NotATrait(..))));
+        assert!(matches!(run(&mut interperter, "Empty = trait { make: () Self };\n1;"), Err(MethodWithoutSelf(..))));
+    }
+
+    #[test]
+    fn declare_types_before_the_statements() {
+        let mut interperter = Interperter::new();
+        let mut analyzer = SemanticAnalyzer::new();
+        let source = "
+            const unit: Length = Length.Metre;
+            const square: (side: u32) u32 = {
+                const inner = area(Square { side = side });
+                Square = record { side: u32 };
+                impl Shape for Square {
+                    area: (square: Square) u32 = { return square.side * square.side; }
+                }
+                return inner;
+            }
+            const total = area(Circle { radius = 2 }) + square(3);
+            Shape = trait { area: (Self) u32 };
+            Circle = record { radius: u32 };
+            Length = union { Metre, Foot };
+            impl Shape for Circle {
+                area: (circle: Circle) u32 = { return 3 * circle.radius * circle.radius; }
+            }
+            total;
+        ";
+        assert_eq!(run_analyzed(&mut interperter, &mut analyzer, source).unwrap(), Value::Integer(21));
     }
 }
@@ -9,10 +9,12 @@ mod expr_eval;
 mod error;
 mod symbol;
 mod environment;
+mod function;
+mod type_check;
 
 pub mod prelude {
     use crate::*;
 
     pub use error::InterperterError;
-    pub use interperter::Interperter;
+    pub use interperter::{Interperter, CALL_STACK_SIZE, DEFAULT_MAX_CALL_DEPTH};
 }
@@ -1,5 +1,8 @@
 use anyhow::Result;
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
+
+use mypl_ast::prelude::{RecordDecl, Type, UnionDecl};
+use mypl_lex::prelude::Span;
 
 use crate::{expr_eval::Value, prelude::InterperterError};
 use InterperterError::{SymbolNotFound, SymbolAlreadyExists};
@@ -12,10 +15,23 @@ pub enum Mutability {
 
 pub enum SymbolKind {
     Variable(Value),
+    Type(NamedType),
+}
+
+/// A type declared by name, its values are made with a literal of its name.
+/// A trait has no values, it is only a bound for the types that implement it.
+#[derive(Clone)]
+pub enum NamedType {
+    Record(Rc<RecordDecl>),
+    Union(Rc<UnionDecl>),
+    Trait,
 }
 
 pub struct Symbol {
     pub mutability: Mutability,
+
+    /// The annotated type, assignments are checked against it
+    pub ty: Option<Type>,
     pub kind: SymbolKind,
 
     // We can also add
@@ -41,14 +57,14 @@ impl SymbolTable {
         }
     }
 
-    pub fn get(&self, name: &str) -> Result<&Symbol, InterperterError> {
+    pub fn get(&self, name: &str, span: Span) -> Result<&Symbol, InterperterError> {
         self.symbols.get(name)
-            .ok_or_else(|| InterperterError::SymbolNotFound(name.to_string()))
+            .ok_or_else(|| InterperterError::SymbolNotFound(name.to_string(), span))
     }
 
-    pub fn insert(&mut self, name: &str, symbol: Symbol) -> Result<(), InterperterError> {
+    pub fn insert(&mut self, name: &str, symbol: Symbol, span: Span) -> Result<(), InterperterError> {
         if self.symbols.contains_key(name) {
-            Err(SymbolAlreadyExists(name.to_string()))
+            Err(SymbolAlreadyExists(name.to_string(), span))
         } else {
             if self.symbols.insert(name.to_string(), symbol).is_some() {
                 // SymbolTable is not Sync or Send
@@ -59,13 +75,13 @@ impl SymbolTable {
         }
     }
 
-    pub fn update<F>(&mut self, name: &str, update: F) -> Result<(), InterperterError> where
+    pub fn update<F>(&mut self, name: &str, span: Span, update: F) -> Result<(), InterperterError> where
         F: FnOnce(&mut Symbol) -> Result<(), InterperterError>,
     {
         if let Some(symbol) = self.symbols.get_mut(name) {
             Ok(update(symbol)?)
         } else {
-            Err(SymbolNotFound(name.to_string()))
+            Err(SymbolNotFound(name.to_string(), span))
         }
     }
 }
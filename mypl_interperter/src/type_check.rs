@@ -0,0 +1,167 @@
+use mypl_ast::prelude::*;
+use mypl_lex::prelude::Span;
+
+use crate::{expr_eval::{Value, Variant}, prelude::InterperterError, symbol::NamedType};
+
+/// The declared types and impls the types of values are checked against
+pub(crate) trait Types {
+    fn get_type(&self, name: &str, span: Span) -> Result<NamedType, InterperterError>;
+
+    /// Whether the trait is implemented for the named type
+    fn has_impl(&self, trait_name: &str, type_name: &str) -> bool;
+}
+
+/// Checks a value against the type it is annotated with.
+/// Integers and floats also have to fit in the annotated numeric type.
+/// Records and variants are typed by their fields, `types` looks up the declared types by name.
+/// A value meets a trait bound when the trait is implemented for its named type.
+pub(crate) fn check_type(ty: &Type, value: &Value, span: Span, types: &dyn Types) -> Result<(), InterperterError> {
+    use PrimitiveType::*;
+
+    let fits = match (ty, value) {
+        (Type::Primitive(primitive), Value::Integer(integer)) => match primitive {
+            U32 => u32::try_from(*integer).is_ok(),
+            U16 => u16::try_from(*integer).is_ok(),
+            U8 => u8::try_from(*integer).is_ok(),
+            I32 => i32::try_from(*integer).is_ok(),
+            I16 => i16::try_from(*integer).is_ok(),
+            I8 => i8::try_from(*integer).is_ok(),
+            _ => return Err(mismatch(ty, value, span)),
+        },
+        // Infinities and NaN are in every float type, only finite floats can be out of range
+        (Type::Primitive(primitive), Value::Float(float)) => match primitive {
+            F32 => (*float as f32).is_finite() || !float.is_finite(),
+            // f16::MAX
+            F16 => float.abs() <= 65504.0 || !float.is_finite(),
+            _ => return Err(mismatch(ty, value, span)),
+        },
+        (Type::Primitive(Bool), Value::Bool(_)) => true,
+        (Type::Primitive(Char), Value::Char(_)) => true,
+        (Type::Primitive(String), Value::String(_)) => true,
+        (Type::Function(..), Value::Function(closure)) if closure.function.ty().is_same(ty) => true,
+        // Anonymous types are structural, like the named ones, the name of the value does not matter
+        (Type::Record(field_types), Value::Record(record)) => {
+            if !check_fields(&record.fields, field_types, span, types)? {
+                return Err(mismatch(ty, value, span));
+            }
+            true
+        }
+        (Type::Union(variant_types), Value::Variant(variant)) => {
+            if !check_variant(variant, variant_types, span, types)? {
+                return Err(mismatch(ty, value, span));
+            }
+            true
+        }
+        (Type::Named(name), value) => {
+            let has_type = match (types.get_type(name, span)?, value) {
+                (NamedType::Record(record_type), Value::Record(record)) => {
+                    check_fields(&record.fields, &record_type.fields, span, types)?
+                }
+                (NamedType::Union(union_type), Value::Variant(variant)) => {
+                    check_variant(variant, &union_type.variants, span, types)?
+                }
+                _ => false,
+            };
+
+            if !has_type {
+                return Err(mismatch(ty, value, span));
+            }
+            true
+        }
+        (Type::Impl(trait_name), value) => {
+            if !matches!(types.get_type(trait_name, span)?, NamedType::Trait) {
+                return Err(InterperterError::NotATrait(trait_name.clone(), span));
+            }
+            match value.named_type() {
+                Some(type_name) if types.has_impl(trait_name, type_name) => true,
+                _ => return Err(mismatch(ty, value, span)),
+            }
+        }
+        _ => return Err(mismatch(ty, value, span)),
+    };
+
+    if fits {
+        Ok(())
+    } else {
+        Err(InterperterError::ValueOutOfRange(value.to_string(), ty.clone(), span))
+    }
+}
+
+// Whether the variant is one of the variants, with the fields of that variant
+fn check_variant(
+    variant: &Variant, variant_types: &[VariantDecl], span: Span, types: &dyn Types
+) -> Result<bool, InterperterError> {
+    variant_types
+        .iter()
+        .find(|variant_type| variant_type.name == variant.name)
+        .map(|variant_type| check_fields(&variant.fields, &variant_type.fields, span, types))
+        .transpose()
+        .map(|has_type| has_type.unwrap_or(false))
+}
+
+// Whether the values have the names of the fields, the values are checked against their types
+fn check_fields(
+    values: &[(String, Value)], fields: &[Field], span: Span, types: &dyn Types
+) -> Result<bool, InterperterError> {
+    let same_names = values.len() == fields.len()
+        && values.iter().zip(fields).all(|((name, _), field)| *name == field.name);
+    if !same_names {
+        return Ok(false);
+    }
+
+    for ((_, value), field) in values.iter().zip(fields) {
+        check_type(&field.ty, value, span, types)?;
+    }
+    Ok(true)
+}
+
+fn mismatch(ty: &Type, value: &Value, span: Span) -> InterperterError {
+    InterperterError::TypeMismatch(ty.clone(), value.get_type(), span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expr_eval::ValueType;
+
+    struct NoTypes;
+
+    impl Types for NoTypes {
+        fn get_type(&self, name: &str, span: Span) -> Result<NamedType, InterperterError> {
+            Err(InterperterError::UnknownType(name.to_string(), span))
+        }
+
+        fn has_impl(&self, _trait_name: &str, _type_name: &str) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn check_primitive_types() {
+        let span = Span::default();
+        let primitive = Type::Primitive;
+        let check_type = |ty: &Type, value: &Value, span: Span| check_type(ty, value, span, &NoTypes);
+
+        assert!(check_type(&primitive(PrimitiveType::U8), &Value::Integer(255), span).is_ok());
+        assert!(check_type(&primitive(PrimitiveType::I8), &Value::Integer(-128), span).is_ok());
+        assert!(check_type(&primitive(PrimitiveType::F16), &Value::Float(1.5), span).is_ok());
+        assert!(check_type(&primitive(PrimitiveType::String), &Value::String("s".into()), span).is_ok());
+
+        assert!(matches!(
+            check_type(&primitive(PrimitiveType::U8), &Value::Integer(256), span),
+            Err(InterperterError::ValueOutOfRange(..)),
+        ));
+        assert!(matches!(
+            check_type(&primitive(PrimitiveType::U32), &Value::Integer(-1), span),
+            Err(InterperterError::ValueOutOfRange(..)),
+        ));
+        assert!(matches!(
+            check_type(&primitive(PrimitiveType::U32), &Value::Float(1.0), span),
+            Err(InterperterError::TypeMismatch(_, ValueType::Float, _)),
+        ));
+        assert!(matches!(
+            check_type(&primitive(PrimitiveType::Bool), &Value::Char('c'), span),
+            Err(InterperterError::TypeMismatch(_, ValueType::Char, _)),
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use mypl_lex::prelude::*;
+
+const SNIPPETS: &[(&str, &str)] = &[
+    ("program-1", include_str!("../../resources/valid-snippets/program-1.mypl")),
+    ("arithmetic-expression", include_str!("../../resources/valid-snippets/arithmetic-expression.mypl")),
+    ("comments", include_str!("../../resources/test-snippets/comments.mypl")),
+    ("keywords", include_str!("../../resources/test-snippets/keywords.mypl")),
+    ("single-chars", include_str!("../../resources/test-snippets/single-chars.mypl")),
+    ("double-chars", include_str!("../../resources/test-snippets/double-chars.mypl")),
+];
+
+fn tokenize(source: &str) -> usize {
+    let mut tokenizer = Tokenizer::new(source);
+    let mut count = 0;
+    while tokenizer.next_token().is_some() {
+        count += 1;
+    }
+    count
+}
+
+fn bench_snippets(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize");
+
+    for (name, source) in SNIPPETS {
+        group.bench_function(*name, |b| b.iter(|| tokenize(black_box(source))));
+    }
+
+    // A larger script, built by repeating every snippet
+    let large = SNIPPETS
+        .iter()
+        .map(|(_, source)| *source)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .repeat(200);
+
+    group.bench_function("all-snippets-x200", |b| b.iter(|| tokenize(black_box(&large))));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_snippets);
+criterion_main!(benches);
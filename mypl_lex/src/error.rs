@@ -0,0 +1,77 @@
+use thiserror::Error;
+
+use crate::span::Span;
+
+#[derive(Error, Clone, PartialEq, Debug)]
+pub enum LexError {
+    #[error("Unknown token \"{0}\"")]
+    UnknownToken(String, Span),
+
+    #[error("Unterminated string literal")]
+    UnterminatedString(Span),
+
+    #[error("Unterminated block comment")]
+    UnterminatedComment(Span),
+
+    #[error("Unterminated interpolation, expected \"}}\"")]
+    UnterminatedInterpolation(Span),
+
+    #[error("Unterminated character literal")]
+    UnterminatedChar(Span),
+
+    #[error("Invalid character literal \"{0}\", it must hold a single character")]
+    InvalidChar(String, Span),
+
+    #[error("Invalid escape sequence \"{0}\"")]
+    InvalidEscape(String, Span),
+
+    #[error("Invalid numeric literal \"{0}\"")]
+    InvalidNumber(String, Span),
+
+    #[error("Invalid numeric suffix \"{0}\"")]
+    InvalidSuffix(String, Span),
+
+    #[error("Numeric literal \"{0}\" is out of range")]
+    NumberOverflow(String, Span),
+
+    #[error("Failed reading the source: {0}")]
+    Io(String, Span),
+}
+
+impl LexError {
+    pub fn span(&self) -> &Span {
+        match self {
+            LexError::UnknownToken(_, span) => span,
+            LexError::UnterminatedString(span) => span,
+            LexError::UnterminatedComment(span) => span,
+            LexError::UnterminatedChar(span) => span,
+            LexError::UnterminatedInterpolation(span) => span,
+            LexError::InvalidChar(_, span) => span,
+            LexError::InvalidEscape(_, span) => span,
+            LexError::InvalidNumber(_, span) => span,
+            LexError::InvalidSuffix(_, span) => span,
+            LexError::NumberOverflow(_, span) => span,
+            LexError::Io(_, span) => span,
+        }
+    }
+
+    /// The same error, with its span moved by a number of bytes
+    pub fn shifted(&self, delta: isize) -> LexError {
+        let mut error = self.clone();
+        let span = match &mut error {
+            LexError::UnknownToken(_, span) => span,
+            LexError::UnterminatedString(span) => span,
+            LexError::UnterminatedComment(span) => span,
+            LexError::UnterminatedChar(span) => span,
+            LexError::UnterminatedInterpolation(span) => span,
+            LexError::InvalidChar(_, span) => span,
+            LexError::InvalidEscape(_, span) => span,
+            LexError::InvalidNumber(_, span) => span,
+            LexError::InvalidSuffix(_, span) => span,
+            LexError::NumberOverflow(_, span) => span,
+            LexError::Io(_, span) => span,
+        };
+        *span = span.shifted(delta);
+        error
+    }
+}
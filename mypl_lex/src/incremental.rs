@@ -0,0 +1,216 @@
+use std::ops::Range;
+
+use crate::error::LexError;
+use crate::span::FileId;
+use crate::token::Token;
+use crate::tokenizer::Tokenizer;
+
+/// A change of the source, the bytes in `start..end` are replaced by `text`
+#[derive(Clone, PartialEq, Debug)]
+pub struct TextEdit {
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(start: usize, end: usize, text: impl Into<String>) -> Self {
+        Self {
+            start,
+            end,
+            text: text.into(),
+        }
+    }
+
+    /// How many bytes the source after the edit moves by
+    pub fn delta(&self) -> isize {
+        self.text.len() as isize - (self.end - self.start) as isize
+    }
+}
+
+/// Keeps the tokens of a source up to date as it is edited, which is what an editor needs.
+///
+/// The Tokenizer holds no state besides its position, so lexing can restart at the start of any
+/// token. An edit is re-lexed from the token before it, until a token lines up with an old token
+/// after the edit. From there on the old tokens are kept, with their spans shifted.
+pub struct IncrementalTokenizer {
+    file: FileId,
+    source: String,
+    tokens: Vec<Token>,
+    errors: Vec<LexError>,
+}
+
+impl IncrementalTokenizer {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self::with_file(source, FileId::default())
+    }
+
+    pub fn with_file(source: impl Into<String>, file: FileId) -> Self {
+        let source = source.into();
+        let (tokens, errors) = {
+            let mut tokenizer = Tokenizer::with_file(&source, file);
+            let tokens = tokenizer.by_ref().collect();
+            (tokens, tokenizer.errors().to_vec())
+        };
+
+        Self {
+            file,
+            source,
+            tokens,
+            errors,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    /// Applies the edit to the source and re-lexes the tokens it damaged.
+    /// Returns the range of the re-lexed tokens, the tokens after it are the old ones.
+    ///
+    /// Panics when the edit is out of the source, or does not lie on char boundaries.
+    pub fn edit(&mut self, edit: &TextEdit) -> Range<usize> {
+        let delta = edit.delta();
+
+        // The token right before the edit is re-lexed too, the edit may extend it or change
+        // what it looked ahead at. There is always a token that ends after the edit, the Eof.
+        let first = self.tokens
+            .iter()
+            .position(|token| token.span.end() >= edit.start)
+            .unwrap_or(self.tokens.len())
+            .saturating_sub(1);
+        let restart = self.tokens.get(first).map_or(0, |token| token.span.start()).min(edit.start);
+
+        self.source.replace_range(edit.start..edit.end, &edit.text);
+        let edit_end = edit.start + edit.text.len();
+
+        let mut tokenizer = Tokenizer::with_file(&self.source[restart..], self.file);
+        let mut relexed = Vec::new();
+        let mut old = first;
+        let mut synced = None;
+
+        for token in tokenizer.by_ref() {
+            let token = token.shifted(restart as isize);
+
+            if token.span.start() >= edit_end {
+                // Skip the old tokens that are damaged, or start before the new token would
+                while self.tokens.get(old).is_some_and(|old| {
+                    old.span.start() < edit.end || old.span.start().saturating_add_signed(delta) < token.span.start()
+                }) {
+                    old += 1;
+                }
+
+                if self.tokens.get(old).is_some_and(|old| old.clone().shifted(delta) == token) {
+                    synced = Some((old, token.span.start()));
+                    break;
+                }
+            }
+
+            relexed.push(token);
+        }
+
+        let (tail_start, sync_position) = synced.unwrap_or((self.tokens.len(), self.source.len() + 1));
+
+        // Errors of the synced token belong to the old tokens
+        let new_errors = tokenizer
+            .errors()
+            .iter()
+            .map(|error| error.shifted(restart as isize))
+            .filter(|error| error.span().start() < sync_position)
+            .collect::<Vec<_>>();
+
+        let old_sync_position = sync_position.saturating_add_signed(-delta);
+        let old_errors = std::mem::take(&mut self.errors);
+        let (before, after): (Vec<_>, Vec<_>) = old_errors
+            .into_iter()
+            .filter(|error| error.span().start() < restart || (synced.is_some() && error.span().start() >= old_sync_position))
+            .partition(|error| error.span().start() < restart);
+
+        self.errors = before;
+        self.errors.extend(new_errors);
+        self.errors.extend(after.into_iter().map(|error| error.shifted(delta)));
+
+        let tail = self.tokens.split_off(tail_start);
+        self.tokens.truncate(first);
+
+        let range = first..first + relexed.len();
+        self.tokens.extend(relexed);
+        self.tokens.extend(tail.into_iter().map(|token| token.shifted(delta)));
+        range
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenKind;
+
+    fn assert_matches_tokenizer(incremental: &IncrementalTokenizer) {
+        let mut tokenizer = Tokenizer::new(incremental.source());
+        let tokens = tokenizer.by_ref().collect::<Vec<_>>();
+
+        assert_eq!(incremental.tokens(), tokens);
+        assert_eq!(incremental.errors(), tokenizer.errors());
+    }
+
+    #[test]
+    fn relex_damaged_tokens_only() {
+        let source = include_str!("../../resources/valid-snippets/program-1.mypl");
+        let mut incremental = IncrementalTokenizer::new(source);
+
+        // Rename `radius` in `var radius = 1.;`
+        let start = source.find("var radius").unwrap() + 4;
+        let range = incremental.edit(&TextEdit::new(start, start + 6, "r"));
+
+        assert_matches_tokenizer(&incremental);
+        assert!(range.len() <= 3, "re-lexed {:?}", range);
+        assert!(incremental.tokens()[range].iter().any(|token| token.kind == TokenKind::Identifier("r".into())));
+    }
+
+    #[test]
+    fn relex_edits_that_change_later_tokens() {
+        let mut incremental = IncrementalTokenizer::new("const x = 1; $ var y = \"a\"; z");
+
+        let edits = [
+            // Opens a block comment that swallows the rest of the source
+            TextEdit::new(13, 13, "/*"),
+            // Closes it again
+            TextEdit::new(24, 24, "*/"),
+            // Joins two tokens
+            TextEdit::new(5, 6, ""),
+            // Removes the comment opening, leaving its end as two tokens
+            TextEdit::new(11, 15, ""),
+            // Appends an unterminated char at the end
+            TextEdit::new(28, 28, " + 'c"),
+            // Prepends a template
+            TextEdit::new(0, 0, "\"{y}\" "),
+        ];
+
+        for edit in &edits {
+            incremental.edit(edit);
+            assert_matches_tokenizer(&incremental);
+        }
+    }
+
+    #[test]
+    fn relex_every_single_edit() {
+        let source = "var x = 1..=2; /* c */ @println \"{x}\" 'a' r\"s\";\n// end";
+
+        for start in 0..source.len() {
+            for text in ["", "\"", "/*", "'", "{", ".", "x", " "] {
+                let end = if text.is_empty() { start + 1 } else { start };
+                let mut incremental = IncrementalTokenizer::new(source);
+                incremental.edit(&TextEdit::new(start, end, text));
+                assert_matches_tokenizer(&incremental);
+            }
+        }
+    }
+}
@@ -1,13 +1,23 @@
 extern crate anyhow;
-extern crate regex;
+extern crate thiserror;
 
+mod error;
+mod incremental;
+mod source_map;
 mod span;
+mod stream;
 mod token;
 mod tokenizer;
+mod trivia;
 
 pub mod prelude {
     use super::*;
-    pub use span::Span;
+    pub use error::LexError;
+    pub use incremental::{IncrementalTokenizer, TextEdit};
+    pub use source_map::{Location, SourceFile, SourceMap};
+    pub use span::{FileId, Span};
     pub use token::*;
+    pub use stream::StreamTokenizer;
     pub use tokenizer::Tokenizer;
+    pub use trivia::{LosslessToken, LosslessTokenizer, Trivia, TriviaKind};
 }
@@ -0,0 +1,176 @@
+use crate::span::{FileId, Span};
+
+/// A human readable position within a source file.
+/// Both line and column are 1-based, and the column counts characters, not bytes.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+pub struct SourceFile {
+    id: FileId,
+    name: String,
+    source: String,
+
+    // Byte offsets of the first character of every line
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(id: FileId, name: impl Into<String>, source: impl Into<String>) -> Self {
+        let source = source.into();
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+
+        Self {
+            id,
+            name: name.into(),
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn id(&self) -> FileId {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The location of a byte offset.
+    /// Offsets past the end of the source (like the one of Eof) are clamped to it.
+    pub fn location(&self, offset: usize) -> Location {
+        let offset = self.floor_char_boundary(offset);
+        let line_index = self.line_index(offset);
+        let line_start = self.line_starts[line_index];
+
+        Location {
+            line: line_index + 1,
+            column: self.source[line_start..offset].chars().count() + 1,
+        }
+    }
+
+    /// The text of a 1-based line, without the line terminator
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let end = self.line_starts
+            .get(line)
+            .map(|next| next - 1)
+            .unwrap_or(self.source.len());
+
+        Some(self.source[start..end].trim_end_matches('\r'))
+    }
+
+    fn line_index(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        }
+    }
+
+    fn floor_char_boundary(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+/// Owns all the sources of a run and maps Spans back to them
+#[derive(Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&mut self, name: impl Into<String>, source: impl Into<String>) -> FileId {
+        let id = FileId::new(self.files.len());
+        self.files.push(SourceFile::new(id, name, source));
+        id
+    }
+
+    pub fn get_file(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.index())
+    }
+
+    pub fn location(&self, span: &Span) -> Option<Location> {
+        self.get_file(span.file())
+            .map(|file| file.location(span.start()))
+    }
+
+    /// Formats the start of a span as "file.mypl:12:5"
+    pub fn format_span(&self, span: &Span) -> String {
+        match self.get_file(span.file()) {
+            Some(file) => format!("{}:{}", file.name(), file.location(span.start())),
+            None => format!("<unknown>:{}", span.start()),
+        }
+    }
+
+    /// The source line of the span start, followed by a marker under the spanned characters
+    pub fn format_snippet(&self, span: &Span) -> Option<String> {
+        let file = self.get_file(span.file())?;
+        let start = file.location(span.start());
+        let text = file.line_text(start.line)?;
+
+        let width = match file.location(span.end()) {
+            end if end.line == start.line => (end.column - start.column).max(1),
+            _ => text.chars().count().saturating_sub(start.column - 1).max(1),
+        };
+
+        Some(format!("{}\n{}{}", text, " ".repeat(start.column - 1), "^".repeat(width)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_of_offsets() {
+        let file = SourceFile::new(FileId::default(), "a.mypl", "const x = 1;\nvar yé = 2;\n");
+
+        assert_eq!(file.location(0), Location { line: 1, column: 1 });
+        assert_eq!(file.location(6), Location { line: 1, column: 7 });
+        assert_eq!(file.location(13), Location { line: 2, column: 1 });
+        // "é" is two bytes but a single column
+        assert_eq!(file.location(20), Location { line: 2, column: 7 });
+        // Eof is clamped
+        assert_eq!(file.location(100), Location { line: 3, column: 1 });
+
+        assert_eq!(file.line_text(2), Some("var yé = 2;"));
+        assert_eq!(file.line_text(3), Some(""));
+        assert_eq!(file.line_text(4), None);
+    }
+
+    #[test]
+    fn format_spans_of_multiple_files() {
+        let mut source_map = SourceMap::new();
+        let a = source_map.add_file("a.mypl", "const x = 1;");
+        let b = source_map.add_file("b.mypl", "\n\n  @println x;");
+
+        assert_eq!(source_map.format_span(&Span::with_file(a, 6, 7)), "a.mypl:1:7");
+        assert_eq!(source_map.format_span(&Span::with_file(b, 13, 14)), "b.mypl:3:12");
+        assert_eq!(
+            source_map.format_snippet(&Span::with_file(b, 4, 12)).unwrap(),
+            "  @println x;\n  ^^^^^^^^"
+        );
+    }
+}
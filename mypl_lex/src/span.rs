@@ -1,5 +1,21 @@
-#[derive(Clone, PartialEq, Debug)]
+/// Identifies the source file a Span originated from, see SourceMap
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct FileId(pub(crate) usize);
+
+impl FileId {
+    pub fn new(index: usize) -> Self {
+        Self(index)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
 pub struct Span {
+    pub(crate) file: FileId,
+
     // Inclusive
     pub(crate) start: usize,
 
@@ -9,16 +25,53 @@ pub struct Span {
 
 impl From<(usize, usize)> for Span {
     fn from((start, end): (usize, usize)) -> Self {
-        Self { start, end }
+        Self::new(start, end)
     }
 }
 
 impl Span {
     pub fn new(start: usize, end: usize) -> Self {
-        Self { start, end }
+        Self::with_file(FileId::default(), start, end)
+    }
+
+    pub fn with_file(file: FileId, start: usize, end: usize) -> Self {
+        Self { file, start, end }
+    }
+
+    pub fn file(&self) -> FileId {
+        self.file
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
     }
 
     pub fn get_size(&self) -> usize {
         self.end - self.start
     }
+
+    /// The smallest span that covers both spans
+    pub fn merge(&self, other: &Span) -> Span {
+        debug_assert_eq!(self.file, other.file, "cannot merge spans of different files");
+        Span::with_file(self.file, self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// The span from the start of this span up to the end of the other
+    pub fn to(&self, other: &Span) -> Span {
+        debug_assert_eq!(self.file, other.file, "cannot join spans of different files");
+        Span::with_file(self.file, self.start, other.end.max(self.start))
+    }
+
+    /// The same span, moved by a number of bytes
+    pub fn shifted(&self, delta: isize) -> Span {
+        Span::with_file(
+            self.file,
+            self.start.saturating_add_signed(delta),
+            self.end.saturating_add_signed(delta),
+        )
+    }
 }
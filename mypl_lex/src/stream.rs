@@ -0,0 +1,159 @@
+use std::collections::VecDeque;
+use std::io::BufRead;
+
+use crate::error::LexError;
+use crate::span::{FileId, Span};
+use crate::token::{Token, TokenKind};
+use crate::tokenizer::Tokenizer;
+
+// The default amount of bytes read from the input before lexing
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Tokenizes any BufRead in chunks, without loading the whole input first.
+///
+/// Every chunk is lexed by a Tokenizer. A token that reaches the end of the chunk may continue
+/// in the next one (an identifier, or a string that spans lines), so it is lexed again once more
+/// input is read. Spans are offsets within the whole input, like the ones of a Tokenizer.
+pub struct StreamTokenizer<R: BufRead> {
+    reader: R,
+    file: FileId,
+    chunk_size: usize,
+
+    // Input that was read but not yet tokenized, starting at `offset` within the whole input
+    buffer: String,
+    offset: usize,
+
+    is_exhausted: bool,
+    is_done: bool,
+    pending: VecDeque<Token>,
+    errors: Vec<LexError>,
+}
+
+impl<R: BufRead> StreamTokenizer<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_file(reader, FileId::default())
+    }
+
+    pub fn with_file(reader: R, file: FileId) -> Self {
+        Self {
+            reader,
+            file,
+            chunk_size: CHUNK_SIZE,
+            buffer: String::new(),
+            offset: 0,
+            is_exhausted: false,
+            is_done: false,
+            pending: VecDeque::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Sets the amount of bytes read before lexing. Input is always read in whole lines.
+    pub fn with_chunk_size(self, chunk_size: usize) -> Self {
+        Self { chunk_size, ..self }
+    }
+
+    /// Lexical errors of the tokens returned so far
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    pub fn next_token(&mut self) -> Option<Token> {
+        while self.pending.is_empty() && !self.is_done {
+            self.read_chunk();
+            self.tokenize_buffer();
+        }
+
+        self.pending.pop_front()
+    }
+
+    // Reads whole lines until there is at least a chunk worth of input
+    fn read_chunk(&mut self) {
+        let target = self.buffer.len() + self.chunk_size;
+        while !self.is_exhausted && self.buffer.len() < target {
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => self.is_exhausted = true,
+                Ok(_) => {}
+                Err(err) => {
+                    let position = self.offset + self.buffer.len();
+                    let span = Span::with_file(self.file, position, position);
+                    self.errors.push(LexError::Io(err.to_string(), span));
+                    self.is_exhausted = true;
+                }
+            }
+        }
+    }
+
+    fn tokenize_buffer(&mut self) {
+        let mut tokenizer = Tokenizer::with_file(&self.buffer, self.file);
+        let mut tokens = Vec::new();
+
+        // Where the tokens that are safe to return end
+        let mut cut = self.buffer.len();
+
+        for token in tokenizer.by_ref() {
+            let is_eof = token.kind == TokenKind::Eof;
+            let may_continue = is_eof || token.span.end() >= self.buffer.len();
+
+            if may_continue && !self.is_exhausted {
+                cut = token.span.start();
+                break;
+            }
+
+            self.is_done = is_eof;
+            tokens.push(token);
+        }
+
+        let delta = self.offset as isize;
+
+        self.errors.extend(tokenizer
+            .errors()
+            .iter()
+            .filter(|error| self.is_done || error.span().start() < cut)
+            .map(|error| error.shifted(delta)));
+
+        self.pending.extend(tokens.into_iter().map(|token| token.shifted(delta)));
+
+        self.buffer.drain(..cut);
+        self.offset += cut;
+    }
+}
+
+impl<R: BufRead> Iterator for StreamTokenizer<R> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.next_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stream_matches_tokenizer() {
+        let source = include_str!("../../resources/valid-snippets/program-1.mypl")
+            .repeat(300);
+
+        let expected = Tokenizer::new(&source).collect::<Vec<_>>();
+        let actual = StreamTokenizer::new(source.as_bytes()).collect::<Vec<_>>();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn stream_tokens_across_reads() {
+        let source = "const identifier = \"multi\nline\" $\n;\n// comment\n";
+
+        // Every line is lexed on its own
+        let mut stream = StreamTokenizer::new(source.as_bytes()).with_chunk_size(1);
+        let actual = stream.by_ref().collect::<Vec<_>>();
+
+        let mut tokenizer = Tokenizer::new(source);
+        let expected = tokenizer.by_ref().collect::<Vec<_>>();
+
+        assert_eq!(actual, expected);
+        assert_eq!(stream.errors(), tokenizer.errors());
+    }
+}
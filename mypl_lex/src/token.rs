@@ -1,20 +1,115 @@
 use crate::span::Span;
 
+/// The type suffix of a numeric literal, like the `u8` in `255u8`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NumericSuffix {
+    U32,
+    U16,
+    U8,
+
+    I32,
+    I16,
+    I8,
+
+    F32,
+    F16,
+}
+
+impl NumericSuffix {
+    pub fn from_code(code: &str) -> Option<Self> {
+        use NumericSuffix::*;
+        match code {
+            "u32" => Some(U32),
+            "u16" => Some(U16),
+            "u8" => Some(U8),
+            "i32" => Some(I32),
+            "i16" => Some(I16),
+            "i8" => Some(I8),
+            "f32" => Some(F32),
+            "f16" => Some(F16),
+            _ => None,
+        }
+    }
+
+    pub fn as_code(&self) -> &str {
+        use NumericSuffix::*;
+        match self {
+            U32 => "u32",
+            U16 => "u16",
+            U8 => "u8",
+            I32 => "i32",
+            I16 => "i16",
+            I8 => "i8",
+            F32 => "f32",
+            F16 => "f16",
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, NumericSuffix::F32 | NumericSuffix::F16)
+    }
+
+    /// Whether the value of a literal is representable by the suffixed type.
+    /// Literals are never negative, so signed types also accept the magnitude of their minimum,
+    /// which is what allows writing `-128i8`.
+    pub fn contains_integer(&self, value: i128) -> bool {
+        use NumericSuffix::*;
+        match self {
+            U32 => u32::try_from(value).is_ok(),
+            U16 => u16::try_from(value).is_ok(),
+            U8 => u8::try_from(value).is_ok(),
+            I32 => (0..=i32::MIN.unsigned_abs() as i128).contains(&value),
+            I16 => (0..=i16::MIN.unsigned_abs() as i128).contains(&value),
+            I8 => (0..=i8::MIN.unsigned_abs() as i128).contains(&value),
+            F32 | F16 => false,
+        }
+    }
+
+    /// Whether the value is finite once converted to the suffixed type
+    pub fn contains_float(&self, value: f64) -> bool {
+        match self {
+            NumericSuffix::F32 => (value as f32).is_finite(),
+            // f16::MAX
+            NumericSuffix::F16 => value.abs() <= 65504.0,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Clone, PartialEq, Debug)]
 pub enum Literal {
     String(String),
+    Char(char),
     Bool(bool),
-    Integer(i128),
-    Float(f64),
+    Integer(i128, Option<NumericSuffix>),
+    Float(f64, Option<NumericSuffix>),
 }
 
+/// A segment of an interpolated string literal, like `"c1 = {c1}"`
 #[derive(Clone, PartialEq, Debug)]
+pub enum TemplatePart {
+    Text(String),
+    // The tokens of an embedded expression, ending with an Eof at its closing brace
+    Expr(Vec<Token>),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CommentKind {
+    // `// ...`
+    Line,
+    // `/* ... */`, which may nest
+    Block,
+    // `/// ...`, documenting the declaration that follows
+    Doc,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum DelimDir {
     Open,
     Close,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum DelimType {
     Paren,
     Brace,
@@ -36,6 +131,9 @@ pub enum Keyword {
     In,
     Match,
     Return,
+    While,
+    Break,
+    Continue,
 
     U32,
     U16,
@@ -64,6 +162,8 @@ pub enum TokenKind {
     Le,
     // ==
     EqEq,
+    // =>
+    FatArrow,
     // !=
     Ne,
     // >=
@@ -112,6 +212,8 @@ pub enum TokenKind {
     Dot,
     // ..
     DotDot,
+    // ..=
+    DotDotEq,
     // ,
     Comma,
     // :
@@ -119,10 +221,12 @@ pub enum TokenKind {
     // ;
     SemiColon,
 
-    Comment(String),
+    Comment(CommentKind, String),
 
     Keyword(Keyword),
     Literal(Literal),
+    // A string literal with embedded expressions
+    Template(Vec<TemplatePart>),
     Identifier(String),
 
     Delim(DelimDir, DelimType),
@@ -154,3 +258,27 @@ pub struct Token {
     pub kind: TokenKind,
     pub span: Span,
 }
+
+impl Token {
+    /// The same token, with its span, and the spans of tokens embedded in it, moved by a number of bytes
+    pub fn shifted(self, delta: isize) -> Token {
+        let kind = match self.kind {
+            TokenKind::Template(parts) => TokenKind::Template(parts
+                .into_iter()
+                .map(|part| match part {
+                    TemplatePart::Expr(tokens) => TemplatePart::Expr(tokens
+                        .into_iter()
+                        .map(|token| token.shifted(delta))
+                        .collect()),
+                    text => text,
+                })
+                .collect()),
+            kind => kind,
+        };
+
+        Token {
+            kind,
+            span: self.span.shifted(delta),
+        }
+    }
+}
@@ -1,199 +1,48 @@
-use anyhow::Result;
-
-use crate::span::Span;
-use crate::token::{Keyword, Token, TokenKind, DelimDir, DelimType, Literal};
-
-use regex::Regex;
-
-// Tokenization Rules
-
-trait TokenizationRule {
-    fn tokenize(&self, source: &SourceReader) -> Option<Token>;
-}
-
-struct SimpleTokenizationRule {
-    create: fn(source: &SourceReader) -> Option<Token>,
-}
-
-impl SimpleTokenizationRule {
-    fn boxed(create: fn(source: &SourceReader) -> Option<Token>) -> Box<Self> {
-        Box::new(Self { create })
-    }
-}
-
-struct ExactTokenizationRule {
-    exact: String,
-    kind: TokenKind,
-}
-
-impl ExactTokenizationRule {
-    fn boxed(exact: impl AsRef<str>, kind: TokenKind) -> Box<Self> {
-        Box::new(Self {
-            exact: exact.as_ref().into(),
-            kind,
-        })
-    }
-}
-
-impl TokenizationRule for ExactTokenizationRule {
-    fn tokenize(&self, source: &SourceReader) -> Option<Token> {
-        if !source.as_ref().starts_with(&self.exact) {
-            None
-        } else {
-            Some(Token {
-                kind: self.kind.clone(),
-                span: source.make_span(self.exact.len()),
-            })
-        }
-    }
-}
-
-impl TokenizationRule for SimpleTokenizationRule {
-    fn tokenize(&self, source: &SourceReader) -> Option<Token> {
-        (self.create)(source)
-    }
-}
-
-struct RegexTokenizationRule {
-    regex: Regex,
-    create: fn(regex::Match) -> TokenKind,
-}
-
-impl RegexTokenizationRule {
-    fn boxed(pattern: &str, create: fn(regex::Match) -> TokenKind) -> Result<Box<Self>> {
-        let regex = Regex::new(pattern)?;
-        Ok(Box::new(Self { regex, create }))
-    }
-}
-
-impl TokenizationRule for RegexTokenizationRule {
-    fn tokenize(&self, source: &SourceReader) -> Option<Token> {
-        self.regex
-            .captures(source.as_ref())
-            .and_then(|captures| captures.get(0))
-            .map(|capture| Token {
-                kind: (self.create)(capture),
-                span: Span::new(source.get_position(), source.get_position() + capture.len()),
-            })
-    }
-}
-
-fn create_tokenization_rules() -> Result<Vec<Box<dyn TokenizationRule>>> {
-    Ok(vec![
-        // Eof
-        SimpleTokenizationRule::boxed(|source| {
-            source.is_eof().then(|| Token {
-                kind: TokenKind::Eof,
-                span: source.make_span(1),
-            })
-        }),
-        // Comments
-        RegexTokenizationRule::boxed("^//.*", |capture| {
-            TokenKind::Comment(capture.as_str().into())
-        })?,
-
-        // Keywords
-        ExactTokenizationRule::boxed("const", TokenKind::Keyword(Keyword::Const)),
-        ExactTokenizationRule::boxed("var", TokenKind::Keyword(Keyword::Var)),
-        ExactTokenizationRule::boxed("record", TokenKind::Keyword(Keyword::Record)),
-        ExactTokenizationRule::boxed("union", TokenKind::Keyword(Keyword::Union)),
-        ExactTokenizationRule::boxed("impl", TokenKind::Keyword(Keyword::Impl)),
-        ExactTokenizationRule::boxed("trait", TokenKind::Keyword(Keyword::Trait)),
-        ExactTokenizationRule::boxed("mod", TokenKind::Keyword(Keyword::Mod)),
-        ExactTokenizationRule::boxed("if", TokenKind::Keyword(Keyword::If)),
-        ExactTokenizationRule::boxed("else", TokenKind::Keyword(Keyword::Else)),
-        ExactTokenizationRule::boxed("for", TokenKind::Keyword(Keyword::For)),
-        ExactTokenizationRule::boxed("in", TokenKind::Keyword(Keyword::In)),
-        ExactTokenizationRule::boxed("match", TokenKind::Keyword(Keyword::Match)),
-        ExactTokenizationRule::boxed("return", TokenKind::Keyword(Keyword::Return)),
-        ExactTokenizationRule::boxed("u32", TokenKind::Keyword(Keyword::U32)),
-        ExactTokenizationRule::boxed("u16", TokenKind::Keyword(Keyword::U16)),
-        ExactTokenizationRule::boxed("u8", TokenKind::Keyword(Keyword::U8)),
-        ExactTokenizationRule::boxed("i32", TokenKind::Keyword(Keyword::I32)),
-        ExactTokenizationRule::boxed("i16", TokenKind::Keyword(Keyword::I16)),
-        ExactTokenizationRule::boxed("i8", TokenKind::Keyword(Keyword::I8)),
-        ExactTokenizationRule::boxed("f32", TokenKind::Keyword(Keyword::F32)),
-        ExactTokenizationRule::boxed("f16", TokenKind::Keyword(Keyword::F16)),
-
-        // Prints. Order is important
-        ExactTokenizationRule::boxed("@println", TokenKind::Keyword(Keyword::Println)),
-        ExactTokenizationRule::boxed("@print", TokenKind::Keyword(Keyword::Print)),
-        
-        // Double Character
-        ExactTokenizationRule::boxed("==", TokenKind::EqEq),
-        ExactTokenizationRule::boxed("<=", TokenKind::Le),
-        ExactTokenizationRule::boxed(">=", TokenKind::Ge),
-        ExactTokenizationRule::boxed("!=", TokenKind::Ne),
-        ExactTokenizationRule::boxed("..", TokenKind::DotDot),
-        ExactTokenizationRule::boxed("&&", TokenKind::AndAnd),
-        ExactTokenizationRule::boxed("||", TokenKind::OrOr),
-        ExactTokenizationRule::boxed(">>", TokenKind::GtGt),
-        ExactTokenizationRule::boxed("<<", TokenKind::LtLt),
-        ExactTokenizationRule::boxed("+=", TokenKind::PlusEq),
-        ExactTokenizationRule::boxed("-=", TokenKind::MinusEq),
-        ExactTokenizationRule::boxed("*=", TokenKind::StarEq),
-        ExactTokenizationRule::boxed("/=", TokenKind::SlashEq),
-        ExactTokenizationRule::boxed("&=", TokenKind::AndEq),
-        ExactTokenizationRule::boxed("|=", TokenKind::OrEq),
-
-        // Single Characters
-        ExactTokenizationRule::boxed("=", TokenKind::Eq),
-        ExactTokenizationRule::boxed("<", TokenKind::Lt),
-        ExactTokenizationRule::boxed(">", TokenKind::Gt),
-        ExactTokenizationRule::boxed("!", TokenKind::Not),
-        ExactTokenizationRule::boxed(".", TokenKind::Dot),
-        ExactTokenizationRule::boxed(",", TokenKind::Comma),
-        ExactTokenizationRule::boxed(":", TokenKind::Colon),
-        ExactTokenizationRule::boxed(";", TokenKind::SemiColon),
-        ExactTokenizationRule::boxed("+", TokenKind::Plus),
-        ExactTokenizationRule::boxed("-", TokenKind::Minus),
-        ExactTokenizationRule::boxed("*", TokenKind::Star),
-        ExactTokenizationRule::boxed("/", TokenKind::Slash),
-        ExactTokenizationRule::boxed("%", TokenKind::Percent),
-        ExactTokenizationRule::boxed("^", TokenKind::Caret),
-        ExactTokenizationRule::boxed("&", TokenKind::And),
-        ExactTokenizationRule::boxed("|", TokenKind::Or),
-        ExactTokenizationRule::boxed("(", TokenKind::Delim(DelimDir::Open, DelimType::Paren)),
-        ExactTokenizationRule::boxed("{", TokenKind::Delim(DelimDir::Open, DelimType::Brace)),
-        ExactTokenizationRule::boxed("[", TokenKind::Delim(DelimDir::Open, DelimType::Brack)),
-        ExactTokenizationRule::boxed(")", TokenKind::Delim(DelimDir::Close, DelimType::Paren)),
-        ExactTokenizationRule::boxed("}", TokenKind::Delim(DelimDir::Close, DelimType::Brace)),
-        ExactTokenizationRule::boxed("]", TokenKind::Delim(DelimDir::Close, DelimType::Brack)),
-
-        // String Literal
-        RegexTokenizationRule::boxed("^\".*\"", |capture| {
-            // It would be nice to enhance the api such that we can extract specifice match groups
-            TokenKind::Literal(Literal::String(capture.as_str()[1..capture.len()-1].to_string()))
-        })?,
-
-        // Float Literal 
-        // Notice that we only tokenize positive floats - is this good?
-        RegexTokenizationRule::boxed("^\\d+\\.\\d+|^\\d+\\.|^\\.\\d+", |capture| {
-            TokenKind::Literal(Literal::Float(capture.as_str().parse::<f64>()
-                                              .expect("Couldn't parse f64 after regex match")))
-        })?,
-
-        // Integer Literal
-        // Notice that we only tokenize positive integers - is this good?
-        RegexTokenizationRule::boxed("^\\d+", |capture| {
-            TokenKind::Literal(Literal::Integer(capture.as_str().parse::<i128>()
-                                                .expect("Couldn't parse u64 after regex match")))
-        })?,
-
-        // Bool Literal
-        ExactTokenizationRule::boxed("true", TokenKind::Literal(Literal::Bool(true))),
-        ExactTokenizationRule::boxed("false", TokenKind::Literal(Literal::Bool(false))),
-
-        // Identifier
-        RegexTokenizationRule::boxed("^[a-zA-Z]\\w*", |capture| {
-            TokenKind::Identifier(capture.as_str().to_string())
-        })?,
-    ])
-}
+use crate::error::LexError;
+use crate::span::{FileId, Span};
+use crate::token::{CommentKind, Keyword, Token, TokenKind, DelimDir, DelimType, Literal, NumericSuffix, TemplatePart};
+
+// Keywords and word-like literals.
+// These are looked up only after a whole word is scanned, so `constant` is an identifier.
+const KEYWORDS: &[(&str, TokenKind)] = &[
+    ("const", TokenKind::Keyword(Keyword::Const)),
+    ("var", TokenKind::Keyword(Keyword::Var)),
+    ("record", TokenKind::Keyword(Keyword::Record)),
+    ("union", TokenKind::Keyword(Keyword::Union)),
+    ("impl", TokenKind::Keyword(Keyword::Impl)),
+    ("trait", TokenKind::Keyword(Keyword::Trait)),
+    ("mod", TokenKind::Keyword(Keyword::Mod)),
+    ("if", TokenKind::Keyword(Keyword::If)),
+    ("else", TokenKind::Keyword(Keyword::Else)),
+    ("for", TokenKind::Keyword(Keyword::For)),
+    ("in", TokenKind::Keyword(Keyword::In)),
+    ("match", TokenKind::Keyword(Keyword::Match)),
+    ("return", TokenKind::Keyword(Keyword::Return)),
+    ("while", TokenKind::Keyword(Keyword::While)),
+    ("break", TokenKind::Keyword(Keyword::Break)),
+    ("continue", TokenKind::Keyword(Keyword::Continue)),
+    ("u32", TokenKind::Keyword(Keyword::U32)),
+    ("u16", TokenKind::Keyword(Keyword::U16)),
+    ("u8", TokenKind::Keyword(Keyword::U8)),
+    ("i32", TokenKind::Keyword(Keyword::I32)),
+    ("i16", TokenKind::Keyword(Keyword::I16)),
+    ("i8", TokenKind::Keyword(Keyword::I8)),
+    ("f32", TokenKind::Keyword(Keyword::F32)),
+    ("f16", TokenKind::Keyword(Keyword::F16)),
+    ("true", TokenKind::Literal(Literal::Bool(true))),
+    ("false", TokenKind::Literal(Literal::Bool(false))),
+];
+
+// Prints
+const DIRECTIVES: &[(&str, TokenKind)] = &[
+    ("@println", TokenKind::Keyword(Keyword::Println)),
+    ("@print", TokenKind::Keyword(Keyword::Print)),
+];
 
 // SourceReader
 
 pub struct SourceReader<'a> {
+    file: FileId,
     position: usize,
     source: &'a str,
 }
@@ -207,53 +56,100 @@ impl<'a> SourceReader<'a> {
         self.position > self.source.len()
     }
 
-    pub fn get_position(&self) -> usize {
-        self.position
+    pub fn make_span(&self, size: usize) -> Span {
+        self.make_span_at(0, size)
     }
 
-    pub fn make_span(&self, size: usize) -> Span {
-        Span::new(self.position, self.position + size)
+    /// A span that starts at an offset from the current position
+    pub fn make_span_at(&self, offset: usize, size: usize) -> Span {
+        let start = self.position + offset;
+        Span::with_file(self.file, start, start + size)
     }
 
     pub fn advance(&mut self, size: usize) {
         self.position += size
     }
+
+    /// The character at the current position
+    pub fn peek(&self) -> Option<char> {
+        self.as_ref().chars().next()
+    }
+
+    /// The character right after the current one
+    pub fn peek_next(&self) -> Option<char> {
+        self.as_ref().chars().nth(1)
+    }
+
+    /// The source from the current position, borrowed for as long as the source itself
+    pub fn remaining(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    /// The size in bytes of the longest prefix whose characters satisfy the predicate
+    pub fn prefix_size<P: Fn(char) -> bool>(&self, offset: usize, predicate: P) -> usize {
+        let rest = &self.as_ref()[offset..];
+        rest.find(|c| !predicate(c)).unwrap_or(rest.len())
+    }
 }
 
 impl<'a> AsRef<str> for SourceReader<'a> {
     fn as_ref(&self) -> &str {
-        &self.source[self.position..]
+        self.remaining()
     }
 }
 
 impl<'a> From<&'a str> for SourceReader<'a> {
     fn from(source: &'a str) -> Self {
         Self {
+            file: FileId::default(),
             position: 0,
             source,
         }
     }
 }
 
+fn is_whitespace(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | ' ')
+}
+
+// Identifiers follow Unicode XID, and may also start with an underscore
+fn is_word_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+fn is_word(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
 pub struct Tokenizer<'a> {
     source: SourceReader<'a>,
-    rules: Vec<Box<dyn TokenizationRule>>,
-    whitespace_regex: Regex,
+    errors: Vec<LexError>,
 }
 
 impl<'a> Tokenizer<'a> {
     pub fn new(source: &'a str) -> Self {
         Self {
             source: source.into(),
+            errors: Vec::new(),
+        }
+    }
 
-            rules: create_tokenization_rules()
-                .expect("failed creating rules - happens only cause of code issues, fix it"),
-
-            // This won't panic, we know it compiles.
-            whitespace_regex: Regex::new(r"^[\t\n\r ]+").unwrap(),
+    /// Creates a tokenizer whose spans refer to the given file of a SourceMap
+    pub fn with_file(source: &'a str, file: FileId) -> Self {
+        Self {
+            source: SourceReader {
+                file,
+                ..source.into()
+            },
+            errors: Vec::new(),
         }
     }
 
+    /// Lexical errors found so far. Tokenization goes on after an error.
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
     pub fn next_token(&mut self) -> Option<Token> {
         if self.source.did_pass_eof() {
             return None;
@@ -261,19 +157,774 @@ impl<'a> Tokenizer<'a> {
 
         self.advance_whitespace();
 
-        for rule in &self.rules {
-            if let Some(token) = rule.tokenize(&self.source) {
-                self.source.advance(token.span.get_size());
-                return Some(token);
+        let token = self.scan_token();
+        self.source.advance(token.span.get_size());
+        Some(token)
+    }
+
+    fn advance_whitespace(&mut self) {
+        let size = self.source.prefix_size(0, is_whitespace);
+        self.source.advance(size);
+    }
+
+    fn make_token(&self, kind: TokenKind, size: usize) -> Token {
+        Token {
+            kind,
+            span: self.source.make_span(size),
+        }
+    }
+
+    // Choose between a double and a single character token based on the next character
+    fn one_or_two(&self, pairs: &[(char, TokenKind)], single: TokenKind) -> Token {
+        let next = self.source.peek_next();
+        match pairs.iter().find(|(c, _)| Some(*c) == next) {
+            Some((_, kind)) => self.make_token(kind.clone(), 2),
+            None => self.make_token(single, 1),
+        }
+    }
+
+    fn scan_token(&mut self) -> Token {
+        use TokenKind::*;
+
+        if self.source.is_eof() {
+            return self.make_token(Eof, 1);
+        }
+
+        let c = self.source.peek().expect("not eof, so there is a character");
+
+        match c {
+            '=' => self.one_or_two(&[('=', EqEq), ('>', FatArrow)], Eq),
+            '<' => self.one_or_two(&[('=', Le), ('<', LtLt)], Lt),
+            '>' => self.one_or_two(&[('=', Ge), ('>', GtGt)], Gt),
+            '!' => self.one_or_two(&[('=', Ne)], Not),
+            '.' if self.source.as_ref().starts_with("..=") => self.make_token(DotDotEq, 3),
+            // A float may leave out the digits before its dot, as in .5
+            '.' if self.source.as_ref()[1..].starts_with(|c: char| c.is_ascii_digit()) => self.scan_number(),
+            '.' => self.one_or_two(&[('.', DotDot)], Dot),
+            '&' => self.one_or_two(&[('&', AndAnd), ('=', AndEq)], And),
+            '|' => self.one_or_two(&[('|', OrOr), ('=', OrEq)], Or),
+            '+' => self.one_or_two(&[('=', PlusEq)], Plus),
+            '-' => self.one_or_two(&[('=', MinusEq)], Minus),
+            '*' => self.one_or_two(&[('=', StarEq)], Star),
+            '/' if self.source.peek_next() == Some('/') => self.scan_comment(),
+            '/' if self.source.peek_next() == Some('*') => self.scan_block_comment(),
+            '/' => self.one_or_two(&[('=', SlashEq)], Slash),
+            '%' => self.make_token(Percent, 1),
+            '^' => self.make_token(Caret, 1),
+            ',' => self.make_token(Comma, 1),
+            ':' => self.make_token(Colon, 1),
+            ';' => self.make_token(SemiColon, 1),
+            '(' => self.make_token(Delim(DelimDir::Open, DelimType::Paren), 1),
+            '{' => self.make_token(Delim(DelimDir::Open, DelimType::Brace), 1),
+            '[' => self.make_token(Delim(DelimDir::Open, DelimType::Brack), 1),
+            ')' => self.make_token(Delim(DelimDir::Close, DelimType::Paren), 1),
+            '}' => self.make_token(Delim(DelimDir::Close, DelimType::Brace), 1),
+            ']' => self.make_token(Delim(DelimDir::Close, DelimType::Brack), 1),
+            '@' => self.scan_directive(),
+            '"' => self.scan_string(),
+            '\'' => self.scan_char(),
+            'r' if self.is_raw_string() => self.scan_raw_string(),
+            c if c.is_ascii_digit() => self.scan_number(),
+            c if is_word_start(c) => self.scan_word(),
+            c => self.scan_unknown(c.len_utf8()),
+        }
+    }
+
+    fn scan_directive(&mut self) -> Token {
+        let size = 1 + self.source.prefix_size(1, is_word);
+        let lexeme = &self.source.as_ref()[..size];
+        match DIRECTIVES.iter().find(|(directive, _)| *directive == lexeme) {
+            Some((_, kind)) => self.make_token(kind.clone(), size),
+            None => self.scan_unknown(size),
+        }
+    }
+
+    // Unknown characters are reported, and lexing goes on after them
+    fn scan_unknown(&mut self, size: usize) -> Token {
+        let lexeme = self.source.as_ref()[..size].to_string();
+        let token = self.make_token(TokenKind::Unknown(lexeme.clone()), size);
+        self.errors.push(LexError::UnknownToken(lexeme, token.span));
+        token
+    }
+
+    fn scan_comment(&self) -> Token {
+        let size = self.source.prefix_size(0, |c| c != '\n');
+        let lexeme = &self.source.as_ref()[..size];
+
+        // Exactly three slashes, `////` is a regular comment
+        let kind = if lexeme.starts_with("///") && !lexeme.starts_with("////") {
+            CommentKind::Doc
+        } else {
+            CommentKind::Line
+        };
+
+        self.make_token(TokenKind::Comment(kind, lexeme.into()), size)
+    }
+
+    fn scan_block_comment(&mut self) -> Token {
+        let source = self.source.as_ref();
+        let mut depth = 0;
+        let mut index = 0;
+
+        while index < source.len() {
+            if source[index..].starts_with("/*") {
+                depth += 1;
+                index += 2;
+            } else if source[index..].starts_with("*/") {
+                depth -= 1;
+                index += 2;
+                if depth == 0 {
+                    let lexeme = &source[..index];
+                    return self.make_token(TokenKind::Comment(CommentKind::Block, lexeme.into()), index);
+                }
+            } else {
+                index += source[index..].chars().next().map_or(1, char::len_utf8);
             }
         }
 
-        None
+        self.errors.push(LexError::UnterminatedComment(self.source.make_span(source.len())));
+        self.make_token(TokenKind::Comment(CommentKind::Block, source.into()), source.len())
     }
 
-    fn advance_whitespace(&mut self) {
-        if let Some(c) = self.whitespace_regex.captures(self.source.as_ref()) {
-            self.source.advance(c.get(0).unwrap().len());
+    // Maximal munch, the whole word is scanned and only then checked against the keywords
+    fn scan_word(&self) -> Token {
+        let size = self.source.prefix_size(0, is_word);
+        let lexeme = &self.source.as_ref()[..size];
+        let kind = KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == lexeme)
+            .map(|(_, kind)| kind.clone())
+            .unwrap_or_else(|| TokenKind::Identifier(lexeme.into()));
+        self.make_token(kind, size)
+    }
+
+    // Strings with `{expression}` segments are lexed into a Template, `{{` and `}}` escape braces
+    fn scan_string(&mut self) -> Token {
+        let source = self.source.remaining();
+        let mut value = String::new();
+        let mut parts = Vec::new();
+        let mut chars = source.char_indices().skip(1).peekable();
+
+        let mut size = None;
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '"' => {
+                    size = Some(index + 1);
+                    break;
+                }
+                '\\' => match self.scan_escape(source, index, &mut chars) {
+                    Ok(c) => value.push(c),
+                    Err(error) => self.errors.push(error),
+                },
+                '{' | '}' if chars.next_if(|(_, next)| *next == c).is_some() => value.push(c),
+                '{' => {
+                    if !value.is_empty() {
+                        parts.push(TemplatePart::Text(std::mem::take(&mut value)));
+                    }
+
+                    let end = self.scan_interpolation(source, index, &mut parts);
+                    while chars.next_if(|(index, _)| *index < end).is_some() {}
+                }
+                c => value.push(c),
+            }
+        }
+
+        let size = size.unwrap_or_else(|| {
+            self.errors.push(LexError::UnterminatedString(self.source.make_span(source.len())));
+            source.len()
+        });
+
+        let kind = if parts.is_empty() {
+            TokenKind::Literal(Literal::String(value))
+        } else {
+            if !value.is_empty() {
+                parts.push(TemplatePart::Text(value));
+            }
+            TokenKind::Template(parts)
+        };
+
+        self.make_token(kind, size)
+    }
+
+    // Lexes the expression of the interpolation that starts with the brace at `start`.
+    // Returns where the interpolation ends, the expression is pushed only when it is terminated.
+    fn scan_interpolation(&mut self, source: &str, start: usize, parts: &mut Vec<TemplatePart>) -> usize {
+        let mut depth = 0;
+        let end = source[start + 1..].char_indices().find_map(|(index, c)| match c {
+            '{' => {
+                depth += 1;
+                None
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                None
+            }
+            '}' => Some(Ok(start + 1 + index)),
+            // Expressions can't hold strings or span lines
+            '"' | '\n' => Some(Err(start + 1 + index)),
+            _ => None,
+        });
+
+        let close = match end {
+            Some(Ok(close)) => close,
+            Some(Err(end)) => {
+                self.errors.push(LexError::UnterminatedInterpolation(self.source.make_span_at(start, end - start)));
+                return end;
+            }
+            None => {
+                self.errors.push(LexError::UnterminatedInterpolation(self.source.make_span_at(start, source.len() - start)));
+                return source.len();
+            }
+        };
+
+        let delta = self.source.make_span_at(start + 1, 0).start() as isize;
+        let mut tokenizer = Tokenizer::with_file(&source[start + 1..close], self.source.file);
+        let tokens = tokenizer
+            .by_ref()
+            .filter(|token| !matches!(token.kind, TokenKind::Comment(..)))
+            .map(|token| token.shifted(delta))
+            .collect();
+
+        self.errors.extend(tokenizer.errors().iter().map(|error| error.shifted(delta)));
+        parts.push(TemplatePart::Expr(tokens));
+        close + 1
+    }
+
+    fn scan_char(&mut self) -> Token {
+        let source = self.source.as_ref();
+        let mut chars = source.char_indices().skip(1).peekable();
+
+        let value = match chars.next() {
+            Some((index, '\\')) => self.scan_escape(source, index, &mut chars).unwrap_or_else(|error| {
+                self.errors.push(error);
+                char::REPLACEMENT_CHARACTER
+            }),
+            Some((index, '\'')) => {
+                self.errors.push(LexError::InvalidChar(source[..=index].into(), self.source.make_span(index + 1)));
+                return self.make_token(TokenKind::Literal(Literal::Char(char::REPLACEMENT_CHARACTER)), index + 1);
+            }
+            Some((_, c)) if c != '\n' => c,
+            _ => {
+                self.errors.push(LexError::UnterminatedChar(self.source.make_span(1)));
+                return self.make_token(TokenKind::Literal(Literal::Char(char::REPLACEMENT_CHARACTER)), 1);
+            }
+        };
+
+        let rest = match chars.next() {
+            Some((index, '\'')) => return self.make_token(TokenKind::Literal(Literal::Char(value)), index + 1),
+            Some((index, _)) => index,
+            None => source.len(),
+        };
+
+        // More than a single character, up to a closing quote on the same line, or to its end
+        let line_end = source[rest..].find('\n').map_or(source.len(), |end| rest + end);
+        let size = match source[rest..line_end].find('\'') {
+            Some(quote) => {
+                let size = rest + quote + 1;
+                self.errors.push(LexError::InvalidChar(source[..size].into(), self.source.make_span(size)));
+                size
+            }
+            None => {
+                self.errors.push(LexError::UnterminatedChar(self.source.make_span(line_end)));
+                line_end
+            }
+        };
+
+        self.make_token(TokenKind::Literal(Literal::Char(char::REPLACEMENT_CHARACTER)), size)
+    }
+
+    // Decodes the escape sequence that starts with the backslash at `start`
+    fn scan_escape<I>(&self, source: &str, start: usize, chars: &mut std::iter::Peekable<I>) -> Result<char, LexError>
+    where
+        I: Iterator<Item = (usize, char)>,
+    {
+        let invalid = |end: usize| {
+            LexError::InvalidEscape(source[start..end].to_string(), self.source.make_span_at(start, end - start))
+        };
+
+        let (index, c) = chars.next().ok_or_else(|| invalid(source.len()))?;
+        let end = index + c.len_utf8();
+
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '\'' => Ok('\''),
+            'u' => {
+                if chars.next_if(|(_, c)| *c == '{').is_none() {
+                    return Err(invalid(end));
+                }
+
+                let mut digits = String::new();
+                let end = loop {
+                    match chars.next() {
+                        Some((index, '}')) => break index + 1,
+                        Some((_, c)) if c.is_ascii_hexdigit() && digits.len() < 6 => digits.push(c),
+                        Some((index, c)) => return Err(invalid(index + c.len_utf8())),
+                        None => return Err(invalid(source.len())),
+                    }
+                };
+
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| invalid(end))
+            }
+            _ => Err(invalid(end)),
+        }
+    }
+
+    // r"...", or r#"..."# with any number of hashes when the string contains quotes
+    fn is_raw_string(&self) -> bool {
+        self.source.as_ref()[1..].trim_start_matches('#').starts_with('"')
+    }
+
+    fn scan_raw_string(&mut self) -> Token {
+        let source = self.source.as_ref();
+        let hashes = self.source.prefix_size(1, |c| c == '#');
+        let start = 1 + hashes + 1;
+        let terminator = format!("\"{}", "#".repeat(hashes));
+
+        match source[start..].find(&terminator) {
+            Some(end) => {
+                let value = source[start..start + end].to_string();
+                let size = start + end + terminator.len();
+                self.make_token(TokenKind::Literal(Literal::String(value)), size)
+            }
+            None => {
+                self.errors.push(LexError::UnterminatedString(self.source.make_span(source.len())));
+                let value = source[start..].to_string();
+                self.make_token(TokenKind::Literal(Literal::String(value)), source.len())
+            }
+        }
+    }
+
+    // Notice that we only tokenize positive numbers - is this good?
+    fn scan_number(&mut self) -> Token {
+        let source = self.source.as_ref();
+
+        let (radix, prefix) = match source.get(..2) {
+            Some("0x") => (16, 2),
+            Some("0o") => (8, 2),
+            Some("0b") => (2, 2),
+            _ => (10, 0),
+        };
+
+        let is_digit = |c: char| c.is_ascii_digit() || c == '_';
+        let mut end = prefix + self.source.prefix_size(prefix, |c| c.is_digit(radix) || c == '_');
+        let mut is_float = false;
+
+        if radix == 10 {
+            // A dot followed by another dot is a range, as in 1..5
+            if source[end..].starts_with('.') && !source[end..].starts_with("..") {
+                is_float = true;
+                end += 1;
+                end += self.source.prefix_size(end, is_digit);
+            }
+
+            // The exponent marker, with an optional sign, must be followed by a digit
+            let marker = match &source.as_bytes()[end..] {
+                [b'e' | b'E', b'+' | b'-', digit, ..] if digit.is_ascii_digit() => 2,
+                [b'e' | b'E', digit, ..] if digit.is_ascii_digit() => 1,
+                _ => 0,
+            };
+
+            if marker > 0 {
+                is_float = true;
+                end += marker;
+                end += self.source.prefix_size(end, is_digit);
+            }
+        }
+
+        let suffix_size = self.source.prefix_size(end, is_word);
+        let size = end + suffix_size;
+        let lexeme = &source[..size];
+        let digits = source[prefix..end].replace('_', "");
+
+        let suffix = match &source[end..size] {
+            "" => None,
+            code => {
+                let suffix = NumericSuffix::from_code(code)
+                    .filter(|suffix| !is_float || suffix.is_float());
+                if suffix.is_none() {
+                    let span = self.source.make_span_at(end, suffix_size);
+                    self.errors.push(LexError::InvalidSuffix(code.into(), span));
+                }
+                suffix
+            }
+        };
+
+        let span = self.source.make_span(size);
+        let is_float = is_float || suffix.is_some_and(|suffix| suffix.is_float());
+
+        let kind = if digits.is_empty() || (is_float && radix != 10) {
+            self.errors.push(LexError::InvalidNumber(lexeme.into(), span));
+            TokenKind::Literal(Literal::Integer(0, suffix))
+        } else if is_float {
+            let value = digits.parse::<f64>().unwrap_or_else(|_| {
+                self.errors.push(LexError::InvalidNumber(lexeme.into(), span));
+                0.0
+            });
+            if !suffix.map_or(value.is_finite(), |suffix| suffix.contains_float(value)) {
+                self.errors.push(LexError::NumberOverflow(lexeme.into(), span));
+            }
+            TokenKind::Literal(Literal::Float(value, suffix))
+        } else {
+            let value = i128::from_str_radix(&digits, radix)
+                .ok()
+                .filter(|value| suffix.is_none_or(|suffix| suffix.contains_integer(*value)));
+            if value.is_none() {
+                self.errors.push(LexError::NumberOverflow(lexeme.into(), span));
+            }
+            TokenKind::Literal(Literal::Integer(value.unwrap_or_default(), suffix))
+        };
+
+        self.make_token(kind, size)
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.next_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(source: &str) -> Vec<TokenKind> {
+        let mut tokenizer = Tokenizer::new(source);
+        let mut kinds = Vec::new();
+        while let Some(token) = tokenizer.next_token() {
+            kinds.push(token.kind);
+        }
+        kinds
+    }
+
+    #[test]
+    fn tokenize_double_chars() {
+        use TokenKind::*;
+        let source = include_str!("../../resources/test-snippets/double-chars.mypl");
+        assert_eq!(tokenize(source), vec![
+            EqEq,
+            Le, FatArrow,
+            LtLt, GtGt,
+            Ne,
+            PlusEq, MinusEq,
+            StarEq, SlashEq,
+            Percent, Eq, Caret, Eq, AndEq, OrEq,
+            Eof,
+        ]);
+    }
+
+    #[test]
+    fn tokenize_statement() {
+        let mut tokenizer = Tokenizer::new("var x = 1.5; // done");
+        let token = tokenizer.next_token().unwrap();
+        assert_eq!(token.span, Span::new(0, 3));
+
+        assert_eq!(tokenize("var x = 1.5; // done"), vec![
+            TokenKind::Keyword(Keyword::Var),
+            TokenKind::Identifier("x".into()),
+            TokenKind::Eq,
+            TokenKind::Literal(Literal::Float(1.5, None)),
+            TokenKind::SemiColon,
+            TokenKind::Comment(CommentKind::Line, "// done".into()),
+            TokenKind::Eof,
+        ]);
+    }
+
+    #[test]
+    fn tokenize_keywords() {
+        use crate::token::Keyword::*;
+
+        let keyword = TokenKind::Keyword;
+        let identifier = |name: &str| TokenKind::Identifier(name.into());
+        let source = include_str!("../../resources/test-snippets/keywords.mypl");
+
+        let kinds = tokenize(source)
+            .into_iter()
+            .filter(|kind| !matches!(kind, TokenKind::Comment(..)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(kinds, vec![
+            keyword(U32), keyword(I8),
+            keyword(U8), keyword(I32),
+            keyword(For), keyword(If),
+            keyword(Record), keyword(Union),
+            keyword(Const), keyword(Var),
+            keyword(U16), keyword(I16), keyword(F32), keyword(F16),
+            keyword(Impl), keyword(Trait), keyword(Mod), keyword(Else),
+            keyword(In), keyword(Match), keyword(Return),
+            keyword(While), keyword(Break), keyword(Continue),
+            TokenKind::Literal(Literal::Bool(true)), TokenKind::Literal(Literal::Bool(false)),
+            identifier("constant"), identifier("index"), identifier("iffy"), identifier("u8x"),
+            identifier("trueish"), identifier("variable"), identifier("records"),
+            identifier("co"), identifier("va"), identifier("i"), identifier("u"),
+            TokenKind::Eof,
+        ]);
+    }
+
+    #[test]
+    fn tokenize_keyword_prefixed_identifier_spans() {
+        let mut tokenizer = Tokenizer::new("constant = index;");
+
+        let token = tokenizer.next_token().unwrap();
+        assert_eq!(token.kind, TokenKind::Identifier("constant".into()));
+        assert_eq!(token.span, Span::new(0, 8));
+
+        tokenizer.next_token();
+
+        let token = tokenizer.next_token().unwrap();
+        assert_eq!(token.kind, TokenKind::Identifier("index".into()));
+        assert_eq!(token.span, Span::new(11, 16));
+    }
+
+    fn tokenize_with_errors(source: &str) -> (Vec<TokenKind>, Vec<LexError>) {
+        let mut tokenizer = Tokenizer::new(source);
+        let mut kinds = Vec::new();
+        while let Some(token) = tokenizer.next_token() {
+            kinds.push(token.kind);
         }
+        (kinds, tokenizer.errors().to_vec())
+    }
+
+    fn string(value: &str) -> TokenKind {
+        TokenKind::Literal(Literal::String(value.into()))
+    }
+
+    #[test]
+    fn tokenize_strings_non_greedy() {
+        use crate::token::Keyword::Print;
+        assert_eq!(tokenize(r#"@print "a"; @print "b";"#), vec![
+            TokenKind::Keyword(Print), string("a"), TokenKind::SemiColon,
+            TokenKind::Keyword(Print), string("b"), TokenKind::SemiColon,
+            TokenKind::Eof,
+        ]);
+    }
+
+    #[test]
+    fn tokenize_string_escapes() {
+        assert_eq!(
+            tokenize(r#""a\n\t\"b\"\\ \u{48}\u{1F600}""#),
+            vec![string("a\n\t\"b\"\\ H\u{1F600}"), TokenKind::Eof],
+        );
+
+        let (kinds, errors) = tokenize_with_errors(r#""a\qb" "\u{110000}""#);
+        assert_eq!(kinds, vec![string("ab"), string(""), TokenKind::Eof]);
+        assert_eq!(errors, vec![
+            LexError::InvalidEscape(r"\q".into(), Span::new(2, 4)),
+            LexError::InvalidEscape(r"\u{110000}".into(), Span::new(8, 18)),
+        ]);
+    }
+
+    #[test]
+    fn tokenize_templates() {
+        let mut tokenizer = Tokenizer::new(r#"x = "c1 = {c1}, {{area}} = {pi * (r)}";"#);
+        let tokens = tokenizer.by_ref().map(|token| token.kind).collect::<Vec<_>>();
+        assert!(tokenizer.errors().is_empty());
+
+        let TokenKind::Template(parts) = &tokens[2] else {
+            panic!("expected a template, got {:?}", tokens[2]);
+        };
+
+        let expr = |part: &TemplatePart| match part {
+            TemplatePart::Expr(tokens) => tokens.iter().map(|t| (t.kind.clone(), t.span)).collect::<Vec<_>>(),
+            _ => panic!("expected an expression, got {:?}", part),
+        };
+
+        assert_eq!(parts.len(), 4);
+        assert_eq!(parts[0], TemplatePart::Text("c1 = ".into()));
+        assert_eq!(expr(&parts[1]), vec![
+            (TokenKind::Identifier("c1".into()), Span::new(11, 13)),
+            (TokenKind::Eof, Span::new(13, 14)),
+        ]);
+        assert_eq!(parts[2], TemplatePart::Text(", {area} = ".into()));
+        assert_eq!(expr(&parts[3]).len(), 6);
+        assert_eq!(tokens[3], TokenKind::SemiColon);
+
+        // Strings without interpolations stay plain strings
+        assert_eq!(tokenize(r#""{{}}""#), vec![string("{}"), TokenKind::Eof]);
+    }
+
+    #[test]
+    fn tokenize_unterminated_interpolation() {
+        let (kinds, errors) = tokenize_with_errors(r#""a {b" 1"#);
+        assert_eq!(kinds, vec![
+            TokenKind::Template(vec![TemplatePart::Text("a ".into())]),
+            integer(1, None),
+            TokenKind::Eof,
+        ]);
+        assert_eq!(errors, vec![LexError::UnterminatedInterpolation(Span::new(3, 5))]);
+    }
+
+    #[test]
+    fn tokenize_multiline_and_raw_strings() {
+        let mut tokenizer = Tokenizer::new("\"first\nsecond\" r\"\\n\" r#\"say \"hi\"\"#");
+
+        let token = tokenizer.next_token().unwrap();
+        assert_eq!(token.kind, string("first\nsecond"));
+        assert_eq!(token.span, Span::new(0, 14));
+
+        assert_eq!(tokenizer.next_token().unwrap().kind, string("\\n"));
+        assert_eq!(tokenizer.next_token().unwrap().kind, string("say \"hi\""));
+        assert_eq!(tokenizer.next_token().unwrap().kind, TokenKind::Eof);
+    }
+
+    #[test]
+    fn tokenize_unterminated_string() {
+        let (kinds, errors) = tokenize_with_errors("x = \"abc;\n");
+        assert_eq!(kinds, vec![
+            TokenKind::Identifier("x".into()),
+            TokenKind::Eq,
+            string("abc;\n"),
+            TokenKind::Eof,
+        ]);
+        assert_eq!(errors, vec![LexError::UnterminatedString(Span::new(4, 10))]);
+    }
+
+    fn integer(value: i128, suffix: Option<NumericSuffix>) -> TokenKind {
+        TokenKind::Literal(Literal::Integer(value, suffix))
+    }
+
+    fn float(value: f64, suffix: Option<NumericSuffix>) -> TokenKind {
+        TokenKind::Literal(Literal::Float(value, suffix))
+    }
+
+    #[test]
+    fn tokenize_numbers() {
+        use NumericSuffix::*;
+        assert_eq!(
+            tokenize("0xFF 0b1010 0o17 1_000_000 1e-3 2.5E+2 3. .25e1 255u8 1.5f32 7f16 -128i8 0x7f_i32"),
+            vec![
+                integer(255, None), integer(10, None), integer(15, None), integer(1_000_000, None),
+                float(1e-3, None), float(250., None), float(3., None), float(2.5, None),
+                integer(255, Some(U8)), float(1.5, Some(F32)), float(7., Some(F16)),
+                TokenKind::Minus, integer(128, Some(I8)), integer(127, Some(I32)),
+                TokenKind::Eof,
+            ],
+        );
+    }
+
+    #[test]
+    fn tokenize_ranges() {
+        assert_eq!(
+            tokenize("0..1000 .5 1..5"),
+            vec![
+                integer(0, None), TokenKind::DotDot, integer(1000, None), float(0.5, None),
+                integer(1, None), TokenKind::DotDot, integer(5, None), TokenKind::Eof,
+            ],
+        );
+        assert_eq!(
+            tokenize("0..=9...x"),
+            vec![integer(0, None), TokenKind::DotDotEq, integer(9, None), TokenKind::DotDot, TokenKind::Dot, TokenKind::Identifier("x".into()), TokenKind::Eof],
+        );
+    }
+
+    #[test]
+    fn tokenize_invalid_numbers() {
+        let (kinds, errors) = tokenize_with_errors("256u8 1.5u8 12abc 0x 129i8 1e40f32 999999999999999999999999999999999999999999");
+        assert_eq!(kinds.len(), 8);
+        assert_eq!(errors, vec![
+            LexError::NumberOverflow("256u8".into(), Span::new(0, 5)),
+            LexError::InvalidSuffix("u8".into(), Span::new(9, 11)),
+            LexError::InvalidSuffix("abc".into(), Span::new(14, 17)),
+            LexError::InvalidNumber("0x".into(), Span::new(18, 20)),
+            LexError::NumberOverflow("129i8".into(), Span::new(21, 26)),
+            LexError::NumberOverflow("1e40f32".into(), Span::new(27, 34)),
+            LexError::NumberOverflow("999999999999999999999999999999999999999999".into(), Span::new(35, 77)),
+        ]);
+    }
+
+    #[test]
+    fn tokenize_unknown_tokens() {
+        let (kinds, errors) = tokenize_with_errors("x = $ 1; # @printx y;");
+        assert_eq!(kinds, vec![
+            TokenKind::Identifier("x".into()),
+            TokenKind::Eq,
+            TokenKind::Unknown("$".into()),
+            integer(1, None),
+            TokenKind::SemiColon,
+            TokenKind::Unknown("#".into()),
+            TokenKind::Unknown("@printx".into()),
+            TokenKind::Identifier("y".into()),
+            TokenKind::SemiColon,
+            TokenKind::Eof,
+        ]);
+        assert_eq!(errors, vec![
+            LexError::UnknownToken("$".into(), Span::new(4, 5)),
+            LexError::UnknownToken("#".into(), Span::new(9, 10)),
+            LexError::UnknownToken("@printx".into(), Span::new(11, 18)),
+        ]);
+    }
+
+    #[test]
+    fn tokenize_unicode_identifiers() {
+        let identifier = |name: &str| TokenKind::Identifier(name.into());
+        assert_eq!(tokenize("_x __ ñandú 变量 x٣ é1"), vec![
+            identifier("_x"),
+            identifier("__"),
+            identifier("ñandú"),
+            identifier("变量"),
+            identifier("x٣"),
+            identifier("é1"),
+            TokenKind::Eof,
+        ]);
+
+        // Digits and emoji can't start an identifier
+        let (kinds, _) = tokenize_with_errors("٣x 😀");
+        assert_eq!(kinds[0], TokenKind::Unknown("٣".into()));
+        assert_eq!(kinds[1], identifier("x"));
+        assert_eq!(kinds[2], TokenKind::Unknown("😀".into()));
+    }
+
+    #[test]
+    fn tokenize_chars() {
+        let char = |c| TokenKind::Literal(Literal::Char(c));
+        assert_eq!(tokenize(r"'a' 'é' '\n' '\'' '\u{1F600}' '\\'"), vec![
+            char('a'), char('é'), char('\n'), char('\''), char('\u{1F600}'), char('\\'),
+            TokenKind::Eof,
+        ]);
+
+        let (kinds, errors) = tokenize_with_errors("'' 'ab' '\\q' 'c\nx");
+        assert_eq!(kinds, vec![
+            char(char::REPLACEMENT_CHARACTER),
+            char(char::REPLACEMENT_CHARACTER),
+            char(char::REPLACEMENT_CHARACTER),
+            char(char::REPLACEMENT_CHARACTER),
+            TokenKind::Identifier("x".into()),
+            TokenKind::Eof,
+        ]);
+        assert_eq!(errors, vec![
+            LexError::InvalidChar("''".into(), Span::new(0, 2)),
+            LexError::InvalidChar("'ab'".into(), Span::new(3, 7)),
+            LexError::InvalidEscape(r"\q".into(), Span::new(9, 11)),
+            LexError::UnterminatedChar(Span::new(13, 15)),
+        ]);
+    }
+
+    #[test]
+    fn tokenize_comments() {
+        let comment = |kind, lexeme: &str| TokenKind::Comment(kind, lexeme.into());
+        let source = "/// docs\n//// line\n/* outer /* inner */ still outer */ x /* é */";
+
+        assert_eq!(tokenize(source), vec![
+            comment(CommentKind::Doc, "/// docs"),
+            comment(CommentKind::Line, "//// line"),
+            comment(CommentKind::Block, "/* outer /* inner */ still outer */"),
+            TokenKind::Identifier("x".into()),
+            comment(CommentKind::Block, "/* é */"),
+            TokenKind::Eof,
+        ]);
+
+        let (kinds, errors) = tokenize_with_errors("x /* a /* b */");
+        assert_eq!(kinds[1], comment(CommentKind::Block, "/* a /* b */"));
+        assert_eq!(errors, vec![LexError::UnterminatedComment(Span::new(2, 14))]);
     }
 }
@@ -0,0 +1,216 @@
+use crate::error::LexError;
+use crate::span::{FileId, Span};
+use crate::token::{CommentKind, Token, TokenKind};
+use crate::tokenizer::Tokenizer;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment(CommentKind),
+}
+
+/// Source text that carries no meaning for the grammar, but is needed to reproduce the source
+#[derive(Clone, PartialEq, Debug)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+/// A token along with its exact source text and the trivia around it.
+///
+/// Trailing trivia is everything after the token up to and including the end of its line,
+/// leading trivia is the rest of the trivia before the token.
+/// Concatenating the leading trivia, text and trailing trivia of all tokens gives back the source.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LosslessToken {
+    pub token: Token,
+    pub text: String,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+impl LosslessToken {
+    pub fn write_text(&self, out: &mut String) {
+        self.leading.iter().for_each(|trivia| out.push_str(&trivia.text));
+        out.push_str(&self.text);
+        self.trailing.iter().for_each(|trivia| out.push_str(&trivia.text));
+    }
+}
+
+/// A Tokenizer mode that preserves whitespace and comments as trivia
+pub struct LosslessTokenizer<'a> {
+    source: &'a str,
+    file: FileId,
+    tokenizer: Tokenizer<'a>,
+
+    // Where the previous token, or trivia, ended
+    position: usize,
+
+    // The next token along with its leading trivia, read while looking for trailing trivia
+    next: Option<(Vec<Trivia>, Token)>,
+}
+
+impl<'a> LosslessTokenizer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self::with_file(source, FileId::default())
+    }
+
+    pub fn with_file(source: &'a str, file: FileId) -> Self {
+        Self {
+            source,
+            file,
+            tokenizer: Tokenizer::with_file(source, file),
+            position: 0,
+            next: None,
+        }
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        self.tokenizer.errors()
+    }
+
+    pub fn next_token(&mut self) -> Option<LosslessToken> {
+        let (leading, token) = match self.next.take() {
+            Some(next) => next,
+            None => self.read_trivia()?,
+        };
+
+        let end = token.span.end().min(self.source.len());
+        let text = self.source[token.span.start().min(end)..end].to_string();
+
+        let mut trailing = Vec::new();
+        if token.kind != TokenKind::Eof {
+            if let Some((mut trivia, next)) = self.read_trivia() {
+                let rest = self.split_trailing(&mut trivia);
+                trailing = trivia;
+                self.next = Some((rest, next));
+            }
+        }
+
+        Some(LosslessToken {
+            token,
+            text,
+            leading,
+            trailing,
+        })
+    }
+
+    // Reads trivia up to the next token that is not a comment
+    fn read_trivia(&mut self) -> Option<(Vec<Trivia>, Token)> {
+        let mut trivia = Vec::new();
+
+        loop {
+            let token = self.tokenizer.next_token()?;
+
+            self.push_whitespace(&mut trivia, token.span.start().min(self.source.len()));
+            self.position = token.span.end().min(self.source.len());
+
+            match token.kind {
+                TokenKind::Comment(kind, text) => trivia.push(Trivia {
+                    kind: TriviaKind::Comment(kind),
+                    text,
+                    span: token.span,
+                }),
+                _ => return Some((trivia, token)),
+            }
+        }
+    }
+
+    fn push_whitespace(&mut self, trivia: &mut Vec<Trivia>, end: usize) {
+        if self.position < end {
+            trivia.push(Trivia {
+                kind: TriviaKind::Whitespace,
+                text: self.source[self.position..end].to_string(),
+                span: Span::with_file(self.file, self.position, end),
+            });
+        }
+    }
+
+    // Keeps the trivia up to the end of the line in place, and returns the rest
+    fn split_trailing(&self, trivia: &mut Vec<Trivia>) -> Vec<Trivia> {
+        let Some(index) = trivia.iter().position(|t| t.text.contains('\n')) else {
+            return Vec::new();
+        };
+
+        let mut rest = trivia.split_off(index);
+        if rest[0].kind != TriviaKind::Whitespace {
+            return rest;
+        }
+
+        // Split the whitespace right after the line ends
+        let first = rest.remove(0);
+        let split = first.text.find('\n').expect("contains a new line") + 1;
+        let start = first.span.start();
+
+        trivia.push(Trivia {
+            kind: TriviaKind::Whitespace,
+            text: first.text[..split].to_string(),
+            span: Span::with_file(self.file, start, start + split),
+        });
+
+        if split < first.text.len() {
+            rest.insert(0, Trivia {
+                kind: TriviaKind::Whitespace,
+                text: first.text[split..].to_string(),
+                span: Span::with_file(self.file, start + split, first.span.end()),
+            });
+        }
+
+        rest
+    }
+}
+
+impl<'a> Iterator for LosslessTokenizer<'a> {
+    type Item = LosslessToken;
+
+    fn next(&mut self) -> Option<LosslessToken> {
+        self.next_token()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reproduce(source: &str) -> String {
+        let mut out = String::new();
+        LosslessTokenizer::new(source).for_each(|token| token.write_text(&mut out));
+        out
+    }
+
+    #[test]
+    fn reproduce_snippets() {
+        let sources = [
+            include_str!("../../resources/valid-snippets/program-1.mypl"),
+            include_str!("../../resources/test-snippets/comments.mypl"),
+            include_str!("../../resources/test-snippets/empty-file.mypl"),
+            "  \n\t x = \"a\\n\" /* b */ $ ;// c\n\n",
+        ];
+
+        for source in sources {
+            assert_eq!(reproduce(source), source);
+        }
+    }
+
+    #[test]
+    fn attach_leading_and_trailing_trivia() {
+        let tokens = LosslessTokenizer::new("// head\nx = 1; // tail\n  \n/* next */ y")
+            .collect::<Vec<_>>();
+
+        let texts = |trivia: &[Trivia]| trivia.iter().map(|t| t.text.clone()).collect::<Vec<_>>();
+
+        assert_eq!(tokens[0].text, "x");
+        assert_eq!(texts(&tokens[0].leading), vec!["// head", "\n"]);
+        assert_eq!(texts(&tokens[0].trailing), vec![" "]);
+
+        assert_eq!(tokens[3].text, ";");
+        assert_eq!(texts(&tokens[3].trailing), vec![" ", "// tail", "\n"]);
+
+        assert_eq!(tokens[4].text, "y");
+        assert_eq!(texts(&tokens[4].leading), vec!["  \n", "/* next */", " "]);
+
+        assert_eq!(tokens[5].token.kind, TokenKind::Eof);
+        assert_eq!(tokens[5].text, "");
+    }
+}
@@ -0,0 +1,243 @@
+use std::iter::Peekable;
+
+use mypl_lex::prelude::*;
+
+/// The kinds of nodes of the concrete syntax tree
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SyntaxKind {
+    Program,
+    Decl,
+    PrintStmt,
+    AssignStmt,
+    ExprStmt,
+    // Tokens between matching delimiters, the delimiters included
+    Group(DelimType),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(LosslessToken),
+}
+
+/// A node of the concrete syntax tree.
+///
+/// Unlike the AST, the CST keeps every token along with its trivia, so its text is exactly the
+/// source it was built from. That is what source preserving tools, like a formatter, build on.
+/// Statements are split on `;` and delimiters are grouped, but the tokens of a statement are
+/// not structured further, so the CST can be built for any input, including invalid syntax.
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out);
+        out
+    }
+
+    pub fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.write_text(out),
+                SyntaxElement::Token(token) => token.write_text(out),
+            }
+        }
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            _ => None,
+        })
+    }
+
+    /// The first token of the node, searching nested nodes
+    pub fn first_token(&self) -> Option<&LosslessToken> {
+        self.children.iter().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+}
+
+pub struct CstBuilder<I: Iterator<Item = LosslessToken>> {
+    tokens: Peekable<I>,
+}
+
+impl<I: Iterator<Item = LosslessToken>> CstBuilder<I> {
+    pub fn new<T: IntoIterator<Item = LosslessToken, IntoIter = I>>(tokens: T) -> Self {
+        Self {
+            tokens: tokens.into_iter().peekable(),
+        }
+    }
+
+    pub fn build(mut self) -> SyntaxNode {
+        let mut children = Vec::new();
+
+        while let Some(token) = self.tokens.peek() {
+            if token.token.kind == TokenKind::Eof {
+                children.push(SyntaxElement::Token(self.tokens.next().unwrap()));
+            } else {
+                children.push(SyntaxElement::Node(self.statement()));
+            }
+        }
+
+        SyntaxNode {
+            kind: SyntaxKind::Program,
+            children,
+        }
+    }
+
+    // A statement ends with a `;`, or with a block that is not followed by one
+    fn statement(&mut self) -> SyntaxNode {
+        let mut children = Vec::new();
+
+        while let Some(token) = self.tokens.peek() {
+            match &token.token.kind {
+                TokenKind::Eof => break,
+                TokenKind::Delim(DelimDir::Close, _) if !children.is_empty() => break,
+                TokenKind::SemiColon => {
+                    children.push(SyntaxElement::Token(self.tokens.next().unwrap()));
+                    break;
+                }
+                TokenKind::Delim(DelimDir::Open, delim) => {
+                    let delim = *delim;
+                    children.push(SyntaxElement::Node(self.group(delim)));
+
+                    let continues = self.tokens.peek().is_some_and(|next| matches!(
+                        next.token.kind,
+                        TokenKind::SemiColon | TokenKind::Keyword(Keyword::Else)
+                    ));
+
+                    if delim == DelimType::Brace && !continues {
+                        break;
+                    }
+                }
+                _ => children.push(SyntaxElement::Token(self.tokens.next().unwrap())),
+            }
+        }
+
+        SyntaxNode {
+            kind: Self::statement_kind(&children),
+            children,
+        }
+    }
+
+    // A declaration without a modifier starts like an assignment, the standard form, `x: u32 = 4;`,
+    // and a type declaration, `Point = record { .. }`, are told apart by the tokens after the name
+    fn statement_kind(children: &[SyntaxElement]) -> SyntaxKind {
+        let mut kinds = children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(&token.token.kind),
+            _ => None,
+        });
+
+        match (kinds.next(), kinds.next(), kinds.next()) {
+            (Some(TokenKind::Keyword(Keyword::Const | Keyword::Var | Keyword::Impl)), _, _) => SyntaxKind::Decl,
+            (Some(TokenKind::Keyword(Keyword::Print | Keyword::Println)), _, _) => SyntaxKind::PrintStmt,
+            (Some(TokenKind::Identifier(..)), Some(TokenKind::Colon), _) => SyntaxKind::Decl,
+            (
+                Some(TokenKind::Identifier(..)),
+                Some(TokenKind::Eq),
+                Some(TokenKind::Keyword(Keyword::Record | Keyword::Union | Keyword::Trait)),
+            ) => SyntaxKind::Decl,
+            (Some(TokenKind::Identifier(..)), Some(TokenKind::Eq), _) => SyntaxKind::AssignStmt,
+            _ => SyntaxKind::ExprStmt,
+        }
+    }
+
+    // Statements inside braces are nodes of their own, other groups hold plain tokens
+    fn group(&mut self, delim: DelimType) -> SyntaxNode {
+        let mut children = vec![SyntaxElement::Token(self.tokens.next().unwrap())];
+
+        while let Some(token) = self.tokens.peek() {
+            match &token.token.kind {
+                TokenKind::Eof => break,
+                TokenKind::Delim(DelimDir::Close, close) => {
+                    let is_match = *close == delim;
+                    children.push(SyntaxElement::Token(self.tokens.next().unwrap()));
+                    if is_match {
+                        break;
+                    }
+                }
+                TokenKind::Delim(DelimDir::Open, inner) if delim != DelimType::Brace => {
+                    let inner = *inner;
+                    children.push(SyntaxElement::Node(self.group(inner)));
+                }
+                _ if delim == DelimType::Brace => children.push(SyntaxElement::Node(self.statement())),
+                _ => children.push(SyntaxElement::Token(self.tokens.next().unwrap())),
+            }
+        }
+
+        SyntaxNode {
+            kind: SyntaxKind::Group(delim),
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build(source: &str) -> SyntaxNode {
+        CstBuilder::new(LosslessTokenizer::new(source)).build()
+    }
+
+    #[test]
+    fn reproduce_source() {
+        let sources = [
+            include_str!("../../resources/valid-snippets/program-1.mypl"),
+            include_str!("../../resources/valid-snippets/arithmetic-expression.mypl"),
+            include_str!("../../resources/test-snippets/single-chars.mypl"),
+            "const x = (1 + /* two */ 2;\n{ y = ) 3; }\n} // unbalanced",
+        ];
+
+        for source in sources {
+            assert_eq!(build(source).text(), source);
+        }
+    }
+
+    #[test]
+    fn split_statements() {
+        let cst = build("/// docs\nconst x = 1;\n@println (x + 1); // show\nx = 2; { x; } x");
+
+        let kinds = cst.nodes().map(|node| node.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![
+            SyntaxKind::Decl,
+            SyntaxKind::PrintStmt,
+            SyntaxKind::AssignStmt,
+            SyntaxKind::ExprStmt,
+            SyntaxKind::ExprStmt,
+        ]);
+
+        let texts = cst.nodes().map(|node| node.text()).collect::<Vec<_>>();
+        assert_eq!(texts[0], "/// docs\nconst x = 1;\n");
+        assert_eq!(texts[1], "@println (x + 1); // show\n");
+        assert_eq!(texts[3], "{ x; } ");
+    }
+
+    #[test]
+    fn classify_declarations() {
+        let source = "x: u32 = 1;\n\
+                      increment: (value: i8) i8 = { return value + 1; }\n\
+                      Point = record { x: u32, y: u32 }\n\
+                      Shape = union { Dot, Line }\n\
+                      Area = trait { area: (Self) f32 };\n\
+                      impl Area for Point { area: (point: Point) f32 = { return 1.0; } }\n\
+                      x = 2;";
+        let kinds = build(source).nodes().map(|node| node.kind).collect::<Vec<_>>();
+        assert_eq!(kinds, vec![
+            SyntaxKind::Decl,
+            SyntaxKind::Decl,
+            SyntaxKind::Decl,
+            SyntaxKind::Decl,
+            SyntaxKind::Decl,
+            SyntaxKind::Decl,
+            SyntaxKind::AssignStmt,
+        ]);
+    }
+}
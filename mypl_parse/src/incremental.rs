@@ -0,0 +1,196 @@
+use std::ops::Range;
+
+use mypl_ast::prelude::*;
+use mypl_lex::prelude::*;
+
+use crate::{
+    parse::ParseError,
+    recursive_descent_parser::RecursiveDescentParser,
+};
+
+/// A top level statement along with the source it was parsed from, doc comments included
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParsedStmt {
+    pub stmt: Stmt,
+    pub span: Span,
+}
+
+/// Keeps the statements of a source up to date as it is edited.
+///
+/// The edit is re-lexed by an IncrementalTokenizer. Statements are then parsed again from the one
+/// before the edit, until one starts where an old statement after the edit starts. From there on
+/// the old statements are kept, along with the errors found after them.
+pub struct IncrementalParser {
+    tokenizer: IncrementalTokenizer,
+    statements: Vec<ParsedStmt>,
+    errors: Vec<ParseError>,
+}
+
+impl IncrementalParser {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self::with_file(source, FileId::default())
+    }
+
+    pub fn with_file(source: impl Into<String>, file: FileId) -> Self {
+        let mut parser = Self {
+            tokenizer: IncrementalTokenizer::with_file(source, file),
+            statements: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        parser.reparse(0, None);
+        parser
+    }
+
+    pub fn tokenizer(&self) -> &IncrementalTokenizer {
+        &self.tokenizer
+    }
+
+    pub fn statements(&self) -> &[ParsedStmt] {
+        &self.statements
+    }
+
+    /// Statements with errors are left out of the statements
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Applies the edit and re-parses the statements it damaged.
+    /// Returns the range of the re-parsed statements, the statements after it are the old ones.
+    pub fn edit(&mut self, edit: &TextEdit) -> Range<usize> {
+        self.tokenizer.edit(edit);
+
+        // The statement before the edit is re-parsed too, it may not have ended where it did
+        let first = self.statements
+            .iter()
+            .position(|parsed| parsed.span.end() >= edit.start)
+            .unwrap_or(self.statements.len())
+            .saturating_sub(1);
+
+        self.reparse(first, Some(edit))
+    }
+
+    // Parses from the `first` statement on, until statements line up with the old ones after the edit.
+    // From the first statement, parsing starts at the beginning of the source, an edit before it may
+    // have fixed an error there.
+    fn reparse(&mut self, first: usize, edit: Option<&TextEdit>) -> Range<usize> {
+        let restart = match first {
+            0 => 0,
+            _ => self.statements[first].span.start(),
+        };
+        let mut old_statements = self.statements.split_off(first);
+        let (errors, mut old_errors) = std::mem::take(&mut self.errors)
+            .into_iter()
+            .partition(|error| error.span().start() < restart);
+        self.errors = errors;
+
+        let tokens = self.tokenizer.tokens();
+        let index = tokens.partition_point(|token| token.span.start() < restart);
+        let mut parser = RecursiveDescentParser::new(tokens[index..].iter().cloned());
+
+        let mut old = 0;
+        loop {
+            if let Some(edit) = edit {
+                let delta = edit.delta();
+                let position = parser.span().start();
+
+                if position >= edit.start + edit.text.len() {
+                    // Skip the old statements that are damaged, or start before the current one would
+                    while old_statements.get(old).is_some_and(|parsed| {
+                        parsed.span.start() < edit.end || parsed.span.start().saturating_add_signed(delta) < position
+                    }) {
+                        old += 1;
+                    }
+
+                    if old_statements.get(old).is_some_and(|parsed| parsed.span.start().saturating_add_signed(delta) == position) {
+                        let range = first..self.statements.len();
+                        self.statements.extend(old_statements.drain(old..).map(|mut parsed| {
+                            parsed.stmt.shift(delta);
+                            parsed.span = parsed.span.shifted(delta);
+                            parsed
+                        }));
+                        let old_position = position.saturating_add_signed(-delta);
+                        old_errors.retain(|error| error.span().start() >= old_position);
+                        self.errors.extend(old_errors.into_iter().map(|error| error.shifted(delta)));
+                        return range;
+                    }
+                }
+            }
+
+            match parser.next_statement() {
+                Some(Ok((stmt, span))) => self.statements.push(ParsedStmt { stmt, span }),
+                Some(Err(error)) => self.errors.push(error),
+                None => break,
+            }
+        }
+
+        first..self.statements.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse::Parser;
+
+    fn assert_matches_parser(incremental: &IncrementalParser) {
+        let expected = RecursiveDescentParser::new(Tokenizer::new(incremental.tokenizer().source())).parse();
+        let statements = incremental.statements().iter().map(|parsed| parsed.stmt.clone()).collect::<Vec<_>>();
+        let spans = |errors: &[ParseError]| errors.iter().map(|error| *error.span()).collect::<Vec<_>>();
+
+        assert_eq!(statements, expected.statements);
+        assert_eq!(spans(incremental.errors()), spans(&expected.errors));
+    }
+
+    #[test]
+    fn reparse_damaged_statements_only() {
+        let source = include_str!("../../resources/valid-snippets/program-1.mypl");
+        let mut incremental = IncrementalParser::new(source);
+        let count = incremental.statements().len();
+
+        let start = source.find("const c1 = 2.").unwrap() + 11;
+        let range = incremental.edit(&TextEdit::new(start, start + 2, "3."));
+
+        assert_matches_parser(&incremental);
+        assert!(range.len() <= 2, "re-parsed {:?}", range);
+        assert_eq!(incremental.statements().len(), count);
+
+        // Statement spans after the edit are still right
+        let last = incremental.statements().last().unwrap();
+        assert_eq!(&incremental.tokenizer().source()[last.span.start()..last.span.end()], "@println \"c1 > c2: {c1 > c2}\";");
+    }
+
+    #[test]
+    fn reparse_around_errors() {
+        let mut incremental = IncrementalParser::new("const x = 1;\n/// y\nvar y = x;\n@println y;\n");
+
+        let edits = [
+            // Breaks the second statement
+            TextEdit::new(25, 26, ""),
+            // Fixes it again
+            TextEdit::new(25, 25, "="),
+            // Changes the first statement
+            TextEdit::new(11, 12, " + 2;"),
+            // Adds a statement at the end
+            TextEdit::new(46, 46, "x = 2;\n"),
+        ];
+
+        for edit in &edits {
+            incremental.edit(edit);
+            assert_matches_parser(&incremental);
+        }
+
+        assert_eq!(incremental.statements().len(), 4);
+    }
+
+    #[test]
+    fn reparse_errors_before_the_first_statement() {
+        let mut incremental = IncrementalParser::new("x = ;\nconst y = 1;");
+        assert_eq!(incremental.statements().len(), 1);
+
+        incremental.edit(&TextEdit::new(4, 4, "1"));
+        assert_matches_parser(&incremental);
+        assert_eq!(incremental.statements().len(), 2);
+        assert!(incremental.errors().is_empty());
+    }
+}
@@ -1,4 +1,7 @@
+mod cst;
+mod incremental;
 mod parse;
+mod precedence;
 mod token_kind_predicates;
 mod recursive_descent_parser;
 
@@ -9,6 +12,9 @@ extern crate anyhow;
 extern crate thiserror;
 
 pub mod prelude {
-    pub use super::parse::{Parser, ParseError};
+    pub use super::cst::{CstBuilder, SyntaxElement, SyntaxKind, SyntaxNode};
+    pub use super::incremental::{IncrementalParser, ParsedStmt};
+    pub use super::parse::{Parser, ParseError, ParseOutput};
+    pub use super::precedence::{binary_operator, Operator, BINARY_OPERATORS};
     pub use super::recursive_descent_parser::RecursiveDescentParser;
 }
\ No newline at end of file
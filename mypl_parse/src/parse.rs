@@ -2,16 +2,57 @@ use anyhow::Result;
 use thiserror::Error;
 
 use mypl_ast::prelude::*;
+use mypl_lex::prelude::Span;
 
 #[derive(Error, Debug)]
 pub enum ParseError {
     #[error("ParseError: {0}")]
-    Default(String),
+    Default(String, Span),
 
     #[error("Expected token \"{0}\" at \"{1}\"")]
-    ExpectedToken(String, String),
+    ExpectedToken(String, String, Span),
+}
+
+impl ParseError {
+    pub fn span(&self) -> &Span {
+        match self {
+            ParseError::Default(_, span) => span,
+            ParseError::ExpectedToken(_, _, span) => span,
+        }
+    }
+
+    /// The same error, with its span moved by a number of bytes
+    pub fn shifted(self, delta: isize) -> ParseError {
+        match self {
+            ParseError::Default(message, span) => ParseError::Default(message, span.shifted(delta)),
+            ParseError::ExpectedToken(token, rule, span) => ParseError::ExpectedToken(token, rule, span.shifted(delta)),
+        }
+    }
+}
+
+/// The statements that were parsed, along with every syntax error found on the way.
+/// Statements with syntax errors are left out, so the statements are a partial AST.
+#[derive(Debug)]
+pub struct ParseOutput {
+    pub statements: Vec<Stmt>,
+    pub errors: Vec<ParseError>,
+}
+
+impl ParseOutput {
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+
+    /// The statements when there are no errors, the errors otherwise
+    pub fn into_result(self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        if self.errors.is_empty() {
+            Ok(self.statements)
+        } else {
+            Err(self.errors)
+        }
+    }
 }
 
 pub trait Parser {
-    fn parse(&mut self) -> Result<Vec<Stmt>, ParseError>;
+    fn parse(&mut self) -> ParseOutput;
 }
@@ -0,0 +1,62 @@
+use mypl_ast::prelude::BinOp;
+
+/// A binary operator, every one of them associates to the left
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Operator {
+    pub op: BinOp,
+    pub precedence: u8,
+}
+
+const fn left(op: BinOp, precedence: u8) -> Operator {
+    Operator { op, precedence }
+}
+
+/// The binary operators, from the lowest precedence to the highest.
+/// Keep in sync with mypl_spec/grammar/080-operator-precedence.md
+pub const BINARY_OPERATORS: &[Operator] = &[
+    left(BinOp::Or, 1),
+
+    left(BinOp::And, 2),
+
+    left(BinOp::Eq, 3),
+    left(BinOp::Ne, 3),
+
+    left(BinOp::Lt, 4),
+    left(BinOp::Le, 4),
+    left(BinOp::Gt, 4),
+    left(BinOp::Ge, 4),
+
+    left(BinOp::BitOr, 5),
+
+    left(BinOp::BitXor, 6),
+
+    left(BinOp::BitAnd, 7),
+
+    left(BinOp::Shl, 8),
+    left(BinOp::Shr, 8),
+
+    left(BinOp::Add, 9),
+    left(BinOp::Sub, 9),
+
+    left(BinOp::Mul, 10),
+    left(BinOp::Div, 10),
+    left(BinOp::Rem, 10),
+];
+
+/// The lowest precedence, an expression starts parsing with it
+pub const MIN_PRECEDENCE: u8 = 1;
+
+pub fn binary_operator(op: BinOp) -> Operator {
+    *BINARY_OPERATORS
+        .iter()
+        .find(|operator| operator.op == op)
+        .expect("every binary operator is in the table")
+}
+
+impl Operator {
+    /// The precedence the right hand side is parsed with.
+    /// A left associative operator does not take an operator of the same precedence on its right.
+    pub fn rhs_precedence(&self) -> u8 {
+        self.precedence + 1
+    }
+}
@@ -1,10 +1,13 @@
+use std::collections::VecDeque;
+
 use mypl_ast::prelude::*;
 use mypl_lex::prelude::*;
 
 use anyhow::Result;
 
 use crate::{
-    parse::{ParseError, Parser},
+    parse::{ParseError, ParseOutput, Parser},
+    precedence::{binary_operator, MIN_PRECEDENCE},
     token_kind_predicates::TokenKindExtensions,
 };
 
@@ -24,45 +27,64 @@ impl<T> OptionExtensions for Option<T> {
     }
 }
 
-pub struct RecursiveDescentParser<'a> {
-    position: usize,
+pub struct RecursiveDescentParser<I: Iterator<Item = Token>> {
+    tokens: I,
 
-    // TODO: replace this with an iterator
-    tokens: &'a Vec<Token>,
-}
+    // Tokens that were read from the iterator but not consumed yet.
+    // The first one is the current token.
+    lookahead: VecDeque<Token>,
 
-impl<'a> RecursiveDescentParser<'a> {
-    pub fn new(tokens: &'a Vec<Token>) -> Self {
-        Self {
-            position: 0,
-            tokens,
-        }
-    }
+    // The span of the last consumed token
+    previous_span: Span,
 
-    fn advance(&mut self) {
-        self.position += 1;
-    }
+    // How many blocks the current statement is nested in, recovery skips to the end of them
+    block_depth: usize,
+
+    // How many loops the current statement is nested in, `break` and `continue` need one
+    loop_depth: usize,
 
-    fn retreat(&mut self) {
-        self.position -= 1;
+    // Whether the current statement is in the body of a function, `return` needs one
+    in_function: bool,
 
+    // Whether the expression is the condition of an if or a loop, where a `{` after an identifier
+    // opens the body rather than a record literal
+    in_condition: bool,
+}
+
+impl<I: Iterator<Item = Token>> RecursiveDescentParser<I> {
+    pub fn new<T: IntoIterator<Item = Token, IntoIter = I>>(tokens: T) -> Self {
+        let mut parser = Self {
+            tokens: tokens.into_iter(),
+            lookahead: VecDeque::new(),
+            previous_span: Span::default(),
+            block_depth: 0,
+            loop_depth: 0,
+            in_function: false,
+            in_condition: false,
+        };
+
+        parser.fill(1);
+        parser
     }
 
-    fn match_twice<T1, T2, M1, M2>(&mut self, mut m1: M1, mut m2: M2) -> Option<(T1, T2)> where
-        M1: FnMut(&mut Self) -> Option<T1>,
-        M2: FnMut(&mut Self) -> Option<T2>,
-    {
-        if let Some(t1) = m1(self) {
-            if let Some(t2) = m2(self) {
-                return Some((t1, t2));
-            } else {
-                self.retreat();
-                return None;
+    // Makes sure there are at least `size` tokens to look at, unless the iterator is exhausted.
+    // Comments are skipped, except for doc comments which are part of the grammar.
+    fn fill(&mut self, size: usize) {
+        while self.lookahead.len() < size {
+            match self.tokens.next() {
+                Some(Token { kind: TokenKind::Comment(CommentKind::Line | CommentKind::Block, _), .. }) => {}
+                Some(token) => self.lookahead.push_back(token),
+                None => break,
             }
         }
+    }
 
-        println!("match twice: 3");
-        return None;
+    fn advance(&mut self) {
+        if let Some(token) = self.lookahead.pop_front() {
+            self.previous_span = token.span;
+        }
+
+        self.fill(1);
     }
 
     fn match_predicate<P: (Fn(&TokenKind) -> bool)>(&mut self, predicate: P) -> Option<Token> {
@@ -104,11 +126,13 @@ impl<'a> RecursiveDescentParser<'a> {
             .when_some(|| self.advance())
     }
 
-    fn match_binary_op(&mut self, ops: &[BinOp]) -> Option<BinOp> {
-        self
-            .token()
-            .and_then(|t| t.kind.to_binary_op())
-            .filter(|op| ops.contains(op))
+    fn match_template(&mut self) -> Option<Vec<TemplatePart>> {
+        self.token()
+            .and_then(|t| match &t.kind {
+                TokenKind::Template(parts) => Some(parts),
+                _ => None
+            })
+            .cloned()
             .when_some(|| self.advance())
     }
 
@@ -121,251 +145,1812 @@ impl<'a> RecursiveDescentParser<'a> {
     }
 
     fn token(&self) -> Option<&Token> {
-        self.tokens.get(self.position)
+        self.lookahead.front()
     }
 
-    // fn previous_token(&mut self) -> Option<&Token> {
-    //     self.tokens.get(self.position - 1)
-    // }
+    // The nth token after the current one
+    fn peek_nth(&mut self, n: usize) -> Option<&Token> {
+        self.fill(n + 1);
+        self.lookahead.get(n)
+    }
+
+    // The span of the current token, or of the last one when all tokens are consumed
+    pub(crate) fn span(&self) -> Span {
+        self.token()
+            .map(|t| t.span)
+            .unwrap_or(self.previous_span)
+    }
+
+    // The span from `start` up to the end of the last consumed token
+    fn span_from(&self, start: Span) -> Span {
+        start.to(&self.previous_span)
+    }
+
+    fn error(&self, message: &str) -> ParseError {
+        ParseError::Default(message.to_string(), self.span())
+    }
+
+    fn expected_token(&self, token: &str, rule: &str) -> ParseError {
+        ExpectedToken(token.to_string(), rule.to_string(), self.span())
+    }
 
     // Grammar
     
-    fn program(&mut self) -> Result<Vec<Stmt>, ParseError> {
+    fn program(&mut self) -> ParseOutput {
         let mut statements = Vec::new();
-        while let None = self.match_variant(&TokenKind::Eof) {
-            statements.push(self.decl()?);
+        let mut errors = Vec::new();
+        while let Some(statement) = self.next_statement() {
+            match statement {
+                Ok((stmt, _)) => statements.push(stmt),
+                Err(error) => errors.push(error),
+            }
         }
 
-        Ok(statements)
+        ParseOutput { statements, errors }
     }
 
-    fn decl(&mut self) -> Result<Stmt, ParseError> {
+    /// Parses the next top level statement along with its span, doc comments included.
+    /// After an error, the tokens up to the next statement are skipped.
+    /// Returns None once Eof is reached.
+    pub(crate) fn next_statement(&mut self) -> Option<Result<(Stmt, Span), ParseError>> {
+        let start = self.span();
+        let doc = self.doc_comments();
+        if self.match_variant(&TokenKind::Eof).is_some() {
+            return None;
+        }
+
+        match self.decl(doc) {
+            Ok(stmt) => Some(Ok((stmt, self.span_from(start)))),
+            Err(error) => {
+                self.synchronize();
+                Some(Err(error))
+            }
+        }
+    }
+
+    // Panic mode recovery, skips tokens up to the end of the statement with the error.
+    // A statement ends after a `;`, and before a declaration keyword.
+    // An error inside of blocks skips the rest of them, up to their closing braces.
+    fn synchronize(&mut self) {
+        let mut depth = std::mem::take(&mut self.block_depth);
+        self.loop_depth = 0;
+        self.in_function = false;
+        self.in_condition = false;
+        while let Some(token) = self.token() {
+            match token.kind {
+                TokenKind::Eof => return,
+                TokenKind::Keyword(Keyword::Const | Keyword::Var | Keyword::Impl | Keyword::Mod) if depth == 0 => return,
+                TokenKind::SemiColon if depth == 0 => {
+                    self.advance();
+                    return;
+                }
+                TokenKind::Delim(DelimDir::Open, DelimType::Brace) => {
+                    depth += 1;
+                    self.advance();
+                }
+                TokenKind::Delim(DelimDir::Close, DelimType::Brace) if depth > 0 => {
+                    depth -= 1;
+                    self.advance();
+                    if depth == 0 {
+                        // An else branch, or the `;` after a block expression, ends the statement too
+                        match self.token().map(|t| &t.kind) {
+                            Some(TokenKind::Keyword(Keyword::Else)) => {}
+                            Some(TokenKind::SemiColon) => {
+                                self.advance();
+                                return;
+                            }
+                            _ => return,
+                        }
+                    }
+                }
+                _ => self.advance(),
+            }
+        }
+    }
+
+    // Consecutive doc comments, joined into the documentation of the following declaration
+    fn doc_comments(&mut self) -> Option<String> {
+        let mut lines = Vec::new();
+        while let Some(Token { kind: TokenKind::Comment(CommentKind::Doc, comment), .. }) = self.token() {
+            let line = comment.trim_start_matches("///");
+            lines.push(line.strip_prefix(' ').unwrap_or(line).to_string());
+            self.advance();
+        }
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    // Doc comments that do not precede a declaration are dropped
+    fn decl(&mut self, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let start = self.span();
         if self.match_keyword(&Keyword::Const).is_some() {
-            Ok(self.const_decl()?)
+            Ok(self.const_decl(start, doc)?)
         } else if self.match_keyword(&Keyword::Var).is_some() {
-            Ok(self.var_decl()?)
+            Ok(self.var_decl(start, doc)?)
+        } else if self.match_keyword(&Keyword::Impl).is_some() {
+            Ok(self.impl_decl(start, doc)?)
+        } else if self.at_standard_form() || self.at_type_decl() {
+            // The standard form without a modifier, `x: u32 = 4;`, declares a constant, and so does
+            // a type declaration without one, `Point = record { .. };`
+            Ok(self.const_decl(start, doc)?)
         } else {
             Ok(self.statement()?)
         }
     }
 
-    fn const_decl(&mut self) -> Result<Stmt, ParseError> {
+    fn at_standard_form(&mut self) -> bool {
+        matches!(self.token().map(|t| &t.kind), Some(TokenKind::Identifier(..)))
+            && matches!(self.peek_nth(1).map(|t| &t.kind), Some(TokenKind::Colon))
+    }
+
+    fn at_type_decl(&mut self) -> bool {
+        matches!(self.token().map(|t| &t.kind), Some(TokenKind::Identifier(..)))
+            && matches!(self.peek_nth(1).map(|t| &t.kind), Some(TokenKind::Eq))
+            && matches!(self.peek_nth(2).map(|t| &t.kind), Some(TokenKind::Keyword(Keyword::Record | Keyword::Union | Keyword::Trait)))
+    }
+
+    // A const with a function type and a block, `increment: (value: i8) i8 = { .. }`, is a function.
+    // A const that is a record, a union or a trait, `Point = record { .. }`, declares the type.
+    fn const_decl(&mut self, start: Span, doc: Option<String>) -> Result<Stmt, ParseError> {
         let identifier = self.match_identifier()
-            .ok_or_else(|| ExpectedToken("identifier".to_string(), "const_decl".to_string()))?;
+            .ok_or_else(|| self.expected_token("identifier", "const_decl"))?;
+
+        let annotation = self.annotation()?;
         
-        let _ = self.match_variant(&TokenKind::Eq)
-            .ok_or_else(|| ExpectedToken("=".to_string(), "const_decl".to_string()));
+        self.match_variant(&TokenKind::Eq)
+            .ok_or_else(|| self.expected_token("=", "const_decl"))?;
 
-        let expr = self.expression()
-            .map_err(|_| ParseError::Default("expected expression in const_decl".to_string()))?;
+        if let Some(token) = self.match_predicate(|k| matches!(k, TokenKind::Keyword(Keyword::Record | Keyword::Union | Keyword::Trait))) {
+            if annotation.is_some() {
+                return Err(ParseError::Default("a type declaration has no type annotation".to_string(), self.span_from(start)));
+            }
+            return match token.kind {
+                TokenKind::Keyword(Keyword::Record) => self.record_decl(start, identifier, doc),
+                TokenKind::Keyword(Keyword::Union) => self.union_decl(start, identifier, doc),
+                _ => self.trait_decl(start, identifier, doc),
+            };
+        }
 
-        let _ = self.match_variant(&TokenKind::SemiColon)
-            .ok_or_else(|| ExpectedToken(";".to_string(), "const_decl".to_string()));
+        let ty = match annotation {
+            Some(Annotation::Signature(signature)) if self.at_block() => {
+                return self.function_decl(start, identifier, signature, doc);
+            }
+            annotation => annotation.map(Annotation::into_type),
+        };
 
-        Ok(Stmt {
-            kind: StmtKind::Decl(Box::new(Decl {
-                kind: DeclKind::Const(identifier, Box::new(expr))
-            }))
-        })
+        let expr = self.initializer(ty.is_some())?;
+
+        self.match_variant(&TokenKind::SemiColon)
+            .ok_or_else(|| self.expected_token(";", "const_decl"))?;
+
+        Ok(self.decl_stmt(start, DeclKind::Const(identifier, ty, Box::new(expr)), doc))
     }
 
-    fn var_decl(&mut self) -> Result<Stmt, ParseError> {
+    fn var_decl(&mut self, start: Span, doc: Option<String>) -> Result<Stmt, ParseError> {
         let identifier = self.match_identifier()
-            .ok_or_else(|| ExpectedToken("identifier".to_string(), "decl".to_string()))?;
+            .ok_or_else(|| self.expected_token("identifier", "decl"))?;
+
+        let annotation = self.annotation()?;
         
         self.match_variant(&TokenKind::Eq)
-            .ok_or_else(|| ExpectedToken("=".to_string(), "decl".to_string()))?;
+            .ok_or_else(|| self.expected_token("=", "decl"))?;
+
+        if matches!(annotation, Some(Annotation::Signature(..))) && self.at_block() {
+            return Err(self.error("a function is declared with const, or without a modifier"));
+        }
+        if matches!(self.token().map(|t| &t.kind), Some(TokenKind::Keyword(Keyword::Record | Keyword::Union | Keyword::Trait))) {
+            return Err(self.error("a type is declared with const, or without a modifier"));
+        }
+        let ty = annotation.map(Annotation::into_type);
 
-        let expr = self.expression()
-            .map_err(|_| ParseError::Default("expected expression at var_decl".to_string()))?;
+        let expr = self.initializer(ty.is_some())?;
 
         self.match_variant(&TokenKind::SemiColon)
-            .ok_or_else(|| ExpectedToken(";".to_string(), "decl".to_string()))?;
+            .ok_or_else(|| self.expected_token(";", "decl"))?;
 
-        Ok(Stmt {
-            kind: StmtKind::Decl(Box::new(Decl {
-                kind: DeclKind::Var(identifier, Box::new(expr))
-            }))
-        })
+        Ok(self.decl_stmt(start, DeclKind::Var(identifier, ty, Box::new(expr)), doc))
+    }
+
+    // The value of a declaration. With a type annotation it may be an anonymous literal, which takes
+    // the annotated type, `const point: Point = { x = 4, y = 3 };`
+    fn initializer(&mut self, annotated: bool) -> Result<Expr, ParseError> {
+        if annotated {
+            self.field_value()
+        } else {
+            self.expression()
+        }
+    }
+
+    fn at_block(&self) -> bool {
+        matches!(self.token().map(|t| &t.kind), Some(TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+    }
+
+    // The `: type` of a declaration, if it has one
+    fn annotation(&mut self) -> Result<Option<Annotation>, ParseError> {
+        if self.match_variant(&TokenKind::Colon).is_none() {
+            return Ok(None);
+        }
+
+        if self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Paren))).is_some() {
+            Ok(Some(Annotation::Signature(self.signature()?)))
+        } else {
+            Ok(Some(Annotation::Type(self.parse_type()?)))
+        }
+    }
+
+    // The parameters of a function type and its return type, the `(` is already consumed.
+    // Parameters may be named, `(value: i8) i8`, the names are only needed to declare a function.
+    fn signature(&mut self) -> Result<Signature, ParseError> {
+        let mut params = Vec::new();
+        while !self.match_list_end(DelimType::Paren, !params.is_empty(), "signature")? {
+            let param_start = self.span();
+            let name = if self.at_standard_form() {
+                let name = self.match_identifier().expect("peeked an identifier");
+                self.advance();
+                Some(name)
+            } else {
+                None
+            };
+            let ty = self.parse_type()?;
+
+            params.push(SignatureParam {
+                name,
+                ty,
+                span: self.span_from(param_start),
+            });
+        }
+
+        // A function without a return type, `() = ..`
+        let return_type = if self.at_type() {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+
+        Ok(Signature { params, return_type })
+    }
+
+    // The name and the signature are already consumed, and so is the `=`
+    fn function_decl(&mut self, start: Span, name: String, signature: Signature, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let params = signature.params
+            .into_iter()
+            .map(|param| match param.name {
+                Some(name) => Ok(Param {
+                    name,
+                    ty: param.ty,
+                    span: param.span,
+                }),
+                None => Err(ExpectedToken("parameter name".to_string(), "function_decl".to_string(), param.span)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Loops around the function do not reach into its body
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_function = std::mem::replace(&mut self.in_function, true);
+        let body = self.braced_block("function_decl")?;
+        self.loop_depth = loop_depth;
+        self.in_function = in_function;
+
+        let StmtKind::Block(body) = body.kind else {
+            unreachable!("braced_block parses a block");
+        };
+
+        // The body is a block, the `;` after it is optional
+        self.match_variant(&TokenKind::SemiColon);
+
+        let function = Function {
+            name,
+            params,
+            return_type: signature.return_type,
+            body,
+        };
+        Ok(self.decl_stmt(start, DeclKind::Function(Box::new(function)), doc))
+    }
+
+    // The `record` keyword is already consumed.
+    // Like a function, the `;` after it is optional.
+    fn record_decl(&mut self, start: Span, name: String, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let fields = self.fields("record_decl")?;
+        self.match_variant(&TokenKind::SemiColon);
+
+        let record = RecordDecl { name, fields };
+        Ok(self.decl_stmt(start, DeclKind::Record(Box::new(record)), doc))
+    }
+
+    // A braced list of typed fields, `{ x: u32, y: u32 }`
+    fn fields(&mut self, rule: &str) -> Result<Vec<Field>, ParseError> {
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", rule))?;
+
+        let mut fields = Vec::new();
+        while !self.match_list_end(DelimType::Brace, !fields.is_empty(), rule)? {
+            let field_start = self.span();
+            let name = self.match_identifier()
+                .ok_or_else(|| self.expected_token("field name", rule))?;
+            self.match_variant(&TokenKind::Colon)
+                .ok_or_else(|| self.expected_token(":", rule))?;
+            let ty = self.parse_type()?;
+
+            fields.push(Field {
+                name,
+                ty,
+                span: self.span_from(field_start),
+            });
+        }
+
+        Ok(fields)
+    }
+
+    // The `union` keyword is already consumed
+    fn union_decl(&mut self, start: Span, name: String, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let variants = self.variants("union_decl")?;
+        self.match_variant(&TokenKind::SemiColon);
+
+        let union = UnionDecl { name, variants };
+        Ok(self.decl_stmt(start, DeclKind::Union(Box::new(union)), doc))
+    }
+
+    // The `trait` keyword is already consumed. The methods are written like fields, with function types,
+    // `area: (Self) f32`, and `Self` is the type that implements the trait.
+    fn trait_decl(&mut self, start: Span, name: String, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let methods = self.fields("trait_decl")?;
+        if let Some(method) = methods.iter().find(|method| !matches!(method.ty, Type::Function(..))) {
+            return Err(ParseError::Default("a trait method has a function type".to_string(), method.span));
+        }
+        self.match_variant(&TokenKind::SemiColon);
+
+        let trait_decl = TraitDecl { name, methods };
+        Ok(self.decl_stmt(start, DeclKind::Trait(Box::new(trait_decl)), doc))
+    }
+
+    // The `impl` keyword is already consumed, `impl Shape for Circle { .. }`.
+    // The methods are function declarations, and like a function the `;` after it is optional.
+    fn impl_decl(&mut self, start: Span, doc: Option<String>) -> Result<Stmt, ParseError> {
+        let trait_name = self.match_identifier()
+            .ok_or_else(|| self.expected_token("trait name", "impl_decl"))?;
+        self.match_keyword(&Keyword::For)
+            .ok_or_else(|| self.expected_token("for", "impl_decl"))?;
+        let type_name = self.match_identifier()
+            .ok_or_else(|| self.expected_token("type name", "impl_decl"))?;
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", "impl_decl"))?;
+
+        self.block_depth += 1;
+        let mut methods = Vec::new();
+        loop {
+            // The methods are documented by the trait
+            self.doc_comments();
+            if self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Close, DelimType::Brace))).is_some() {
+                break;
+            }
+
+            let method_start = self.span();
+            let method = self.const_decl(method_start, None)?;
+            let StmtKind::Decl(decl) = method.kind else {
+                unreachable!("const_decl parses a declaration");
+            };
+            let DeclKind::Function(function) = decl.kind else {
+                return Err(ParseError::Default("an impl declares only functions".to_string(), decl.span));
+            };
+            methods.push(*function);
+        }
+        self.block_depth -= 1;
+        self.match_variant(&TokenKind::SemiColon);
+
+        let impl_decl = ImplDecl { trait_name, type_name, methods };
+        Ok(self.decl_stmt(start, DeclKind::Impl(Box::new(impl_decl)), doc))
+    }
+
+    // A braced list of variants, a variant is a name, with fields when a record follows it,
+    // `RGB: record { red: u8, green: u8, blue: u8 }`
+    fn variants(&mut self, rule: &str) -> Result<Vec<VariantDecl>, ParseError> {
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", rule))?;
+
+        let mut variants = Vec::new();
+        while !self.match_list_end(DelimType::Brace, !variants.is_empty(), rule)? {
+            let variant_start = self.span();
+            let name = self.match_identifier()
+                .ok_or_else(|| self.expected_token("variant name", rule))?;
+
+            let fields = if self.match_variant(&TokenKind::Colon).is_some() {
+                self.match_keyword(&Keyword::Record)
+                    .ok_or_else(|| self.expected_token("record", rule))?;
+                self.fields(rule)?
+            } else {
+                Vec::new()
+            };
+
+            variants.push(VariantDecl {
+                name,
+                fields,
+                span: self.span_from(variant_start),
+            });
+        }
+
+        Ok(variants)
+    }
+
+    // Whether a list ends, its closing delimiter is consumed then.
+    // Items are separated by commas, and a trailing comma is allowed.
+    fn match_list_end(&mut self, close: DelimType, has_items: bool, rule: &str) -> Result<bool, ParseError> {
+        let is_close = |k: &TokenKind| *k == TokenKind::Delim(DelimDir::Close, close);
+        if self.match_predicate(is_close).is_some() {
+            return Ok(true);
+        }
+
+        if has_items {
+            self.match_variant(&TokenKind::Comma)
+                .ok_or_else(|| self.expected_token(",", rule))?;
+            return Ok(self.match_predicate(is_close).is_some());
+        }
+
+        Ok(false)
+    }
+
+    fn at_type(&self) -> bool {
+        matches!(self.token().map(|t| &t.kind), Some(
+            TokenKind::Identifier(..) |
+            TokenKind::Delim(DelimDir::Open, DelimType::Paren) |
+            TokenKind::Keyword(
+                Keyword::U32 | Keyword::U16 | Keyword::U8 | Keyword::I32 | Keyword::I16 | Keyword::I8 | Keyword::F32 | Keyword::F16
+                | Keyword::Record | Keyword::Union | Keyword::Impl
+            )
+        ))
+    }
+
+    fn parse_type(&mut self) -> Result<Type, ParseError> {
+        let primitive = self.match_predicate(|k| matches!(k, TokenKind::Keyword(
+            Keyword::U32 | Keyword::U16 | Keyword::U8 | Keyword::I32 | Keyword::I16 | Keyword::I8 | Keyword::F32 | Keyword::F16
+        )));
+
+        if let Some(token) = primitive {
+            return Ok(Type::Primitive(match token.kind {
+                TokenKind::Keyword(Keyword::U32) => PrimitiveType::U32,
+                TokenKind::Keyword(Keyword::U16) => PrimitiveType::U16,
+                TokenKind::Keyword(Keyword::U8) => PrimitiveType::U8,
+                TokenKind::Keyword(Keyword::I32) => PrimitiveType::I32,
+                TokenKind::Keyword(Keyword::I16) => PrimitiveType::I16,
+                TokenKind::Keyword(Keyword::I8) => PrimitiveType::I8,
+                TokenKind::Keyword(Keyword::F32) => PrimitiveType::F32,
+                TokenKind::Keyword(Keyword::F16) => PrimitiveType::F16,
+                _ => unreachable!("matched a numeric type keyword"),
+            }));
+        }
+
+        if self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Paren))).is_some() {
+            return Ok(self.signature()?.into_type());
+        }
+
+        // An anonymous type, `record { u: u32, v: u32 }`
+        if self.match_keyword(&Keyword::Record).is_some() {
+            return Ok(Type::Record(self.fields("type")?));
+        }
+        if self.match_keyword(&Keyword::Union).is_some() {
+            return Ok(Type::Union(self.variants("type")?));
+        }
+
+        // A trait bound, `impl Shape`
+        if self.match_keyword(&Keyword::Impl).is_some() {
+            let trait_name = self.match_identifier()
+                .ok_or_else(|| self.expected_token("trait name", "type"))?;
+            return Ok(Type::Impl(trait_name));
+        }
+
+        let name = self.match_identifier()
+            .ok_or_else(|| self.expected_token("type", "type"))?;
+
+        Ok(PrimitiveType::from_name(&name).map_or(Type::Named(name), Type::Primitive))
+    }
+
+    fn decl_stmt(&self, start: Span, kind: DeclKind, doc: Option<String>) -> Stmt {
+        let span = self.span_from(start);
+        Stmt {
+            kind: StmtKind::Decl(Box::new(Decl { kind, doc, span })),
+            span,
+        }
     }
 
     fn statement(&mut self) -> Result<Stmt, ParseError> {
-        if self.match_keyword(&Keyword::Print).is_some() {
-            self.print_statement()
+        let start = self.span();
+        if self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace))).is_some() {
+            self.block(start)
+        } else if self.match_keyword(&Keyword::If).is_some() {
+            self.if_statement(start)
+        } else if self.match_keyword(&Keyword::For).is_some() {
+            self.for_statement(start)
+        } else if self.match_keyword(&Keyword::While).is_some() {
+            self.while_statement(start)
+        } else if self.match_keyword(&Keyword::Return).is_some() {
+            self.return_statement(start)
+        } else if self.match_keyword(&Keyword::Match).is_some() {
+            self.match_statement(start)
+        } else if let Some(token) = self.match_predicate(|k| matches!(k, TokenKind::Keyword(Keyword::Break | Keyword::Continue))) {
+            self.loop_control_statement(start, token.kind == TokenKind::Keyword(Keyword::Break))
+        } else if self.match_keyword(&Keyword::Print).is_some() {
+            self.print_statement(start)
         } else if self.match_keyword(&Keyword::Println).is_some() {
-            self.println_statement()
+            self.println_statement(start)
         } else if let Some(stmt) = self.try_assignment_statement()? {
             Ok(stmt)
-        }else {
+        } else {
             self.expression_statement()
         }
     }
 
-    fn println_statement(&mut self) -> Result<Stmt, ParseError> {
+    // The opening brace is already consumed
+    fn block(&mut self, start: Span) -> Result<Stmt, ParseError> {
+        self.block_depth += 1;
+
+        let mut statements = Vec::new();
+        loop {
+            let doc = self.doc_comments();
+            if self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Close, DelimType::Brace))).is_some() {
+                break;
+            }
+            if matches!(self.token().map(|t| &t.kind), None | Some(TokenKind::Eof)) {
+                return Err(self.expected_token("}", "block"));
+            }
+
+            statements.push(self.decl(doc)?);
+        }
+
+        self.block_depth -= 1;
+        Ok(Stmt {
+            kind: StmtKind::Block(statements),
+            span: self.span_from(start),
+        })
+    }
+
+    fn if_statement(&mut self, start: Span) -> Result<Stmt, ParseError> {
+        let condition = self.condition()?;
+        let then_branch = self.braced_block("if_statement")?;
+
+        let else_branch = if self.match_keyword(&Keyword::Else).is_some() {
+            let else_start = self.span();
+            if self.match_keyword(&Keyword::If).is_some() {
+                Some(Box::new(self.if_statement(else_start)?))
+            } else {
+                Some(Box::new(self.braced_block("if_statement")?))
+            }
+        } else {
+            None
+        };
+
+        Ok(Stmt {
+            kind: StmtKind::If(Box::new(condition), Box::new(then_branch), else_branch),
+            span: self.span_from(start),
+        })
+    }
+
+    fn for_statement(&mut self, start: Span) -> Result<Stmt, ParseError> {
+        let variable = self.match_identifier()
+            .ok_or_else(|| self.expected_token("identifier", "for_statement"))?;
+
+        self.match_keyword(&Keyword::In)
+            .ok_or_else(|| self.expected_token("in", "for_statement"))?;
+
+        let iterable = self.condition()?;
+        let body = self.loop_body("for_statement")?;
+
+        Ok(Stmt {
+            kind: StmtKind::For(variable, Box::new(iterable), Box::new(body)),
+            span: self.span_from(start),
+        })
+    }
+
+    fn while_statement(&mut self, start: Span) -> Result<Stmt, ParseError> {
+        let condition = self.condition()?;
+        let body = self.loop_body("while_statement")?;
+
+        Ok(Stmt {
+            kind: StmtKind::While(Box::new(condition), Box::new(body)),
+            span: self.span_from(start),
+        })
+    }
+
+    fn loop_body(&mut self, rule: &str) -> Result<Stmt, ParseError> {
+        self.loop_depth += 1;
+        let body = self.braced_block(rule)?;
+        self.loop_depth -= 1;
+        Ok(body)
+    }
+
+    // `break;` or `continue;`, the keyword is already consumed
+    fn loop_control_statement(&mut self, start: Span, is_break: bool) -> Result<Stmt, ParseError> {
+        let (keyword, kind) = if is_break { ("break", StmtKind::Break) } else { ("continue", StmtKind::Continue) };
+        if self.loop_depth == 0 {
+            return Err(ParseError::Default(format!("\"{}\" outside of a loop", keyword), start));
+        }
+
+        self.match_variant(&TokenKind::SemiColon)
+            .ok_or_else(|| self.expected_token(";", "loop_control_statement"))?;
+
+        Ok(Stmt {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    // `return;` or `return expr;`, the keyword is already consumed
+    fn return_statement(&mut self, start: Span) -> Result<Stmt, ParseError> {
+        if !self.in_function {
+            return Err(ParseError::Default("\"return\" outside of a function".to_string(), start));
+        }
+
+        let expr = if self.match_variant(&TokenKind::SemiColon).is_some() {
+            None
+        } else {
+            let expr = self.expression()?;
+            self.match_variant(&TokenKind::SemiColon)
+                .ok_or_else(|| self.expected_token(";", "return_statement"))?;
+            Some(Box::new(expr))
+        };
+
+        Ok(Stmt {
+            kind: StmtKind::Return(expr),
+            span: self.span_from(start),
+        })
+    }
+
+    // Like an if statement, every arm is a block, and the commas after them are optional
+    fn match_statement(&mut self, start: Span) -> Result<Stmt, ParseError> {
+        let subject = self.condition()?;
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", "match_statement"))?;
+        self.block_depth += 1;
+
+        let mut arms = Vec::new();
+        while self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Close, DelimType::Brace))).is_none() {
+            if matches!(self.token().map(|t| &t.kind), None | Some(TokenKind::Eof)) {
+                return Err(self.expected_token("}", "match_statement"));
+            }
+
+            let arm_start = self.span();
+            let pattern = self.match_arm_pattern("match_statement")?;
+            let body = self.braced_block("match_statement")?;
+            self.match_variant(&TokenKind::Comma);
+
+            arms.push(MatchArm {
+                pattern,
+                body,
+                span: self.span_from(arm_start),
+            });
+        }
+
+        self.block_depth -= 1;
+        Ok(Stmt {
+            kind: StmtKind::Match(Box::new(subject), arms),
+            span: self.span_from(start),
+        })
+    }
+
+    // The pattern of an arm, and the `=>` after it
+    fn match_arm_pattern(&mut self, rule: &str) -> Result<Pattern, ParseError> {
+        let pattern = self.pattern()?;
+        self.match_variant(&TokenKind::FatArrow)
+            .ok_or_else(|| self.expected_token("=>", rule))?;
+        Ok(pattern)
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        let start = self.span();
+        let kind = if let Some(literal) = self.match_literal() {
+            PatternKind::Literal(literal)
+        } else if self.match_variant(&TokenKind::Minus).is_some() {
+            match self.match_literal() {
+                Some(Literal::Integer(value, suffix)) => PatternKind::Literal(Literal::Integer(-value, suffix)),
+                Some(Literal::Float(value, suffix)) => PatternKind::Literal(Literal::Float(-value, suffix)),
+                _ => return Err(self.expected_token("number", "pattern")),
+            }
+        } else if self.match_variant(&TokenKind::Dot).is_some() {
+            let variant = self.match_identifier()
+                .ok_or_else(|| self.expected_token("variant name", "pattern"))?;
+            let fields = if self.at_block() {
+                self.field_patterns()?
+            } else {
+                Vec::new()
+            };
+            PatternKind::AnonymousVariant(variant, fields)
+        } else if let Some(name) = self.match_identifier() {
+            if self.match_variant(&TokenKind::Dot).is_some() {
+                let variant = self.match_identifier()
+                    .ok_or_else(|| self.expected_token("variant name", "pattern"))?;
+                let fields = if self.at_block() {
+                    self.field_patterns()?
+                } else {
+                    Vec::new()
+                };
+                PatternKind::Variant(name, variant, fields)
+            } else if name == "_" {
+                PatternKind::Wildcard
+            } else {
+                PatternKind::Binding(name)
+            }
+        } else {
+            return Err(self.expected_token("pattern", "pattern"));
+        };
+
+        Ok(Pattern {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    // `{ red = 0, green }`, a field without a pattern is bound to its name
+    fn field_patterns(&mut self) -> Result<Vec<FieldPattern>, ParseError> {
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", "pattern"))?;
+
+        let mut fields = Vec::new();
+        while !self.match_list_end(DelimType::Brace, !fields.is_empty(), "pattern")? {
+            let field_start = self.span();
+            let name = self.match_identifier()
+                .ok_or_else(|| self.expected_token("field name", "pattern"))?;
+            let pattern = if self.match_variant(&TokenKind::Eq).is_some() {
+                self.pattern()?
+            } else {
+                Pattern {
+                    kind: PatternKind::Binding(name.clone()),
+                    span: self.span_from(field_start),
+                }
+            };
+
+            fields.push(FieldPattern {
+                name,
+                pattern,
+                span: self.span_from(field_start),
+            });
+        }
+
+        Ok(fields)
+    }
+
+    fn braced_block(&mut self, rule: &str) -> Result<Stmt, ParseError> {
+        let start = self.span();
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", rule))?;
+        self.block(start)
+    }
+
+    // Whether the current token starts a statement that is not an expression statement
+    fn at_statement(&mut self) -> bool {
+        match self.token().map(|t| &t.kind) {
+            Some(TokenKind::Keyword(
+                Keyword::Const | Keyword::Var | Keyword::If | Keyword::For | Keyword::While | Keyword::Match |
+                Keyword::Break | Keyword::Continue | Keyword::Return | Keyword::Print | Keyword::Println
+            )) => true,
+            Some(TokenKind::Delim(DelimDir::Open, DelimType::Brace)) => true,
+            Some(TokenKind::Identifier(..)) => {
+                matches!(self.peek_nth(1).map(|t| &t.kind), Some(TokenKind::Colon)) || self.assignment_target_len().is_some()
+            }
+            _ => false,
+        }
+    }
+
+    // How many tokens an assignment target takes, `x` or `x.field.field`, when an `=` follows it
+    fn assignment_target_len(&mut self) -> Option<usize> {
+        if !matches!(self.token().map(|t| &t.kind), Some(TokenKind::Identifier(..))) {
+            return None;
+        }
+
+        let mut len = 1;
+        while matches!(self.peek_nth(len).map(|t| &t.kind), Some(TokenKind::Dot))
+            && matches!(self.peek_nth(len + 1).map(|t| &t.kind), Some(TokenKind::Identifier(..)))
+        {
+            len += 2;
+        }
+
+        matches!(self.peek_nth(len).map(|t| &t.kind), Some(TokenKind::Eq)).then_some(len)
+    }
+
+    fn println_statement(&mut self, start: Span) -> Result<Stmt, ParseError> {
         let expr = self.expression()?;
         self.match_variant(&TokenKind::SemiColon)
-            .ok_or_else(|| ExpectedToken(";".to_string(), "println_statement".to_string()))?;
+            .ok_or_else(|| self.expected_token(";", "println_statement"))?;
         Ok(Stmt {
             kind: StmtKind::Println(Box::new(expr)),
+            span: self.span_from(start),
         })
     }
 
-    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+    fn print_statement(&mut self, start: Span) -> Result<Stmt, ParseError> {
         let expr = self.expression()?;
         self.match_variant(&TokenKind::SemiColon)
-            .ok_or_else(|| ExpectedToken(";".to_string(), "print_statement".to_string()))?;
+            .ok_or_else(|| self.expected_token(";", "print_statement"))?;
         Ok(Stmt {
             kind: StmtKind::Print(Box::new(expr)),
+            span: self.span_from(start),
         })
     }
 
     // tries to parse an assignment statement.
-    // this is a self2 token look ahead operation.
-    // first, match identifier and equal sign
+    // this is a look ahead operation.
+    // first, match the target, an identifier and the fields after it, and the equal sign
     // second, parse expression
-    // Error is returned when we already matched identifier and equal sign but couldn't parse
+    // Error is returned when we already matched the target and equal sign but couldn't parse
     // expression - it means invalid syntax.
     // Ok(None) is returned when we don't have a match.
     fn try_assignment_statement(&mut self) -> Result<Option<Stmt>, ParseError> {
-        if let Some((ident, _)) = self.match_twice(
-            |s| s.match_identifier(),
-            |s| s.match_variant(&TokenKind::Eq))
-        {
-            let expr = self.expression()
-                .map_err(|_| ParseError::Default("assignment_statement expected expression".to_string()))?;
+        if self.assignment_target_len().is_some() {
+            let start = self.span();
+            let ident = self.match_identifier().expect("peeked an identifier");
+
+            let mut fields = Vec::new();
+            while self.match_variant(&TokenKind::Dot).is_some() {
+                fields.push(self.match_identifier().expect("peeked a field"));
+            }
+            self.advance();
+
+            let expr = self.expression()?;
 
             self.match_variant(&TokenKind::SemiColon)
-                .ok_or_else(|| ExpectedToken(";".to_string(), "try_assignment_statement".to_string()))?;
+                .ok_or_else(|| self.expected_token(";", "try_assignment_statement"))?;
+
+            let kind = if fields.is_empty() {
+                StmtKind::Assign(ident.to_string(), Box::new(expr))
+            } else {
+                StmtKind::FieldAssign(ident.to_string(), fields, Box::new(expr))
+            };
 
             return Ok(Some(Stmt {
-                kind: StmtKind::Assign(ident.to_string(), Box::new(expr))
+                kind,
+                span: self.span_from(start),
             }));
         }
 
-        return Ok(None);
+        Ok(None)
     }
 
     fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.span();
         let expr = self.expression()?;
 
-        // TODO: type the error 
-        let _ = self.match_variant(&TokenKind::SemiColon)
-            .ok_or_else(|| ParseError::Default("exprStmt: Expected token \";\"".to_string()));
+        self.match_variant(&TokenKind::SemiColon)
+            .ok_or_else(|| self.expected_token(";", "expression_statement"))?;
 
         Ok(Stmt {
-            kind: StmtKind::Expr(Box::new(expr))
+            kind: StmtKind::Expr(Box::new(expr)),
+            span: self.span_from(start),
         })
     }
 
-    fn expression(&mut self) -> Result<Expr, ParseError> {
-        return self.equality();
-    }
-
-    fn equality(&mut self) -> Result<Expr, ParseError> {
-        let mut lhs = self.comparison()?;
-
-        while let Some(op) = self.match_binary_op(&[BinOp::Eq, BinOp::Ne]) {
-            let rhs = self.expression()?;
-            lhs = Expr {
-                kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs))
-            }
-        }
-
-        Ok(lhs)
+    // The condition of an if, or the header of a loop, a record literal has to be in parens there
+    fn condition(&mut self) -> Result<Expr, ParseError> {
+        let in_condition = std::mem::replace(&mut self.in_condition, true);
+        let condition = self.expression();
+        self.in_condition = in_condition;
+        condition
     }
 
-    fn comparison(&mut self) -> Result<Expr, ParseError> {
-        let mut lhs = self.term()?;
-
-        while let Some(op) = self.match_binary_op(&[BinOp::Gt, BinOp::Ge, BinOp::Lt, BinOp::Le]) {
-            let rhs = self.term()?;
-            lhs = Expr {
-                kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs))
-            }
-        }
-
-        Ok(lhs)
+    // An expression between delimiters, where a record literal is fine even in a condition
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, ParseError>) -> Result<T, ParseError> {
+        let in_condition = std::mem::take(&mut self.in_condition);
+        let result = f(self);
+        self.in_condition = in_condition;
+        result
     }
 
-    fn term(&mut self) -> Result<Expr, ParseError> {
-        let mut lhs = self.factor()?;
+    // A range binds looser than any binary operator, and does not chain
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        let start = self.binary_expression(MIN_PRECEDENCE)?;
 
-        while let Some(op) = self.match_binary_op(&[BinOp::Add, BinOp::Sub]) {
-            let rhs = self.factor()?;
-            lhs = Expr {
-                kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs))
-            }
-        }
+        let kind = if self.match_variant(&TokenKind::DotDot).is_some() {
+            RangeKind::Exclusive
+        } else if self.match_variant(&TokenKind::DotDotEq).is_some() {
+            RangeKind::Inclusive
+        } else {
+            return Ok(start);
+        };
 
-        Ok(lhs)
+        let end = self.binary_expression(MIN_PRECEDENCE)?;
+        Ok(Expr {
+            span: start.span.to(&end.span),
+            kind: ExprKind::Range(Box::new(start), Box::new(end), kind),
+        })
     }
 
-    fn factor(&mut self) -> Result<Expr, ParseError> {
-        // TODO: figure unwraps
+    // Precedence climbing, parses the operators whose precedence is at least `min_precedence`.
+    // The operators and their precedence come from BINARY_OPERATORS.
+    fn binary_expression(&mut self, min_precedence: u8) -> Result<Expr, ParseError> {
         let mut lhs = self.unary()?;
 
-        while let Some(op) = self.match_binary_op(&[BinOp::Mul, BinOp::Div]) {
-            let rhs = self.unary()?;
-            lhs = Expr {
-                kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs))
-            }
+        while let Some(operator) = self
+            .token()
+            .and_then(|t| t.kind.to_binary_op())
+            .map(binary_operator)
+            .filter(|operator| operator.precedence >= min_precedence)
+        {
+            self.advance();
+            let rhs = self.binary_expression(operator.rhs_precedence())?;
+            lhs = binary(operator.op, lhs, rhs);
         }
 
         Ok(lhs)
     }
 
     fn unary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.span();
         if let Some(op) = self.match_unary_op(&[UnOp::Not, UnOp::Neg]) {
             let expr = self.unary()?;
             return Ok(Expr {
                 kind: ExprKind::Unary(op, Box::new(expr)),
+                span: self.span_from(start),
             });
         }
 
-        self.primary()
+        self.postfix()
+    }
+
+    // Calls `f(a, b)` and field accesses `point.x`, which chain, `f(a)(b)` calls what `f(a)` returns
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let start = self.span();
+        let mut expr = self.primary()?;
+
+        loop {
+            let kind = if self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Paren))).is_some() {
+                let mut args = Vec::new();
+                while !self.match_list_end(DelimType::Paren, !args.is_empty(), "call")? {
+                    args.push(self.nested(Self::expression)?);
+                }
+                ExprKind::Call(Box::new(expr), args)
+            } else if self.match_variant(&TokenKind::Dot).is_some() {
+                let field = self.match_identifier()
+                    .ok_or_else(|| self.expected_token("field name", "field"))?;
+                match expr {
+                    // `Color.RGB { .. }`, a variant with fields
+                    Expr { kind: ExprKind::Variable(union), .. } if self.at_block() && !self.in_condition => {
+                        ExprKind::Variant(union, field, self.field_inits("variant")?)
+                    }
+                    expr => ExprKind::Field(Box::new(expr), field),
+                }
+            } else {
+                return Ok(expr);
+            };
+
+            expr = Expr {
+                kind,
+                span: self.span_from(start),
+            };
+        }
     }
 
     fn primary(&mut self) -> Result<Expr, ParseError> {
         // TODO: This is a very shitty code. Refactor.
+        let start = self.span();
         if let Some(literal) = self.match_literal() {
             Ok(Expr {
-                kind: ExprKind::Literal(literal)
+                kind: ExprKind::Literal(literal),
+                span: start,
             })
-        } else if self.match_predicate(|k| match k {
-            TokenKind::Delim(DelimDir::Open, DelimType::Paren) => true,
-            _ => false,
+        } else if let Some(parts) = self.match_template() {
+            self.interpolated(start, parts)
+        } else if self.match_predicate(|k| {
+            matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Paren))
         }).is_some() {
-            let expr = self
-                .expression()
-                .expect("SyntaxError: expected expression - TODO: better error handling");
-
-            let _ = self
-                .match_predicate(|k| match k {
-                    TokenKind::Delim(DelimDir::Close, DelimType::Paren) => true,
-                    _ => false,
-                })
-                .expect("SyntaxError: expected right paren - TODO: better error handling");
-
-            Ok(expr)
+            let expr = self.nested(Self::expression)?;
+
+            self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Close, DelimType::Paren)))
+                .ok_or_else(|| self.expected_token(")", "primary"))?;
+
+            // The parentheses are part of the expression
+            Ok(Expr {
+                span: self.span_from(start),
+                ..expr
+            })
+        } else if self.match_keyword(&Keyword::If).is_some() {
+            self.if_expression(start)
+        } else if self.match_keyword(&Keyword::Match).is_some() {
+            self.match_expression(start)
         } else if let Some(ident) = self.match_identifier() {
+            if self.at_block() && !self.in_condition {
+                return self.record_literal(start, ident);
+            }
+
             Ok(Expr {
-                kind: ExprKind::Variable(ident.to_string())
+                kind: ExprKind::Variable(ident.to_string()),
+                span: start,
             })
         } else {
-            Err(ParseError::Default("primary expression exhausted".to_string()))
+            Err(self.error("primary expression exhausted"))
+        }
+    }
+
+    // `Point { x = 3, y = 4 }`, the name is already consumed
+    fn record_literal(&mut self, start: Span, name: String) -> Result<Expr, ParseError> {
+        let fields = self.field_inits("record_literal")?;
+        Ok(Expr {
+            kind: ExprKind::Record(name, fields),
+            span: self.span_from(start),
+        })
+    }
+
+    // The braced fields of a literal, `{ x = 3, y = 4 }`
+    fn field_inits(&mut self, rule: &str) -> Result<Vec<FieldInit>, ParseError> {
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", rule))?;
+
+        let mut fields = Vec::new();
+        while !self.match_list_end(DelimType::Brace, !fields.is_empty(), rule)? {
+            let field_start = self.span();
+            let name = self.match_identifier()
+                .ok_or_else(|| self.expected_token("field name", rule))?;
+            self.match_variant(&TokenKind::Eq)
+                .ok_or_else(|| self.expected_token("=", rule))?;
+            let value = self.nested(Self::field_value)?;
+
+            fields.push(FieldInit {
+                name,
+                value,
+                span: self.span_from(field_start),
+            });
+        }
+
+        Ok(fields)
+    }
+
+    // The value of a field, which may be an anonymous literal that takes the type of the field,
+    // `{ u = 12, v = 32 }` or `.Line { length = 3 }`. A block is not an expression, so a brace
+    // always starts an anonymous record.
+    fn field_value(&mut self) -> Result<Expr, ParseError> {
+        let start = self.span();
+        let kind = if self.at_block() {
+            ExprKind::AnonymousRecord(self.field_inits("anonymous_record")?)
+        } else if self.match_variant(&TokenKind::Dot).is_some() {
+            let variant = self.match_identifier()
+                .ok_or_else(|| self.expected_token("variant name", "anonymous_variant"))?;
+            let fields = if self.at_block() {
+                self.field_inits("anonymous_variant")?
+            } else {
+                Vec::new()
+            };
+            ExprKind::AnonymousVariant(variant, fields)
+        } else {
+            return self.expression();
+        };
+
+        Ok(Expr {
+            kind,
+            span: self.span_from(start),
+        })
+    }
+
+    // `match subject { pattern => expr, .. }`, an arm is an expression or a block expression
+    fn match_expression(&mut self, start: Span) -> Result<Expr, ParseError> {
+        let subject = self.condition()?;
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", "match_expression"))?;
+        self.block_depth += 1;
+
+        let mut arms = Vec::new();
+        while !self.match_list_end(DelimType::Brace, !arms.is_empty(), "match_expression")? {
+            let arm_start = self.span();
+            let pattern = self.match_arm_pattern("match_expression")?;
+            let body = if self.at_block() {
+                self.block_expression("match_expression")?
+            } else {
+                self.nested(Self::expression)?
+            };
+
+            arms.push(MatchArm {
+                pattern,
+                body,
+                span: self.span_from(arm_start),
+            });
+        }
+
+        self.block_depth -= 1;
+        Ok(Expr {
+            kind: ExprKind::Match(Box::new(subject), arms),
+            span: self.span_from(start),
+        })
+    }
+
+    // Unlike the if statement, the if expression always has an else branch
+    fn if_expression(&mut self, start: Span) -> Result<Expr, ParseError> {
+        let condition = self.condition()?;
+        let then_branch = self.block_expression("if_expression")?;
+
+        self.match_keyword(&Keyword::Else)
+            .ok_or_else(|| self.expected_token("else", "if_expression"))?;
+
+        let else_start = self.span();
+        let else_branch = if self.match_keyword(&Keyword::If).is_some() {
+            self.if_expression(else_start)?
+        } else {
+            self.block_expression("if_expression")?
+        };
+
+        Ok(Expr {
+            kind: ExprKind::If(Box::new(condition), Box::new(then_branch), Box::new(else_branch)),
+            span: self.span_from(start),
+        })
+    }
+
+    // A block that ends with an expression without a `;`, which is the value of the block.
+    // An if expression as the value has to be in parens, a leading `if` is an if statement.
+    // An expression always yields a value, so it can't `break`, `continue` or `return`.
+    fn block_expression(&mut self, rule: &str) -> Result<Expr, ParseError> {
+        let start = self.span();
+        self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Open, DelimType::Brace)))
+            .ok_or_else(|| self.expected_token("{", rule))?;
+        self.block_depth += 1;
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let in_function = std::mem::take(&mut self.in_function);
+        let in_condition = std::mem::take(&mut self.in_condition);
+
+        let mut statements = Vec::new();
+        let value = loop {
+            let doc = self.doc_comments();
+            if self.at_statement() {
+                statements.push(self.decl(doc)?);
+                continue;
+            }
+
+            let expr_start = self.span();
+            let expr = self.expression()?;
+            if self.match_variant(&TokenKind::SemiColon).is_some() {
+                statements.push(Stmt {
+                    kind: StmtKind::Expr(Box::new(expr)),
+                    span: self.span_from(expr_start),
+                });
+            } else {
+                self.match_predicate(|k| matches!(k, TokenKind::Delim(DelimDir::Close, DelimType::Brace)))
+                    .ok_or_else(|| self.expected_token("}", "block_expression"))?;
+                break expr;
+            }
+        };
+
+        self.block_depth -= 1;
+        self.loop_depth = loop_depth;
+        self.in_function = in_function;
+        self.in_condition = in_condition;
+        Ok(Expr {
+            kind: ExprKind::Block(statements, Box::new(value)),
+            span: self.span_from(start),
+        })
+    }
+
+    // Every embedded expression is parsed on its own, and must span all of its tokens
+    fn interpolated(&mut self, span: Span, parts: Vec<TemplatePart>) -> Result<Expr, ParseError> {
+        let parts = parts
+            .into_iter()
+            .map(|part| match part {
+                TemplatePart::Text(text) => Ok(InterpolatedPart::Text(text)),
+                TemplatePart::Expr(tokens) => {
+                    let mut parser = RecursiveDescentParser::new(tokens);
+                    let expr = parser.expression()?;
+                    parser
+                        .match_variant(&TokenKind::Eof)
+                        .ok_or_else(|| parser.expected_token("}", "interpolation"))?;
+                    Ok(InterpolatedPart::Expr(Box::new(expr)))
+                }
+            })
+            .collect::<Result<_, ParseError>>()?;
+
+        Ok(Expr {
+            kind: ExprKind::Interpolated(parts),
+            span,
+        })
+    }
+}
+
+// A parameter of a function type, named when the type declares a function
+struct SignatureParam {
+    name: Option<String>,
+    ty: Type,
+    span: Span,
+}
+
+struct Signature {
+    params: Vec<SignatureParam>,
+    return_type: Option<Type>,
+}
+
+impl Signature {
+    fn into_type(self) -> Type {
+        Type::Function(
+            self.params.into_iter().map(|param| param.ty).collect(),
+            self.return_type.map(Box::new),
+        )
+    }
+}
+
+enum Annotation {
+    Type(Type),
+    Signature(Signature),
+}
+
+impl Annotation {
+    fn into_type(self) -> Type {
+        match self {
+            Annotation::Type(ty) => ty,
+            Annotation::Signature(signature) => signature.into_type(),
         }
     }
 }
 
-impl<'a> Parser for RecursiveDescentParser<'a> {
-    fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
+fn binary(op: BinOp, lhs: Expr, rhs: Expr) -> Expr {
+    Expr {
+        span: lhs.span.to(&rhs.span),
+        kind: ExprKind::Binary(op, Box::new(lhs), Box::new(rhs)),
+    }
+}
+
+impl<I: Iterator<Item = Token>> Parser for RecursiveDescentParser<I> {
+    fn parse(&mut self) -> ParseOutput {
         self.program()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
+
+    fn literal(literal: Literal, start: usize, end: usize) -> Box<Expr> {
+        Box::new(Expr { kind: ExprKind::Literal(literal), span: Span::new(start, end) })
+    }
+
+    fn variable(identifier: &str, start: usize, end: usize) -> Box<Expr> {
+        Box::new(Expr { kind: ExprKind::Variable(identifier.to_string()), span: Span::new(start, end) })
+    }
+
+    #[test]
+    fn parse_token_stream() {
+        let source = "var x = 1;\n// increment\nx = x + 2;\n";
+        let tokens = StreamTokenizer::new(source.as_bytes());
+        let statements = RecursiveDescentParser::new(tokens).parse().into_result().unwrap();
+
+        assert_eq!(statements, vec![
+            Stmt {
+                kind: StmtKind::Decl(Box::new(Decl {
+                    kind: DeclKind::Var("x".to_string(), None, literal(Literal::Integer(1, None), 8, 9)),
+                    doc: None,
+                    span: Span::new(0, 10),
+                })),
+                span: Span::new(0, 10),
+            },
+            Stmt {
+                kind: StmtKind::Assign("x".to_string(), Box::new(Expr {
+                    kind: ExprKind::Binary(BinOp::Add, variable("x", 28, 29), literal(Literal::Integer(2, None), 32, 33)),
+                    span: Span::new(28, 33),
+                })),
+                span: Span::new(24, 34),
+            },
+        ]);
+    }
+
+    #[test]
+    fn parse_expression_after_identifier() {
+        let statements = RecursiveDescentParser::new(Tokenizer::new("x == 2;"))
+            .parse()
+            .into_result()
+            .unwrap();
+
+        assert_eq!(statements, vec![Stmt {
+            kind: StmtKind::Expr(Box::new(Expr {
+                kind: ExprKind::Binary(BinOp::Eq, variable("x", 0, 1), literal(Literal::Integer(2, None), 5, 6)),
+                span: Span::new(0, 6),
+            })),
+            span: Span::new(0, 7),
+        }]);
+    }
+
+    #[test]
+    fn parse_doc_comments() {
+        let source = "/// The answer\n/// to everything\n/* not a doc */ const x = 42;\n/// dropped\n@println x;\n/// var docs\nvar y = x;";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let docs = statements
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Decl(decl) => decl.doc.clone(),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(docs, vec![
+            Some("The answer\nto everything".to_string()),
+            None,
+            Some("var docs".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn parse_interpolated_string() {
+        let statements = RecursiveDescentParser::new(Tokenizer::new(r#"@println "x = {x + 2}!";"#))
+            .parse()
+            .into_result()
+            .unwrap();
+
+        assert_eq!(statements[0].kind, StmtKind::Println(Box::new(Expr {
+            kind: ExprKind::Interpolated(vec![
+                InterpolatedPart::Text("x = ".to_string()),
+                InterpolatedPart::Expr(Box::new(Expr {
+                    kind: ExprKind::Binary(BinOp::Add, variable("x", 15, 16), literal(Literal::Integer(2, None), 19, 20)),
+                    span: Span::new(15, 20),
+                })),
+                InterpolatedPart::Text("!".to_string()),
+            ]),
+            span: Span::new(9, 23),
+        })));
+
+        let errors = RecursiveDescentParser::new(Tokenizer::new(r#"@println "{x y}";"#))
+            .parse()
+            .errors;
+        assert!(matches!(errors[0], ParseError::ExpectedToken(..)));
+        assert_eq!(errors[0].span(), &Span::new(13, 14));
+    }
+
+    #[test]
+    fn recover_from_syntax_errors() {
+        let source = "const a = (1 + 2;\nvar b = 1 const c = 3;\n@println ) + 1; @println c;\nd = ;";
+        let output = RecursiveDescentParser::new(Tokenizer::new(source)).parse();
+
+        let spans = output.errors.iter().map(|error| *error.span()).collect::<Vec<_>>();
+        assert_eq!(spans, vec![
+            // Missing `)`
+            Span::new(16, 17),
+            // Missing `;`
+            Span::new(28, 33),
+            // No expression
+            Span::new(50, 51),
+            // No expression in the assignment
+            Span::new(73, 74),
+        ]);
+
+        // The statements without errors are kept
+        assert_eq!(output.statements.len(), 2);
+        assert!(matches!(&output.statements[0].kind, StmtKind::Decl(decl) if matches!(&decl.kind, DeclKind::Const(c, _, _) if c == "c")));
+        assert!(matches!(output.statements[1].kind, StmtKind::Println(..)));
+    }
+
+    #[test]
+    fn parse_blocks() {
+        let source = "{ var x = 1; { x = 2; } }";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::Block(outer) = &statements[0].kind else {
+            panic!("expected a block, got {:?}", statements[0]);
+        };
+        assert_eq!(statements[0].span, Span::new(0, 25));
+        assert!(matches!(outer[0].kind, StmtKind::Decl(..)));
+        assert!(matches!(&outer[1].kind, StmtKind::Block(inner) if matches!(inner[0].kind, StmtKind::Assign(..))));
+        assert_eq!(outer[1].span, Span::new(13, 23));
+
+        let errors = RecursiveDescentParser::new(Tokenizer::new("{ var x = 1;")).parse().errors;
+        assert!(matches!(&errors[0], ParseError::ExpectedToken(token, ..) if token == "}"));
+    }
+
+    #[test]
+    fn recover_from_errors_in_blocks() {
+        // The error skips the rest of both blocks, not only the statement
+        let source = "{ { x = ; var y = 1; } const z = 2; }
+@println 1;";
+        let output = RecursiveDescentParser::new(Tokenizer::new(source)).parse();
+
+        assert_eq!(output.errors.len(), 1);
+        assert_eq!(output.errors[0].span(), &Span::new(8, 9));
+        assert_eq!(output.statements.len(), 1);
+        assert!(matches!(output.statements[0].kind, StmtKind::Println(..)));
+    }
+
+    #[test]
+    fn parse_if_statements() {
+        let source = "if a { @println 1; } else if b { } else { x = 2; }";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::If(condition, then_branch, Some(else_branch)) = &statements[0].kind else {
+            panic!("expected an if statement with an else branch, got {:?}", statements[0]);
+        };
+        assert_eq!(condition.kind, ExprKind::Variable("a".to_string()));
+        assert!(matches!(&then_branch.kind, StmtKind::Block(statements) if statements.len() == 1));
+
+        // `else if` is an if statement in the else branch
+        let StmtKind::If(_, _, Some(last)) = &else_branch.kind else {
+            panic!("expected an else if, got {:?}", else_branch);
+        };
+        assert_eq!(else_branch.span, Span::new(26, 50));
+        assert!(matches!(&last.kind, StmtKind::Block(statements) if matches!(statements[0].kind, StmtKind::Assign(..))));
+    }
+
+    #[test]
+    fn parse_if_expressions() {
+        let source = "const x = 1 + if a { var y = 2; y } else if b { 3 } else { 4 };";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::Decl(decl) = &statements[0].kind else {
+            panic!("expected a declaration, got {:?}", statements[0]);
+        };
+        let DeclKind::Const(_, _, expr) = &decl.kind else {
+            panic!("expected a const declaration, got {:?}", decl);
+        };
+        let ExprKind::Binary(BinOp::Add, _, rhs) = &expr.kind else {
+            panic!("expected an addition, got {:?}", expr);
+        };
+        let ExprKind::If(_, then_branch, else_branch) = &rhs.kind else {
+            panic!("expected an if expression, got {:?}", rhs);
+        };
+        assert!(matches!(&then_branch.kind, ExprKind::Block(statements, value)
+            if statements.len() == 1 && value.kind == ExprKind::Variable("y".to_string())));
+        assert!(matches!(else_branch.kind, ExprKind::If(..)));
+        assert_eq!(rhs.span, Span::new(14, 62));
+
+        // The else branch is required, the expression needs a value either way
+        let errors = RecursiveDescentParser::new(Tokenizer::new("const x = if a { 1 };")).parse().errors;
+        assert!(matches!(&errors[0], ParseError::ExpectedToken(token, ..) if token == "else"));
+    }
+
+    #[test]
+    fn parse_loops() {
+        let source = "for i in 0..n + 1 { if i == 2 { continue; } } while a { break; }";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::For(variable, iterable, body) = &statements[0].kind else {
+            panic!("expected a for statement, got {:?}", statements[0]);
+        };
+        assert_eq!(variable, "i");
+        assert!(matches!(body.kind, StmtKind::Block(..)));
+
+        // The range binds looser than the addition
+        let ExprKind::Range(start, end, RangeKind::Exclusive) = &iterable.kind else {
+            panic!("expected a range, got {:?}", iterable);
+        };
+        assert_eq!(start.kind, ExprKind::Literal(Literal::Integer(0, None)));
+        assert!(matches!(end.kind, ExprKind::Binary(BinOp::Add, ..)));
+        assert_eq!(iterable.span, Span::new(9, 17));
+
+        assert!(matches!(&statements[1].kind, StmtKind::While(_, body)
+            if matches!(&body.kind, StmtKind::Block(statements) if statements[0].kind == StmtKind::Break)));
+    }
+
+    #[test]
+    fn reject_loop_control_outside_of_loops() {
+        // An if expression always yields a value, even in a loop
+        let source = "continue;\nfor i in 0..=3 { const x = if i > 1 { break; 1 } else { 2 }; }\n@println 1;";
+        let output = RecursiveDescentParser::new(Tokenizer::new(source)).parse();
+
+        let spans = output.errors.iter().map(|error| *error.span()).collect::<Vec<_>>();
+        assert_eq!(spans, vec![Span::new(0, 8), Span::new(48, 53)]);
+        assert_eq!(output.statements.len(), 1);
+    }
+
+    #[test]
+    fn parse_functions() {
+        let source = "/// Adds one\nconst increment: (value: i8, by: Step,) i8 = { return value + by; }\nlog: () = { return; };\nf(1, g(x))(2);";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::Decl(decl) = &statements[0].kind else {
+            panic!("expected a declaration, got {:?}", statements[0]);
+        };
+        let DeclKind::Function(function) = &decl.kind else {
+            panic!("expected a function, got {:?}", decl);
+        };
+        assert_eq!(decl.doc.as_deref(), Some("Adds one"));
+        assert_eq!(function.name, "increment");
+        assert_eq!(function.params, vec![
+            Param { name: "value".to_string(), ty: Type::Primitive(PrimitiveType::I8), span: Span::new(31, 40) },
+            Param { name: "by".to_string(), ty: Type::Named("Step".to_string()), span: Span::new(42, 50) },
+        ]);
+        assert_eq!(function.return_type, Some(Type::Primitive(PrimitiveType::I8)));
+        assert!(matches!(function.body[0].kind, StmtKind::Return(Some(..))));
+
+        // Without a modifier, a return type, or a return value
+        assert!(matches!(&statements[1].kind, StmtKind::Decl(decl) if matches!(&decl.kind,
+            DeclKind::Function(function) if function.return_type.is_none() && function.body[0].kind == StmtKind::Return(None))));
+
+        let StmtKind::Expr(call) = &statements[2].kind else {
+            panic!("expected an expression statement, got {:?}", statements[2]);
+        };
+        let ExprKind::Call(callee, args) = &call.kind else {
+            panic!("expected a call, got {:?}", call);
+        };
+        assert!(matches!(callee.kind, ExprKind::Call(..)));
+        assert_eq!(args.len(), 1);
+        assert_eq!(callee.span, Span::new(104, 114));
+
+        let errors = RecursiveDescentParser::new(Tokenizer::new("return 1;\nfor i in 0..1 { f: () = { break; } }")).parse().errors;
+        let spans = errors.iter().map(|error| *error.span()).collect::<Vec<_>>();
+        assert_eq!(spans, vec![Span::new(0, 6), Span::new(36, 41)]);
+    }
+
+    #[test]
+    fn parse_type_annotations() {
+        let source = "const x: u32 = 4;\nvar f: (i8, (x: bool) string) = g;\ny: Point = p;\nvar z = 1;";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+        let types = statements
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Decl(decl) => match &decl.kind {
+                    DeclKind::Const(_, ty, _) | DeclKind::Var(_, ty, _) => ty.clone(),
+                    _ => panic!("expected a variable, got {:?}", decl),
+                },
+                _ => panic!("expected a declaration, got {:?}", stmt),
+            })
+            .collect::<Vec<_>>();
+
+        let primitive = Type::Primitive;
+        assert_eq!(types, vec![
+            Some(primitive(PrimitiveType::U32)),
+            Some(Type::Function(vec![
+                primitive(PrimitiveType::I8),
+                Type::Function(vec![primitive(PrimitiveType::Bool)], Some(Box::new(primitive(PrimitiveType::String)))),
+            ], None)),
+            Some(Type::Named("Point".to_string())),
+            None,
+        ]);
+
+        // Only functions have a block, and only constant ones
+        let source = "const g: (u32) u32 = { return 1; }\nvar h: () = { }";
+        let errors = RecursiveDescentParser::new(Tokenizer::new(source)).parse().errors;
+        let spans = errors.iter().map(|error| *error.span()).collect::<Vec<_>>();
+        assert_eq!(spans, vec![Span::new(10, 13), Span::new(47, 48)]);
+    }
+
+    #[test]
+    fn parse_records() {
+        let source = "Point = record { x: u32, y: u32, };
+var p = Point { x = 3, y = f(Point {}) };
+p.x = p.y.z;
+if p { }";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::Decl(decl) = &statements[0].kind else {
+            panic!("expected a declaration, got {:?}", statements[0]);
+        };
+        let DeclKind::Record(record) = &decl.kind else {
+            panic!("expected a record, got {:?}", decl);
+        };
+        assert_eq!(record.name, "Point");
+        assert_eq!(record.fields, vec![
+            Field { name: "x".to_string(), ty: Type::Primitive(PrimitiveType::U32), span: Span::new(17, 23) },
+            Field { name: "y".to_string(), ty: Type::Primitive(PrimitiveType::U32), span: Span::new(25, 31) },
+        ]);
+
+        let StmtKind::Decl(decl) = &statements[1].kind else {
+            panic!("expected a declaration, got {:?}", statements[1]);
+        };
+        let DeclKind::Var(_, _, expr) = &decl.kind else {
+            panic!("expected a var, got {:?}", decl);
+        };
+        let ExprKind::Record(name, fields) = &expr.kind else {
+            panic!("expected a record literal, got {:?}", expr);
+        };
+        assert_eq!(name, "Point");
+        assert_eq!(fields.iter().map(|field| field.name.as_str()).collect::<Vec<_>>(), vec!["x", "y"]);
+        assert!(matches!(&fields[1].value.kind, ExprKind::Call(_, args) if matches!(args[0].kind, ExprKind::Record(..))));
+        assert_eq!(expr.span, Span::new(44, 76));
+
+        let StmtKind::FieldAssign(variable, path, value) = &statements[2].kind else {
+            panic!("expected a field assignment, got {:?}", statements[2]);
+        };
+        assert_eq!((variable.as_str(), path.as_slice()), ("p", ["x".to_string()].as_slice()));
+        assert!(matches!(&value.kind, ExprKind::Field(expr, field) if field == "z" && matches!(expr.kind, ExprKind::Field(..))));
+
+        // The braces after a condition are the body, not a record literal
+        assert!(matches!(&statements[3].kind, StmtKind::If(condition, ..) if condition.kind == ExprKind::Variable("p".to_string())));
+
+        // Types are constant, and are not annotated
+        let source = "var P = record { };
+const Q: T = record { };";
+        let errors = RecursiveDescentParser::new(Tokenizer::new(source)).parse().errors;
+        let spans = errors.iter().map(|error| *error.span()).collect::<Vec<_>>();
+        assert_eq!(spans, vec![Span::new(8, 14), Span::new(20, 39)]);
+    }
+
+    #[test]
+    fn parse_unions_and_match() {
+        let source = "Color = union { Red, RGB: record { red: u8 } }\nconst c = match (Color.RGB { red = 1 }) {\n    Color.RGB { red = -1, green } => 1,\n    x => { x },\n};\nmatch c { _ => { } 'c' => { } }";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::Decl(decl) = &statements[0].kind else {
+            panic!("expected a declaration, got {:?}", statements[0]);
+        };
+        let DeclKind::Union(union) = &decl.kind else {
+            panic!("expected a union, got {:?}", decl);
+        };
+        assert_eq!(union.variants, vec![
+            VariantDecl { name: "Red".to_string(), fields: vec![], span: Span::new(16, 19) },
+            VariantDecl {
+                name: "RGB".to_string(),
+                fields: vec![Field { name: "red".to_string(), ty: Type::Primitive(PrimitiveType::U8), span: Span::new(35, 42) }],
+                span: Span::new(21, 44),
+            },
+        ]);
+
+        let StmtKind::Decl(decl) = &statements[1].kind else {
+            panic!("expected a declaration, got {:?}", statements[1]);
+        };
+        let DeclKind::Const(_, _, expr) = &decl.kind else {
+            panic!("expected a const, got {:?}", decl);
+        };
+        let ExprKind::Match(subject, arms) = &expr.kind else {
+            panic!("expected a match expression, got {:?}", expr);
+        };
+        assert!(matches!(&subject.kind, ExprKind::Variant(union, variant, fields) if union == "Color" && variant == "RGB" && fields.len() == 1));
+
+        let PatternKind::Variant(_, _, fields) = &arms[0].pattern.kind else {
+            panic!("expected a variant pattern, got {:?}", arms[0].pattern);
+        };
+        assert_eq!(fields[0].pattern.kind, PatternKind::Literal(Literal::Integer(-1, None)));
+        assert_eq!(fields[1].pattern.kind, PatternKind::Binding("green".to_string()));
+        assert_eq!(arms[1].pattern.kind, PatternKind::Binding("x".to_string()));
+        assert!(matches!(arms[1].body.kind, ExprKind::Block(..)));
+
+        let StmtKind::Match(_, arms) = &statements[2].kind else {
+            panic!("expected a match statement, got {:?}", statements[2]);
+        };
+        let patterns = arms.iter().map(|arm| arm.pattern.kind.clone()).collect::<Vec<_>>();
+        assert_eq!(patterns, vec![PatternKind::Wildcard, PatternKind::Literal(Literal::Char('c'))]);
+    }
+
+    #[test]
+    fn parse_anonymous_types() {
+        let source = "Vertex = record { texture: record { u: u32 }, shape: union { Dot, Line: record { length: u8 } } }\n\
+                      const v = Vertex { texture = { u = 1 }, shape = .Line { length = 2 } };\n\
+                      match v.shape { .Dot => { } _ => { } }\n\
+                      const b = Vertex { texture = { u = (1) }, shape = .Dot };";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+
+        let StmtKind::Decl(decl) = &statements[0].kind else {
+            panic!("expected a declaration, got {:?}", statements[0]);
+        };
+        let DeclKind::Record(record) = &decl.kind else {
+            panic!("expected a record, got {:?}", decl);
+        };
+        assert_eq!(record.fields[0].ty.to_string(), "record { u: u32 }");
+        assert_eq!(record.fields[1].ty.to_string(), "union { Dot, Line: record { length: u8 } }");
+        assert_eq!(record.fields[0].ty, Type::Record(vec![
+            Field { name: "u".to_string(), ty: Type::Primitive(PrimitiveType::U32), span: Span::new(36, 42) },
+        ]));
+
+        let field_values = |stmt: &Stmt| {
+            let StmtKind::Decl(decl) = &stmt.kind else {
+                panic!("expected a declaration, got {:?}", stmt);
+            };
+            let DeclKind::Const(_, _, expr) = &decl.kind else {
+                panic!("expected a const, got {:?}", decl);
+            };
+            let ExprKind::Record(_, fields) = &expr.kind else {
+                panic!("expected a record literal, got {:?}", expr);
+            };
+            fields.iter().map(|field| field.value.kind.clone()).collect::<Vec<_>>()
+        };
+
+        let values = field_values(&statements[1]);
+        assert!(matches!(&values[0], ExprKind::AnonymousRecord(fields) if fields[0].name == "u"));
+        assert!(matches!(&values[1], ExprKind::AnonymousVariant(variant, fields) if variant == "Line" && fields.len() == 1));
+
+        let StmtKind::Match(_, arms) = &statements[2].kind else {
+            panic!("expected a match statement, got {:?}", statements[2]);
+        };
+        assert_eq!(arms[0].pattern.kind, PatternKind::AnonymousVariant("Dot".to_string(), vec![]));
+
+        let values = field_values(&statements[3]);
+        assert!(matches!(&values[0], ExprKind::AnonymousRecord(..)));
+        assert_eq!(values[1], ExprKind::AnonymousVariant("Dot".to_string(), vec![]));
+
+        // The annotation of a declaration is the type of an anonymous literal too
+        let parse = |source| RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result();
+        let statements = parse("const point: Point = { x = 4, y = 3 };\nvar kind: Kind = .Dot;").unwrap();
+        let initializers = statements
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Decl(decl) => match &decl.kind {
+                    DeclKind::Const(_, _, expr) | DeclKind::Var(_, _, expr) => expr.kind.clone(),
+                    kind => panic!("expected a const or a var, got {:?}", kind),
+                },
+                kind => panic!("expected a declaration, got {:?}", kind),
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(&initializers[0], ExprKind::AnonymousRecord(fields) if fields.len() == 2));
+        assert_eq!(initializers[1], ExprKind::AnonymousVariant("Dot".to_string(), vec![]));
+        assert!(parse("const point = { x = 4, y = 3 };").is_err());
+    }
+
+    #[test]
+    fn parse_traits_and_impls() {
+        let source = "Shape = trait { area: (Self) f32, scale: (Self, f32) Self }\n\
+                      impl Shape for Circle {\n\
+                          /// The area\n\
+                          area: (circle: Circle) f32 = { return 1.0; }\n\
+                          scale: (circle: Circle, by: f32) Circle = { return circle; };\n\
+                      }\n\
+                      const report: (shape: impl Shape) = { }";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+        let decls = statements
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Decl(decl) => &decl.kind,
+                _ => panic!("expected a declaration, got {:?}", stmt),
+            })
+            .collect::<Vec<_>>();
+
+        let DeclKind::Trait(trait_decl) = decls[0] else {
+            panic!("expected a trait, got {:?}", decls[0]);
+        };
+        assert_eq!(trait_decl.methods[1].ty.to_string(), "(Self, f32) Self");
+        assert_eq!(trait_decl.method_type(&trait_decl.methods[1], "Circle").to_string(), "(Circle, f32) Circle");
+
+        let DeclKind::Impl(impl_decl) = decls[1] else {
+            panic!("expected an impl, got {:?}", decls[1]);
+        };
+        assert_eq!((impl_decl.trait_name.as_str(), impl_decl.type_name.as_str()), ("Shape", "Circle"));
+        assert_eq!(impl_decl.methods.iter().map(|method| method.name.as_str()).collect::<Vec<_>>(), ["area", "scale"]);
+
+        let DeclKind::Function(function) = decls[2] else {
+            panic!("expected a function, got {:?}", decls[2]);
+        };
+        assert_eq!(function.params[0].ty, Type::Impl("Shape".to_string()));
+
+        let parse = |source| RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result();
+        assert!(parse("Shape = trait { area: f32 };").is_err());
+        assert!(parse("impl Shape for Circle { const x = 1; }").is_err());
+        assert!(parse("var Shape = trait { area: (Self) f32 };").is_err());
+    }
+
+    // Renders the expression with every binary and unary expression in parens
+    fn parenthesize(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Binary(op, lhs, rhs) => format!("({} {} {})", parenthesize(lhs), op.as_code(), parenthesize(rhs)),
+            ExprKind::Unary(op, expr) => format!("({}{})", op.as_code(), parenthesize(expr)),
+            ExprKind::Literal(Literal::Integer(value, _)) => value.to_string(),
+            ExprKind::Literal(Literal::Bool(value)) => value.to_string(),
+            ExprKind::Variable(id) => id.clone(),
+            kind => panic!("unexpected expression {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn parse_operator_precedence() {
+        let cases = [
+            ("a - b - c", "((a - b) - c)"),
+            ("a / b * c", "((a / b) * c)"),
+            ("1 + 2 * 3 % 4", "(1 + ((2 * 3) % 4))"),
+            ("a || b && c", "(a || (b && c))"),
+            ("a == b < c", "(a == (b < c))"),
+            ("x & 1 == 0", "((x & 1) == 0)"),
+            ("a | b ^ c & d", "(a | (b ^ (c & d)))"),
+            ("1 << 2 + 1", "(1 << (2 + 1))"),
+            ("-a * !b", "((-a) * (!b))"),
+            ("(a || b) && c", "((a || b) && c)"),
+        ];
+
+        for (source, expected) in cases {
+            let statements = RecursiveDescentParser::new(Tokenizer::new(&format!("{};", source)))
+                .parse()
+                .into_result()
+                .unwrap();
+            let StmtKind::Expr(expr) = &statements[0].kind else {
+                panic!("expected an expression statement, got {:?}", statements[0]);
+            };
+            assert_eq!(parenthesize(expr), expected, "parsing {}", source);
+        }
+    }
+
+    #[test]
+    fn parse_node_spans() {
+        let source = "/// docs\nconst x = -(1 + y) * 2;";
+        let statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+        let text = |span: Span| &source[span.start()..span.end()];
+
+        let StmtKind::Decl(decl) = &statements[0].kind else {
+            panic!("expected a declaration, got {:?}", statements[0]);
+        };
+        assert_eq!(text(statements[0].span), "const x = -(1 + y) * 2;");
+        assert_eq!(text(decl.span), "const x = -(1 + y) * 2;");
+
+        let DeclKind::Const(_, _, expr) = &decl.kind else {
+            panic!("expected a const declaration, got {:?}", decl);
+        };
+        assert_eq!(text(expr.span), "-(1 + y) * 2");
+
+        let ExprKind::Binary(_, lhs, _) = &expr.kind else {
+            panic!("expected a binary expression, got {:?}", expr);
+        };
+        assert_eq!(text(lhs.span), "-(1 + y)");
+    }
+
     // use mypl_lex::prelude::*;
 
     // #[test]
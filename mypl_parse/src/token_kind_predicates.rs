@@ -2,19 +2,11 @@ use mypl_ast::prelude::{BinOp, UnOp};
 use mypl_lex::prelude::TokenKind;
 
 pub trait TokenKindExtensions {
-    fn is_eof(&self) -> bool;
     fn to_binary_op(&self) -> Option<BinOp>;
     fn to_unary_op(&self) -> Option<UnOp>;
 }
 
 impl TokenKindExtensions for TokenKind {
-    fn is_eof(&self) -> bool {
-        match self {
-            TokenKind::Eof => true,
-            _ => false,
-        }
-    }
-
     fn to_unary_op(&self) -> Option<UnOp> {
         use TokenKind::*;
         match self {
@@ -40,6 +32,11 @@ impl TokenKindExtensions for TokenKind {
             Star => Some(BinOp::Mul),
             Slash => Some(BinOp::Div),
             Percent => Some(BinOp::Rem),
+            And => Some(BinOp::BitAnd),
+            Or => Some(BinOp::BitOr),
+            Caret => Some(BinOp::BitXor),
+            LtLt => Some(BinOp::Shl),
+            GtGt => Some(BinOp::Shr),
             _ => None,
         }
     }
@@ -0,0 +1,877 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use mypl_ast::prelude::*;
+use mypl_lex::prelude::{Literal, Span};
+
+use crate::error::SemanticError;
+use crate::exhaustiveness::{check_arms, Pat};
+use crate::resolution::Resolutions;
+
+/// Checks a program before it runs, so its errors are found without running into them.
+///
+/// Every list of statements, the program, a block and a function body, gets a declaration pass
+/// first, which collects the types, traits and impls it declares. The statements are walked after it:
+/// the arms of every match are checked against the unions they name, impls against their traits,
+/// and the bodies of functions with trait bounds against the bounds, where they are declared.
+///
+/// Calls to methods are resolved to the methods of impls by the types of their arguments, and a
+/// function with trait bounds gets an instance for the types of the arguments of every call to it.
+/// The program runs with the calls resolved, values carry no methods.
+pub struct SemanticAnalyzer {
+    // From the program to the innermost block
+    scopes: Vec<Scope>,
+    errors: Vec<SemanticError>,
+
+    // From the program to the innermost instance being checked
+    resolutions: Vec<Resolutions>,
+}
+
+#[derive(Default, Clone)]
+struct Scope {
+    symbols: HashMap<String, Symbol>,
+
+    // The traits implemented in the scope, and the types they are implemented for
+    impls: HashSet<(String, String)>,
+}
+
+// What a name in scope is, as far as the checks care. The other symbols only shadow the ones of
+// enclosing scopes, like a record shadows a union of the same name.
+#[derive(Clone)]
+enum Symbol {
+    Record(Rc<RecordDecl>),
+    Union(Rc<UnionDecl>),
+    Trait(Rc<TraitDecl>),
+
+    /// The method at the index of the trait
+    Method(Rc<TraitDecl>, usize),
+    Function(Rc<Function>),
+
+    /// A function with trait bounds, it is called through its instances
+    Generic(Rc<Generic>),
+
+    /// A parameter bounded by the trait, `shape: impl Shape`
+    Bounded(Rc<TraitDecl>),
+
+    /// Any other value, with the name of its type when it is known before the program runs
+    Value(Option<String>),
+}
+
+struct Generic {
+    function: Function,
+
+    // Where it is declared, its instances are checked in the same scope
+    span: Span,
+    depth: usize,
+    resolutions: usize,
+}
+
+impl Default for SemanticAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SemanticAnalyzer {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![Scope::default()],
+            errors: Vec::new(),
+            resolutions: vec![Resolutions::default()],
+        }
+    }
+
+    /// Checks the statements of a program and resolves their calls, the types and functions they
+    /// declare stay known to the next ones
+    pub fn analyze(&mut self, statements: &mut Vec<Stmt>) -> Vec<SemanticError> {
+        let scope = self.scopes[0].clone();
+        let checkpoint = self.resolutions[0].checkpoint();
+        self.declare_types(statements);
+        for stmt in statements.iter() {
+            stmt.accept_stmt_visitor(self);
+        }
+        self.resolutions[0].apply(statements);
+
+        // A program with errors does not run, so the types, impls and instances it declares are not made
+        if self.errors.iter().any(|error| !error.is_warning()) {
+            self.scopes[0] = scope;
+            self.resolutions[0].rollback(checkpoint);
+        }
+        std::mem::take(&mut self.errors)
+    }
+
+    fn in_scope(&mut self, statements: &[Stmt], f: impl FnOnce(&mut Self)) {
+        self.in_scope_with(Scope::default(), statements, f);
+    }
+
+    // Like `in_scope`, with the symbols of the scope that are not declared by its statements
+    fn in_scope_with(&mut self, scope: Scope, statements: &[Stmt], f: impl FnOnce(&mut Self)) {
+        self.scopes.push(scope);
+        self.declare_types(statements);
+        f(self);
+        self.scopes.pop();
+    }
+
+    // The declaration pass, a type, a trait along with its methods, or an impl is known in the whole
+    // list it is declared in
+    fn declare_types(&mut self, statements: &[Stmt]) {
+        let scope = self.scopes.last_mut().expect("there is always the program scope");
+        for stmt in statements {
+            let StmtKind::Decl(decl) = &stmt.kind else {
+                continue;
+            };
+            match &decl.kind {
+                DeclKind::Record(record) => {
+                    scope.symbols.insert(record.name.clone(), Symbol::Record(Rc::new(record.as_ref().clone())));
+                }
+                DeclKind::Union(union) => {
+                    scope.symbols.insert(union.name.clone(), Symbol::Union(Rc::new(union.as_ref().clone())));
+                }
+                DeclKind::Trait(trait_decl) => {
+                    let trait_decl = Rc::new(trait_decl.as_ref().clone());
+                    scope.symbols.insert(trait_decl.name.clone(), Symbol::Trait(trait_decl.clone()));
+                    for (index, method) in trait_decl.methods.iter().enumerate() {
+                        scope.symbols.insert(method.name.clone(), Symbol::Method(trait_decl.clone(), index));
+                    }
+                }
+                DeclKind::Impl(impl_decl) => {
+                    let key = (impl_decl.trait_name.clone(), impl_decl.type_name.clone());
+                    if !scope.impls.insert(key) {
+                        let error = SemanticError::DuplicateImpl(impl_decl.trait_name.clone(), impl_decl.type_name.clone(), decl.span);
+                        self.errors.push(error);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &str, symbol: Symbol) {
+        let scope = self.scopes.last_mut().expect("there is always the program scope");
+        scope.symbols.insert(name.to_string(), symbol);
+    }
+
+    fn get_symbol(&self, name: &str) -> Option<Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.symbols.get(name)).cloned()
+    }
+
+    fn get_union(&self, name: &str) -> Option<Rc<UnionDecl>> {
+        match self.get_symbol(name)? {
+            Symbol::Union(union) => Some(union),
+            _ => None,
+        }
+    }
+
+    fn get_trait(&self, name: &str) -> Option<Rc<TraitDecl>> {
+        match self.get_symbol(name)? {
+            Symbol::Trait(trait_decl) => Some(trait_decl),
+            _ => None,
+        }
+    }
+
+    fn has_impl(&self, trait_name: &str, type_name: &str) -> bool {
+        let key = (trait_name.to_string(), type_name.to_string());
+        self.scopes.iter().any(|scope| scope.impls.contains(&key))
+    }
+
+    // The name of the type of the value of the expression, when it is known before the program runs
+    fn named_type(&self, expr: &Expr) -> Option<String> {
+        let named = |ty: &Type| match ty {
+            Type::Named(name) => Some(name.clone()),
+            _ => None,
+        };
+        match &expr.kind {
+            ExprKind::Record(name, _) | ExprKind::Variant(name, _, _) => Some(name.clone()),
+            ExprKind::Variable(name) => match self.get_symbol(name)? {
+                Symbol::Value(ty) => ty,
+                _ => None,
+            },
+            ExprKind::Field(expr, field) => match &expr.kind {
+                // `Color.Red`
+                ExprKind::Variable(name) if self.get_union(name).is_some() => Some(name.clone()),
+                _ => match self.get_symbol(&self.named_type(expr)?)? {
+                    Symbol::Record(record) => named(&record.fields.iter().find(|other| &other.name == field)?.ty),
+                    _ => None,
+                },
+            },
+            ExprKind::Call(callee, args) => {
+                if let Some(arg) = self.returned_self(callee, args) {
+                    return self.named_type(arg);
+                }
+                let ExprKind::Variable(name) = &callee.kind else {
+                    return None;
+                };
+                match self.get_symbol(name)? {
+                    Symbol::Function(function) => named(function.return_type.as_ref()?),
+                    Symbol::Generic(generic) => named(generic.function.return_type.as_ref()?),
+                    Symbol::Method(trait_decl, index) => match &trait_decl.methods[index].ty {
+                        Type::Function(_, Some(return_type)) => named(return_type),
+                        _ => None,
+                    },
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // The trait bound of the value of the expression in the body of a function with trait bounds,
+    // along with the parameter it comes from
+    fn bound(&self, expr: &Expr) -> Option<(Rc<TraitDecl>, String)> {
+        match &expr.kind {
+            ExprKind::Variable(name) => match self.get_symbol(name)? {
+                Symbol::Bounded(bound) => Some((bound, name.clone())),
+                _ => None,
+            },
+            ExprKind::Call(callee, args) => self.bound(self.returned_self(callee, args)?),
+            _ => None,
+        }
+    }
+
+    // The argument in the position of `Self` of a call to a method that returns `Self`, the value
+    // of the call has the type of the argument
+    fn returned_self<'a>(&self, callee: &Expr, args: &'a [Expr]) -> Option<&'a Expr> {
+        let ExprKind::Variable(name) = &callee.kind else {
+            return None;
+        };
+        let Symbol::Method(trait_decl, index) = self.get_symbol(name)? else {
+            return None;
+        };
+        let ty = &trait_decl.methods[index].ty;
+        match ty {
+            Type::Function(_, Some(return_type)) if matches!(return_type.as_ref(), Type::Named(name) if name == "Self") => {
+                args.get(ty.self_position()?)
+            }
+            _ => None,
+        }
+    }
+
+    fn check_trait(&mut self, trait_decl: &TraitDecl) {
+        for (index, method) in trait_decl.methods.iter().enumerate() {
+            if trait_decl.methods[..index].iter().any(|other| other.name == method.name) {
+                self.errors.push(SemanticError::DuplicateMethod(method.name.clone(), method.span));
+            }
+            if method.ty.self_position().is_none() {
+                self.errors.push(SemanticError::MethodWithoutSelf(method.name.clone(), method.span));
+            }
+            if let Type::Function(params, _) = &method.ty {
+                if params.iter().any(|param| matches!(param, Type::Impl(_))) {
+                    self.errors.push(SemanticError::BoundedMethod(method.name.clone(), method.span));
+                }
+            }
+        }
+    }
+
+    // The impl has every method of the trait, with `Self` in their types replaced by the type of the impl
+    fn check_impl(&mut self, impl_decl: &ImplDecl, span: Span) {
+        if !matches!(self.get_symbol(&impl_decl.type_name), Some(Symbol::Record(_) | Symbol::Union(_))) {
+            self.errors.push(SemanticError::InvalidImplType(impl_decl.type_name.clone(), span));
+        }
+        let Some(trait_decl) = self.get_trait(&impl_decl.trait_name) else {
+            self.errors.push(SemanticError::UnknownTrait(impl_decl.trait_name.clone(), span));
+            return;
+        };
+
+        for (index, method) in impl_decl.methods.iter().enumerate() {
+            if impl_decl.methods[..index].iter().any(|other| other.name == method.name) {
+                self.errors.push(SemanticError::DuplicateMethod(method.name.clone(), span));
+                continue;
+            }
+            let Some(signature) = trait_decl.methods.iter().find(|signature| signature.name == method.name) else {
+                self.errors.push(SemanticError::UnknownMethod(trait_decl.name.clone(), method.name.clone(), span));
+                continue;
+            };
+
+            let expected = trait_decl.method_type(signature, &impl_decl.type_name);
+            if !method.ty().is_same(&expected) {
+                self.errors.push(SemanticError::MethodTypeMismatch(method.name.clone(), expected, method.ty(), span));
+            }
+        }
+
+        for signature in &trait_decl.methods {
+            if !impl_decl.methods.iter().any(|method| method.name == signature.name) {
+                self.errors.push(SemanticError::MissingMethod(trait_decl.name.clone(), signature.name.clone(), span));
+            }
+        }
+    }
+
+    // The body is checked where the function is declared, a parameter bounded by a trait is only
+    // known to have the methods of the trait
+    fn check_function(&mut self, function: &Function) {
+        let mut scope = Scope::default();
+        for param in &function.params {
+            let symbol = match &param.ty {
+                Type::Impl(trait_name) => match self.get_trait(trait_name) {
+                    Some(trait_decl) => Symbol::Bounded(trait_decl),
+                    None => {
+                        self.errors.push(SemanticError::UnknownTrait(trait_name.clone(), param.span));
+                        Symbol::Value(None)
+                    }
+                },
+                Type::Named(name) => Symbol::Value(Some(name.clone())),
+                _ => Symbol::Value(None),
+            };
+            scope.symbols.insert(param.name.clone(), symbol);
+        }
+
+        self.in_scope_with(scope, &function.body, |analyzer| {
+            for stmt in &function.body {
+                stmt.accept_stmt_visitor(analyzer);
+            }
+        });
+    }
+
+    // A call to a method is resolved to the method of the impl for the type of the argument in the
+    // position of `Self`, and a call to a function with trait bounds to its instance for the types
+    // of the arguments of the bounded parameters
+    fn check_call(&mut self, callee: &Expr, args: &[Expr], span: Span) {
+        let ExprKind::Variable(name) = &callee.kind else {
+            return;
+        };
+        match self.get_symbol(name) {
+            Some(Symbol::Method(trait_decl, index)) => {
+                let method = &trait_decl.methods[index];
+                let Type::Function(params, _) = &method.ty else {
+                    unreachable!("the parser only accepts function types for methods");
+                };
+                if params.len() != args.len() {
+                    self.errors.push(SemanticError::ArityMismatch(name.clone(), params.len(), args.len(), span));
+                    return;
+                }
+
+                // A method without `Self` is reported with the trait
+                let Some(position) = method.ty.self_position() else {
+                    return;
+                };
+                if let Some(type_name) = self.check_bound(&trait_decl, &args[position], name, true) {
+                    let resolved = ExprKind::Method(trait_decl.name.clone(), type_name, name.clone());
+                    self.resolutions.last_mut().expect("there are always the program resolutions").resolve_call(span, resolved);
+                }
+            }
+            Some(Symbol::Generic(generic)) => {
+                let params = &generic.function.params;
+                if params.len() != args.len() {
+                    self.errors.push(SemanticError::ArityMismatch(name.clone(), params.len(), args.len(), span));
+                    return;
+                }
+
+                let mut types = Vec::new();
+                for (param, arg) in params.iter().zip(args) {
+                    if let Type::Impl(trait_name) = &param.ty {
+                        // An unknown trait is reported with the function
+                        let trait_decl = self.get_trait(trait_name);
+                        types.push(trait_decl.and_then(|trait_decl| self.check_bound(&trait_decl, arg, name, false)));
+                    }
+                }
+                if let Some(types) = types.into_iter().collect::<Option<Vec<_>>>() {
+                    let resolved = ExprKind::Variable(self.instantiate(&generic, types));
+                    self.resolutions.last_mut().expect("there are always the program resolutions").resolve_call(span, resolved);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The name of the type of the argument, when it implements the trait. A bounded argument is
+    // only resolved in the instances of the function it is a parameter of, any other argument has
+    // to be of a type known before the program runs.
+    fn check_bound(&mut self, trait_decl: &TraitDecl, arg: &Expr, callee: &str, is_method: bool) -> Option<String> {
+        if let Some((bound, param)) = self.bound(arg) {
+            if bound.name != trait_decl.name {
+                self.errors.push(match is_method {
+                    true => SemanticError::MethodNotInBound(callee.to_string(), param, bound.name.clone(), arg.span),
+                    false => SemanticError::NotImplemented(trait_decl.name.clone(), Type::Impl(bound.name.clone()).to_string(), arg.span),
+                });
+            }
+            return None;
+        }
+
+        let Some(type_name) = self.named_type(arg) else {
+            self.errors.push(SemanticError::UnknownArgumentType(callee.to_string(), arg.span));
+            return None;
+        };
+        if !self.has_impl(&trait_decl.name, &type_name) {
+            self.errors.push(SemanticError::NotImplemented(trait_decl.name.clone(), type_name, arg.span));
+            return None;
+        }
+        Some(type_name)
+    }
+
+    // The name of the instance of the function for the types of its bounded parameters. A new
+    // instance is checked in the scope the function is declared in, like the function, and
+    // declared after it. The errors it shares with the function are reported once.
+    fn instantiate(&mut self, generic: &Generic, types: Vec<String>) -> String {
+        let name = format!("{}<{}>", generic.function.name, types.join(", "));
+        if !self.resolutions[generic.resolutions].add_name(&name) {
+            return name;
+        }
+
+        let mut instance = generic.function.clone();
+        instance.name = name.clone();
+        let bounded = instance.params.iter_mut().filter(|param| matches!(param.ty, Type::Impl(_)));
+        for (param, type_name) in bounded.zip(types) {
+            param.ty = Type::Named(type_name);
+        }
+
+        let inner_scopes = self.scopes.split_off(generic.depth + 1);
+        let reported = self.errors.len();
+        self.resolutions.push(Resolutions::default());
+        self.check_function(&instance);
+        let mut resolutions = self.resolutions.pop().expect("the resolutions of the instance were pushed");
+        self.scopes.extend(inner_scopes);
+
+        for error in self.errors.split_off(reported) {
+            let message = error.to_string();
+            if !self.errors.iter().any(|other| other.span() == error.span() && other.to_string() == message) {
+                self.errors.push(error);
+            }
+        }
+
+        resolutions.apply(&mut instance.body);
+        self.resolutions[generic.resolutions].add_instance(generic.span, &generic.function.name, instance);
+        name
+    }
+
+    // The names the pattern binds in its arm
+    fn declare_bindings(scope: &mut Scope, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Binding(name) => {
+                scope.symbols.insert(name.clone(), Symbol::Value(None));
+            }
+            PatternKind::Variant(_, _, fields) | PatternKind::AnonymousVariant(_, fields) => {
+                fields.iter().for_each(|field| Self::declare_bindings(scope, &field.pattern));
+            }
+            PatternKind::Wildcard | PatternKind::Literal(_) => {}
+        }
+    }
+
+    fn check_match<T>(&mut self, arms: &[MatchArm<T>], span: Span) {
+        let patterns = arms
+            .iter()
+            .map(|arm| Pat::lower(&arm.pattern, None, &|name| self.get_union(name)))
+            .collect::<Option<Vec<_>>>();
+
+        // A pattern that names an unknown union, variant or field is an error when the arm is tried
+        let Some(patterns) = patterns else {
+            return;
+        };
+
+        let (missing, unreachable) = check_arms(&patterns);
+        for index in unreachable {
+            self.errors.push(SemanticError::UnreachableArm(arms[index].pattern.span));
+        }
+        if !missing.is_empty() {
+            let missing = missing.iter().map(|pattern| pattern.to_string()).collect();
+            self.errors.push(SemanticError::NonExhaustiveMatch(missing, span));
+        }
+    }
+}
+
+impl ExprVisitor for SemanticAnalyzer {
+    type Result = ();
+
+    fn visit_binary_expr(&mut self, _op: &BinOp, lhs: &Expr, rhs: &Expr, _span: Span) {
+        lhs.accept_expr_visitor(self);
+        rhs.accept_expr_visitor(self);
+    }
+
+    fn visit_unary_expr(&mut self, _op: &UnOp, expr: &Expr, _span: Span) {
+        expr.accept_expr_visitor(self);
+    }
+
+    fn visit_literal_expr(&mut self, _literal: &Literal, _span: Span) {}
+
+    fn visit_interpolated_expr(&mut self, parts: &[InterpolatedPart], _span: Span) {
+        for part in parts {
+            if let InterpolatedPart::Expr(expr) = part {
+                expr.accept_expr_visitor(self);
+            }
+        }
+    }
+
+    // A method or a function with trait bounds is resolved where it is called, it is no value of its own
+    fn visit_variable_expr(&mut self, identifier: &str, span: Span) {
+        if let Some(Symbol::Method(..) | Symbol::Generic(_)) = self.get_symbol(identifier) {
+            self.errors.push(SemanticError::UnresolvedValue(identifier.to_string(), span));
+        }
+    }
+
+    fn visit_if_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr, _span: Span) {
+        condition.accept_expr_visitor(self);
+        then_branch.accept_expr_visitor(self);
+        else_branch.accept_expr_visitor(self);
+    }
+
+    fn visit_block_expr(&mut self, statements: &[Stmt], value: &Expr, _span: Span) {
+        self.in_scope(statements, |analyzer| {
+            for stmt in statements {
+                stmt.accept_stmt_visitor(analyzer);
+            }
+            value.accept_expr_visitor(analyzer);
+        });
+    }
+
+    fn visit_range_expr(&mut self, start: &Expr, end: &Expr, _kind: RangeKind, _span: Span) {
+        start.accept_expr_visitor(self);
+        end.accept_expr_visitor(self);
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, args: &[Expr], span: Span) {
+        // A name that is called is resolved by the call
+        if !matches!(callee.kind, ExprKind::Variable(_)) {
+            callee.accept_expr_visitor(self);
+        }
+        for arg in args {
+            arg.accept_expr_visitor(self);
+        }
+        self.check_call(callee, args, span);
+    }
+
+    fn visit_record_expr(&mut self, _name: &str, fields: &[FieldInit], _span: Span) {
+        for field in fields {
+            field.value.accept_expr_visitor(self);
+        }
+    }
+
+    fn visit_field_expr(&mut self, expr: &Expr, _field: &str, span: Span) {
+        expr.accept_expr_visitor(self);
+        if let Some((bound, param)) = self.bound(expr) {
+            self.errors.push(SemanticError::FieldOfBoundedValue(param, bound.name.clone(), span));
+        }
+    }
+
+    fn visit_variant_expr(&mut self, _union: &str, _variant: &str, fields: &[FieldInit], _span: Span) {
+        for field in fields {
+            field.value.accept_expr_visitor(self);
+        }
+    }
+
+    fn visit_match_expr(&mut self, subject: &Expr, arms: &[MatchArm<Expr>], span: Span) {
+        subject.accept_expr_visitor(self);
+        for arm in arms {
+            let mut scope = Scope::default();
+            Self::declare_bindings(&mut scope, &arm.pattern);
+            self.in_scope_with(scope, &[], |analyzer| arm.body.accept_expr_visitor(analyzer));
+        }
+        self.check_match(arms, span);
+    }
+
+    fn visit_anonymous_record_expr(&mut self, fields: &[FieldInit], _span: Span) {
+        for field in fields {
+            field.value.accept_expr_visitor(self);
+        }
+    }
+
+    fn visit_anonymous_variant_expr(&mut self, _variant: &str, fields: &[FieldInit], _span: Span) {
+        for field in fields {
+            field.value.accept_expr_visitor(self);
+        }
+    }
+
+    fn visit_method_expr(&mut self, _trait_name: &str, _type_name: &str, _method: &str, _span: Span) {}
+}
+
+impl StmtVisitor for SemanticAnalyzer {
+    type Result = ();
+
+    fn visit_expr_stmt(&mut self, expr: &Expr, _span: Span) {
+        expr.accept_expr_visitor(self);
+    }
+
+    fn visit_print_stmt(&mut self, expr: &Expr, _span: Span) {
+        expr.accept_expr_visitor(self);
+    }
+
+    fn visit_println_stmt(&mut self, expr: &Expr, _span: Span) {
+        expr.accept_expr_visitor(self);
+    }
+
+    fn visit_decl_stmt(&mut self, decl: &Decl, _span: Span) {
+        match &decl.kind {
+            DeclKind::Var(name, ty, expr) | DeclKind::Const(name, ty, expr) => {
+                expr.accept_expr_visitor(self);
+                // A var without a type may be assigned a value of another type
+                let ty = match ty {
+                    Some(Type::Named(ty)) => Some(ty.clone()),
+                    Some(_) => None,
+                    None if matches!(decl.kind, DeclKind::Const(..)) => self.named_type(expr),
+                    None => None,
+                };
+                self.declare(name, Symbol::Value(ty));
+            }
+            DeclKind::Function(function) => {
+                let symbol = match function.params.iter().any(|param| matches!(param.ty, Type::Impl(_))) {
+                    true => Symbol::Generic(Rc::new(Generic {
+                        function: function.as_ref().clone(),
+                        span: decl.span,
+                        depth: self.scopes.len() - 1,
+                        resolutions: self.resolutions.len() - 1,
+                    })),
+                    false => Symbol::Function(Rc::new(function.as_ref().clone())),
+                };
+                self.declare(&function.name, symbol);
+                self.check_function(function);
+            }
+            DeclKind::Trait(trait_decl) => self.check_trait(trait_decl),
+            DeclKind::Impl(impl_decl) => {
+                self.check_impl(impl_decl, decl.span);
+                for method in &impl_decl.methods {
+                    self.check_function(method);
+                }
+            }
+            DeclKind::Record(_) | DeclKind::Union(_) => {}
+        }
+    }
+
+    fn visit_assign_stmt(&mut self, _identifier: &str, expr: &Expr, _span: Span) {
+        expr.accept_expr_visitor(self);
+    }
+
+    fn visit_field_assign_stmt(&mut self, _identifier: &str, _fields: &[String], expr: &Expr, _span: Span) {
+        expr.accept_expr_visitor(self);
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt], _span: Span) {
+        self.in_scope(statements, |analyzer| {
+            for stmt in statements {
+                stmt.accept_stmt_visitor(analyzer);
+            }
+        });
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>, _span: Span) {
+        condition.accept_expr_visitor(self);
+        then_branch.accept_stmt_visitor(self);
+        if let Some(else_branch) = else_branch {
+            else_branch.accept_stmt_visitor(self);
+        }
+    }
+
+    fn visit_for_stmt(&mut self, variable: &str, iterable: &Expr, body: &Stmt, _span: Span) {
+        iterable.accept_expr_visitor(self);
+        let mut scope = Scope::default();
+        scope.symbols.insert(variable.to_string(), Symbol::Value(None));
+        self.in_scope_with(scope, &[], |analyzer| body.accept_stmt_visitor(analyzer));
+    }
+
+    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt, _span: Span) {
+        condition.accept_expr_visitor(self);
+        body.accept_stmt_visitor(self);
+    }
+
+    fn visit_break_stmt(&mut self, _span: Span) {}
+
+    fn visit_continue_stmt(&mut self, _span: Span) {}
+
+    fn visit_return_stmt(&mut self, expr: Option<&Expr>, _span: Span) {
+        if let Some(expr) = expr {
+            expr.accept_expr_visitor(self);
+        }
+    }
+
+    fn visit_match_stmt(&mut self, subject: &Expr, arms: &[MatchArm<Stmt>], span: Span) {
+        subject.accept_expr_visitor(self);
+        for arm in arms {
+            let mut scope = Scope::default();
+            Self::declare_bindings(&mut scope, &arm.pattern);
+            self.in_scope_with(scope, &[], |analyzer| arm.body.accept_stmt_visitor(analyzer));
+        }
+        self.check_match(arms, span);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mypl_parse::prelude::*;
+    use mypl_lex::prelude::Tokenizer;
+
+    const COLOR: &str = "Color = union { Red, Green, Blue, RGB: record { red: u8, green: u8, blue: u8 } };\n";
+
+    fn analyze(source: &str) -> Vec<String> {
+        let mut statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+        SemanticAnalyzer::new()
+            .analyze(&mut statements)
+            .iter()
+            .map(|error| error.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn report_missing_variants() {
+        let errors = analyze(&format!("{COLOR}const c = Color.Red;\nconst n = match c {{ Color.Red => 0, Color.RGB {{ red = 0 }} => 1 }};"));
+        assert_eq!(errors, ["Match is not exhaustive, Color.Green, Color.Blue not covered"]);
+
+        let errors = analyze(&format!("{COLOR}var c = Color.Red;\nmatch c {{ Color.Red => {{ }} Color.Green => {{ }} Color.Blue => {{ }} Color.RGB {{ blue = 0 }} => {{ }} }}"));
+        assert_eq!(errors, ["Match is not exhaustive, Color.RGB not covered"]);
+
+        // Every variant, or a catch all, covers the union
+        assert!(analyze(&format!("{COLOR}const f: (c: Color) u8 = {{ return match c {{ Color.Red => 0, Color.Green => 1, Color.Blue => 2, Color.RGB => 3 }}; }}")).is_empty());
+        assert!(analyze(&format!("{COLOR}const n = match Color.Red {{ Color.Red => 0, other => 1 }};")).is_empty());
+    }
+
+    #[test]
+    fn report_missing_literals_and_fields() {
+        assert_eq!(analyze("const n = match 3 { 1 => 1, 2 => 2 };"), ["Match is not exhaustive, _ not covered"]);
+        assert_eq!(analyze("const n = match true { true => 1 };"), ["Match is not exhaustive, false not covered"]);
+        assert!(analyze("const n = match true { true => 1, false => 0 };").is_empty());
+
+        let errors = analyze(&format!(
+            "{COLOR}Pair = union {{ Both: record {{ left: bool, right: Color }} }};\n\
+             const p = Pair.Both {{ left = true, right = Color.Red }};\n\
+             const n = match p {{ Pair.Both {{ left = true }} => 0, Pair.Both {{ right = Color.Red }} => 1 }};"
+        ));
+        assert_eq!(errors, ["Match is not exhaustive, Pair.Both { left = false, right = Color.Green }, Pair.Both { left = false, right = Color.Blue }, Pair.Both { left = false, right = Color.RGB } not covered"]);
+    }
+
+    #[test]
+    fn check_anonymous_variants_of_fields() {
+        // The union of `.Edge` is the type of the field
+        let source = "Shape = union { Poly: record { kind: union { Corner, Edge: record { length: u8 } } } };\n\
+                      const s = Shape.Poly { kind = .Corner };\n\
+                      const n = match s { Shape.Poly { kind = .Edge { length = 0 } } => 0, Shape.Poly { kind = .Corner } => 1 };";
+        assert_eq!(analyze(source), ["Match is not exhaustive, Shape.Poly { kind = .Edge } not covered"]);
+
+        // Without the type of the value, the union is not known
+        assert!(analyze("const n = match 1 { .Corner => 0 };").is_empty());
+    }
+
+    #[test]
+    fn report_unreachable_arms() {
+        let errors = analyze(&format!(
+            "{COLOR}const n = match Color.Red {{ Color.RGB => 0, Color.RGB {{ red = 1 }} => 1, _ => 2, Color.Red => 3 }};"
+        ));
+        assert_eq!(errors.len(), 2);
+        assert!(errors.iter().all(|error| error.starts_with("Unreachable arm")));
+
+        assert_eq!(analyze("const n = match 1 { 1 => 1, 1 => 2, x => x };").len(), 1);
+    }
+
+    const SHAPE: &str = "Shape = trait { area: (Self) f32 };\nCircle = record { radius: f32 };\n";
+
+    #[test]
+    fn check_impls_against_traits() {
+        let impl_circle = "impl Shape for Circle { area: (circle: Circle) f32 = { return circle.radius; } }";
+        assert!(analyze(&format!("{SHAPE}{impl_circle}")).is_empty());
+        assert_eq!(analyze(&format!("{SHAPE}{impl_circle}\n{impl_circle}")), ["Trait \"Shape\" is already implemented for \"Circle\""]);
+
+        let errors = analyze(&format!("{SHAPE}impl Shape for Circle {{ area: (circle: Circle) u8 = {{ return 1; }} }}"));
+        assert_eq!(errors, ["Method \"area\" has type \"(Circle) u8\", the trait expects \"(Circle) f32\""]);
+
+        let errors = analyze(&format!("{SHAPE}impl Shape for Circle {{ size: (circle: Circle) f32 = {{ return 1.0; }} }}"));
+        assert_eq!(errors, ["Trait \"Shape\" has no method \"size\"", "Method \"area\" of trait \"Shape\" is not implemented"]);
+
+        assert_eq!(analyze("Empty = trait { make: () u8 };"), ["Method \"make\" has no parameter of type \"Self\" to dispatch on"]);
+        assert_eq!(analyze(&format!("{SHAPE}impl Shape for Shape {{ }}")).len(), 2);
+    }
+
+    #[test]
+    fn forget_rejected_programs() {
+        let mut analyzer = SemanticAnalyzer::new();
+        let mut analyze = |source: &str| {
+            let mut statements = RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+            analyzer.analyze(&mut statements).len()
+        };
+
+        // The impl and the types of a program that does not run are not declared
+        assert_eq!(analyze(&format!("{SHAPE}impl Shape for Circle {{ area: (circle: Circle) u8 = {{ return 1; }} }}")), 1);
+        assert_eq!(analyze("const f: (shape: impl Shape) = { }"), 1);
+        assert_eq!(analyze(&format!("{SHAPE}impl Shape for Circle {{ area: (circle: Circle) f32 = {{ return 1.0; }} }}")), 0);
+        assert_eq!(analyze("const f: (shape: impl Shape) = { }"), 0);
+    }
+
+    #[test]
+    fn check_trait_bounds() {
+        // The body is checked where the function is declared, whether or not it is called
+        let source = format!(
+            "{SHAPE}Named = trait {{ name: (Self) string }};\n\
+             const f: (shape: impl Shape) = {{ @println shape.radius; @println name(shape); }}"
+        );
+        assert_eq!(analyze(&source), [
+            "Cannot access a field of \"shape\", it is only known to implement \"Shape\"",
+            "\"name\" is not a method of \"Shape\", the bound of \"shape\"",
+        ]);
+
+        // A binding of the same name shadows the bounded parameter
+        let source = format!("{SHAPE}const f: (shape: impl Shape) = {{ const shape = Circle {{ radius = 1.0 }}; @println shape.radius; }}");
+        assert!(analyze(&source).is_empty());
+
+        // The arguments of a known type have to implement the trait
+        let source = format!(
+            "{SHAPE}const f: (shape: impl Shape) f32 = {{ return area(shape); }}\n\
+             const c = Circle {{ radius = 1.0 }};\n\
+             @println f(c) + area(c);"
+        );
+        assert_eq!(analyze(&source), ["Trait \"Shape\" is not implemented for \"Circle\""; 2]);
+        assert!(analyze(&format!("{source}\nimpl Shape for Circle {{ area: (circle: Circle) f32 = {{ return 1.0; }} }}")).is_empty());
+
+        assert_eq!(analyze("const f: (shape: impl Shape) = { }"), ["Trait \"Shape\" not found"]);
+    }
+
+    #[test]
+    fn resolve_calls_by_static_types() {
+        let impl_circle = "impl Shape for Circle { area: (circle: Circle) f32 = { return circle.radius; } }";
+        let source = format!(
+            "{SHAPE}{impl_circle}\n\
+             const f: (shape: impl Shape) f32 = {{ return area(shape); }}\n\
+             const c = Circle {{ radius = 1.0 }};\n\
+             @println f(c) + area(c);"
+        );
+        let mut statements = RecursiveDescentParser::new(Tokenizer::new(&source)).parse().into_result().unwrap();
+        assert!(SemanticAnalyzer::new().analyze(&mut statements).is_empty());
+
+        // The instance of `f` for `Circle` is declared after `f`, and calls the method of the impl
+        let StmtKind::Decl(decl) = &statements[4].kind else {
+            panic!("the instance should be declared after the function");
+        };
+        let DeclKind::Function(instance) = &decl.kind else {
+            panic!("the instance should be a function");
+        };
+        assert_eq!(instance.name, "f<Circle>");
+        assert_eq!(instance.params[0].ty, Type::Named("Circle".to_string()));
+        let StmtKind::Return(Some(value)) = &instance.body[0].kind else {
+            panic!("the body of the instance should return");
+        };
+        let ExprKind::Call(callee, _) = &value.kind else {
+            panic!("the instance should call the method");
+        };
+        assert_eq!(callee.kind, ExprKind::Method("Shape".to_string(), "Circle".to_string(), "area".to_string()));
+
+        let StmtKind::Println(sum) = &statements[6].kind else {
+            panic!("the program should end with the print");
+        };
+        let ExprKind::Binary(_, lhs, rhs) = &sum.kind else {
+            panic!("the print should add the calls");
+        };
+        assert!(matches!(&lhs.kind, ExprKind::Call(callee, _) if callee.kind == ExprKind::Variable("f<Circle>".to_string())));
+        assert!(matches!(&rhs.kind, ExprKind::Call(callee, _) if matches!(callee.kind, ExprKind::Method(..))));
+
+        // The types of the arguments have to be known before the program runs
+        let source = format!("{SHAPE}{impl_circle}\nvar c = Circle {{ radius = 1.0 }};\n@println area(c);\nconst a = area;");
+        assert_eq!(analyze(&source), [
+            "Cannot resolve the call to \"area\", the type of the argument is not known before the program runs",
+            "\"area\" is resolved by the types of its arguments, it can only be called",
+        ]);
+        let source = format!("{SHAPE}{impl_circle}\nconst make: () Circle = {{ return Circle {{ radius = 1.0 }}; }}\n@println area(make()) + area(make(), 1);");
+        assert_eq!(analyze(&source), ["Function \"area\" takes 1 arguments, but 2 were given"]);
+        assert_eq!(analyze("Shape = trait { area: (Self, impl Shape) f32 };"), [
+            "Method \"area\" has a parameter bounded by a trait, only functions can have trait bounds",
+        ]);
+    }
+
+    #[test]
+    fn resolve_unions_by_scope() {
+        // A union is known in the whole block it is declared in, and a record shadows it
+        let source = "{ const n = match Shape.Dot { Shape.Dot => 0 }; Shape = union { Dot, Line }; }";
+        assert_eq!(analyze(source), ["Match is not exhaustive, Shape.Line not covered"]);
+
+        let source = "Shape = union { Dot, Line };\n{ Shape = record { x: u8 }; const n = match 1 { Shape.Dot => 0 }; }";
+        assert!(analyze(source).is_empty());
+
+        // The types of earlier statements stay known, like in the repl
+        let mut analyzer = SemanticAnalyzer::new();
+        let parse = |source| RecursiveDescentParser::new(Tokenizer::new(source)).parse().into_result().unwrap();
+        assert!(analyzer.analyze(&mut parse(COLOR)).is_empty());
+        assert_eq!(analyzer.analyze(&mut parse("const n = match Color.Red { Color.RGB => 1 };")).len(), 1);
+    }
+}
@@ -0,0 +1,97 @@
+use mypl_ast::prelude::Type;
+use mypl_lex::prelude::Span;
+
+/// The patterns listed in an error, the rest are only counted
+const MAX_LISTED_PATTERNS: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SemanticError {
+    #[error("Match is not exhaustive, {} not covered", list_patterns(.0))]
+    NonExhaustiveMatch(Vec<String>, Span),
+
+    #[error("Unreachable arm, the arms before it match everything it does")]
+    UnreachableArm(Span),
+
+    #[error("Trait \"{0}\" not found")]
+    UnknownTrait(String, Span),
+
+    #[error("Method \"{0}\" is declared more than once")]
+    DuplicateMethod(String, Span),
+
+    #[error("Method \"{0}\" has no parameter of type \"Self\" to dispatch on")]
+    MethodWithoutSelf(String, Span),
+
+    #[error("Cannot implement a trait for \"{0}\", only for a record or union type")]
+    InvalidImplType(String, Span),
+
+    #[error("Trait \"{0}\" is already implemented for \"{1}\"")]
+    DuplicateImpl(String, String, Span),
+
+    #[error("Trait \"{0}\" has no method \"{1}\"")]
+    UnknownMethod(String, String, Span),
+
+    #[error("Method \"{1}\" of trait \"{0}\" is not implemented")]
+    MissingMethod(String, String, Span),
+
+    #[error("Method \"{0}\" has type \"{2}\", the trait expects \"{1}\"")]
+    MethodTypeMismatch(String, Type, Type, Span),
+
+    #[error("\"{0}\" is not a method of \"{2}\", the bound of \"{1}\"")]
+    MethodNotInBound(String, String, String, Span),
+
+    #[error("Cannot access a field of \"{0}\", it is only known to implement \"{1}\"")]
+    FieldOfBoundedValue(String, String, Span),
+
+    #[error("Trait \"{0}\" is not implemented for \"{1}\"")]
+    NotImplemented(String, String, Span),
+
+    #[error("Method \"{0}\" has a parameter bounded by a trait, only functions can have trait bounds")]
+    BoundedMethod(String, Span),
+
+    #[error("Function \"{0}\" takes {1} arguments, but {2} were given")]
+    ArityMismatch(String, usize, usize, Span),
+
+    #[error("Cannot resolve the call to \"{0}\", the type of the argument is not known before the program runs")]
+    UnknownArgumentType(String, Span),
+
+    #[error("\"{0}\" is resolved by the types of its arguments, it can only be called")]
+    UnresolvedValue(String, Span),
+}
+
+impl SemanticError {
+    pub fn span(&self) -> &Span {
+        use SemanticError::*;
+        match self {
+            NonExhaustiveMatch(_, span) => span,
+            UnreachableArm(span) => span,
+            UnknownTrait(_, span) => span,
+            DuplicateMethod(_, span) => span,
+            MethodWithoutSelf(_, span) => span,
+            InvalidImplType(_, span) => span,
+            DuplicateImpl(_, _, span) => span,
+            UnknownMethod(_, _, span) => span,
+            MissingMethod(_, _, span) => span,
+            MethodTypeMismatch(_, _, _, span) => span,
+            MethodNotInBound(_, _, _, span) => span,
+            FieldOfBoundedValue(_, _, span) => span,
+            NotImplemented(_, _, span) => span,
+            BoundedMethod(_, span) => span,
+            ArityMismatch(_, _, _, span) => span,
+            UnknownArgumentType(_, span) => span,
+            UnresolvedValue(_, span) => span,
+        }
+    }
+
+    /// A warning does not stop the program from running
+    pub fn is_warning(&self) -> bool {
+        matches!(self, SemanticError::UnreachableArm(_))
+    }
+}
+
+fn list_patterns(patterns: &[String]) -> String {
+    let listed = patterns[..patterns.len().min(MAX_LISTED_PATTERNS)].join(", ");
+    match patterns.len().checked_sub(MAX_LISTED_PATTERNS) {
+        Some(more) if more > 0 => format!("{} and {} more", listed, more),
+        _ => listed,
+    }
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+use std::iter::once;
+use std::rc::Rc;
+
+use mypl_ast::prelude::*;
+use mypl_lex::prelude::Literal;
+
+/// What a pattern checks a value is, before it looks at the fields
+#[derive(Clone, Debug)]
+pub enum Ctor {
+    /// The variant at the index in the union
+    Variant(Rc<UnionDecl>, usize),
+    Bool(bool),
+    /// Any other literal, there are too many of them to list
+    Literal(Literal),
+}
+
+impl Ctor {
+    fn arity(&self) -> usize {
+        match self {
+            Ctor::Variant(union, index) => union.variants[*index].fields.len(),
+            Ctor::Bool(_) | Ctor::Literal(_) => 0,
+        }
+    }
+}
+
+impl PartialEq for Ctor {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Ctor::Variant(union, index), Ctor::Variant(other_union, other_index)) => {
+                same_union(union, other_union) && index == other_index
+            }
+            (Ctor::Bool(value), Ctor::Bool(other)) => value == other,
+            (Ctor::Literal(literal), Ctor::Literal(other)) => literal == other,
+            _ => false,
+        }
+    }
+}
+
+/// A pattern reduced to what matters for the analysis, the fields of a variant are in declaration order
+#[derive(Clone, PartialEq, Debug)]
+pub enum Pat {
+    Wild,
+    Ctor(Ctor, Vec<Pat>),
+}
+
+impl Pat {
+    /// None when the pattern names a union, variant or field that is not known. The union of an anonymous
+    /// variant pattern comes from the type of the value, which is only known for the fields of a variant.
+    pub fn lower(pattern: &Pattern, ty: Option<&Type>, unions: &dyn Fn(&str) -> Option<Rc<UnionDecl>>) -> Option<Pat> {
+        Some(match &pattern.kind {
+            PatternKind::Wildcard | PatternKind::Binding(_) => Pat::Wild,
+            PatternKind::Literal(Literal::Bool(value)) => Pat::Ctor(Ctor::Bool(*value), Vec::new()),
+            PatternKind::Literal(literal) => Pat::Ctor(Ctor::Literal(without_suffix(literal)), Vec::new()),
+            PatternKind::Variant(union, variant, fields) => Pat::lower_variant(unions(union)?, variant, fields, unions)?,
+            PatternKind::AnonymousVariant(variant, fields) => {
+                let union = match ty? {
+                    Type::Named(name) => unions(name)?,
+                    Type::Union(variants) => Rc::new(UnionDecl {
+                        name: String::new(),
+                        variants: variants.clone(),
+                    }),
+                    _ => return None,
+                };
+                Pat::lower_variant(union, variant, fields, unions)?
+            }
+        })
+    }
+
+    fn lower_variant(
+        union: Rc<UnionDecl>, variant: &str, fields: &[FieldPattern], unions: &dyn Fn(&str) -> Option<Rc<UnionDecl>>
+    ) -> Option<Pat> {
+        let index = union.variants.iter().position(|variant_decl| variant_decl.name == variant)?;
+        let field_decls = &union.variants[index].fields;
+
+        let mut args = vec![Pat::Wild; field_decls.len()];
+        for (position, field) in fields.iter().enumerate() {
+            if fields[..position].iter().any(|other| other.name == field.name) {
+                return None;
+            }
+            let field_index = field_decls.iter().position(|field_decl| field_decl.name == field.name)?;
+            args[field_index] = Pat::lower(&field.pattern, Some(&field_decls[field_index].ty), unions)?;
+        }
+        Some(Pat::Ctor(Ctor::Variant(union, index), args))
+    }
+}
+
+/// Prints the pattern as it is written, the fields that match anything are left out
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pat::Wild => write!(f, "_"),
+            Pat::Ctor(Ctor::Bool(value), _) => write!(f, "{}", value),
+            Pat::Ctor(Ctor::Literal(literal), _) => match literal {
+                Literal::String(value) => write!(f, "{:?}", value),
+                Literal::Char(value) => write!(f, "{:?}", value),
+                Literal::Bool(value) => write!(f, "{}", value),
+                Literal::Integer(value, _) => write!(f, "{}", value),
+                Literal::Float(value, _) => write!(f, "{:?}", value),
+            },
+            Pat::Ctor(Ctor::Variant(union, index), args) => {
+                let variant = &union.variants[*index];
+                write!(f, "{}.{}", union.name, variant.name)?;
+
+                let mut fields = variant.fields.iter().zip(args).filter(|(_, arg)| **arg != Pat::Wild).peekable();
+                if fields.peek().is_some() {
+                    write!(f, " {{ ")?;
+                    for (position, (field, arg)) in fields.enumerate() {
+                        if position > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{} = {}", field.name, arg)?;
+                    }
+                    write!(f, " }}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// The values no arm matches, and the indices of the arms that match no value the arms before them don't
+pub fn check_arms(arms: &[Pat]) -> (Vec<Pat>, Vec<usize>) {
+    let rows = arms.iter().map(|arm| vec![arm.clone()]).collect::<Vec<_>>();
+
+    let unreachable = (0..rows.len())
+        .filter(|&index| !is_useful(&rows[..index], &rows[index]))
+        .collect();
+    let missing = missing(&rows, 1)
+        .into_iter()
+        .map(|mut row| row.remove(0))
+        .collect();
+
+    (missing, unreachable)
+}
+
+// The analysis works on rows of patterns, which start as the arms, and get the fields of a variant
+// in place of the variant as it looks deeper into the values
+type Row = Vec<Pat>;
+
+// Whether a value matches the row, but none of the rows
+fn is_useful(rows: &[Row], row: &[Pat]) -> bool {
+    let Some((head, rest)) = row.split_first() else {
+        return rows.is_empty();
+    };
+
+    match head {
+        Pat::Ctor(ctor, args) => is_useful(&specialize(rows, ctor), &concat(args, rest)),
+        Pat::Wild => {
+            let heads = head_ctors(rows);
+            match complete_signature(&heads) {
+                Some(ctors) => ctors
+                    .iter()
+                    .any(|ctor| is_useful(&specialize(rows, ctor), &concat(&wilds(ctor.arity()), rest))),
+                None => is_useful(&default_rows(rows), rest),
+            }
+        }
+    }
+}
+
+// Examples of the values, `width` patterns each, that none of the rows match
+fn missing(rows: &[Row], width: usize) -> Vec<Row> {
+    if width == 0 {
+        return if rows.is_empty() { vec![Vec::new()] } else { Vec::new() };
+    }
+
+    let heads = head_ctors(rows);
+    if let Some(ctors) = complete_signature(&heads) {
+        return ctors
+            .into_iter()
+            .flat_map(|ctor| {
+                let arity = ctor.arity();
+                missing(&specialize(rows, &ctor), arity + width - 1)
+                    .into_iter()
+                    .map(move |mut args| {
+                        let rest = args.split_off(arity);
+                        once(Pat::Ctor(ctor.clone(), args)).chain(rest).collect()
+                    })
+            })
+            .collect();
+    }
+
+    let rest = missing(&default_rows(rows), width - 1);
+    if rest.is_empty() {
+        return rest;
+    }
+
+    // The constructors no row starts with are the examples, when there are few enough to list
+    let firsts = match signature(&heads) {
+        Some(ctors) if !heads.is_empty() => ctors
+            .into_iter()
+            .filter(|ctor| !heads.contains(ctor))
+            .map(|ctor| Pat::Ctor(ctor.clone(), wilds(ctor.arity())))
+            .collect(),
+        _ => vec![Pat::Wild],
+    };
+
+    firsts
+        .iter()
+        .flat_map(|first| rest.iter().map(move |row| once(first.clone()).chain(row.iter().cloned()).collect()))
+        .collect()
+}
+
+// The rows for the values that start with the constructor, with its fields in place of the first pattern
+fn specialize(rows: &[Row], ctor: &Ctor) -> Vec<Row> {
+    rows.iter()
+        .filter_map(|row| {
+            let (head, rest) = row.split_first().expect("a row is as wide as the values");
+            match head {
+                Pat::Wild => Some(concat(&wilds(ctor.arity()), rest)),
+                Pat::Ctor(head, args) if head == ctor => Some(concat(args, rest)),
+                Pat::Ctor(..) => None,
+            }
+        })
+        .collect()
+}
+
+// The rows for the values that start with none of the constructors of the first column
+fn default_rows(rows: &[Row]) -> Vec<Row> {
+    rows.iter()
+        .filter(|row| row[0] == Pat::Wild)
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+fn head_ctors(rows: &[Row]) -> Vec<Ctor> {
+    let mut heads = Vec::new();
+    for row in rows {
+        if let Pat::Ctor(ctor, _) = &row[0] {
+            if !heads.contains(ctor) {
+                heads.push(ctor.clone());
+            }
+        }
+    }
+    heads
+}
+
+// All the constructors of the type of the first column, when it has few enough to list
+fn signature(heads: &[Ctor]) -> Option<Vec<Ctor>> {
+    match heads.first()? {
+        Ctor::Variant(union, _) => heads
+            .iter()
+            .all(|ctor| matches!(ctor, Ctor::Variant(other, _) if same_union(union, other)))
+            .then(|| (0..union.variants.len()).map(|index| Ctor::Variant(union.clone(), index)).collect()),
+        Ctor::Bool(_) => heads
+            .iter()
+            .all(|ctor| matches!(ctor, Ctor::Bool(_)))
+            .then(|| vec![Ctor::Bool(true), Ctor::Bool(false)]),
+        Ctor::Literal(_) => None,
+    }
+}
+
+// The signature, when the first column has every one of its constructors
+fn complete_signature(heads: &[Ctor]) -> Option<Vec<Ctor>> {
+    signature(heads).filter(|ctors| ctors.iter().all(|ctor| heads.contains(ctor)))
+}
+
+// The union of an anonymous variant pattern is made for every pattern, the unions are the same when they are equal
+fn same_union(union: &Rc<UnionDecl>, other: &Rc<UnionDecl>) -> bool {
+    Rc::ptr_eq(union, other) || union == other
+}
+
+fn without_suffix(literal: &Literal) -> Literal {
+    match literal {
+        Literal::Integer(value, _) => Literal::Integer(*value, None),
+        Literal::Float(value, _) => Literal::Float(*value, None),
+        literal => literal.clone(),
+    }
+}
+
+fn wilds(count: usize) -> Vec<Pat> {
+    vec![Pat::Wild; count]
+}
+
+fn concat(first: &[Pat], rest: &[Pat]) -> Row {
+    first.iter().chain(rest).cloned().collect()
+}
@@ -0,0 +1,16 @@
+extern crate thiserror;
+
+extern crate mypl_lex;
+extern crate mypl_ast;
+
+mod analyzer;
+mod error;
+mod exhaustiveness;
+mod resolution;
+
+pub mod prelude {
+    use crate::*;
+
+    pub use analyzer::SemanticAnalyzer;
+    pub use error::SemanticError;
+}
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, HashSet};
+
+use mypl_ast::prelude::*;
+use mypl_lex::prelude::Span;
+
+/// What the analysis resolves in a list of statements, the program or the body of an instance.
+/// It is put in place once the whole list is checked.
+#[derive(Default)]
+pub(crate) struct Resolutions {
+    // The callee of the call at the span, the method of an impl or an instance
+    calls: HashMap<Span, ExprKind>,
+
+    // The instances of the function of the name declared at the span, they are declared after it
+    instances: HashMap<(Span, String), Vec<Function>>,
+
+    // The names of the instances, an instance is made once for the types of its arguments
+    names: HashSet<String>,
+}
+
+impl Resolutions {
+    pub(crate) fn resolve_call(&mut self, span: Span, callee: ExprKind) {
+        self.calls.insert(span, callee);
+    }
+
+    /// Whether the instance is new, and has to be made
+    pub(crate) fn add_name(&mut self, name: &str) -> bool {
+        self.names.insert(name.to_string())
+    }
+
+    /// The names of the instances made so far, to go back to when a program does not run
+    pub(crate) fn checkpoint(&self) -> HashSet<String> {
+        self.names.clone()
+    }
+
+    pub(crate) fn rollback(&mut self, names: HashSet<String>) {
+        self.names = names;
+    }
+
+    pub(crate) fn add_instance(&mut self, declaration: Span, function: &str, instance: Function) {
+        self.instances.entry((declaration, function.to_string())).or_default().push(instance);
+    }
+
+    /// Replaces the callees of the resolved calls, and declares the instances after their functions.
+    /// An instance of a function declared by an earlier program is declared first.
+    pub(crate) fn apply(&mut self, statements: &mut Vec<Stmt>) {
+        self.apply_stmts(statements);
+        let instances = std::mem::take(&mut self.instances).into_iter().flat_map(|((span, _), instances)| {
+            instances.into_iter().map(move |instance| declare(instance, span))
+        });
+        statements.splice(0..0, instances);
+        self.calls.clear();
+    }
+
+    fn apply_stmts(&mut self, statements: &mut Vec<Stmt>) {
+        let mut index = 0;
+        while index < statements.len() {
+            self.apply_stmt(&mut statements[index]);
+            index += 1;
+
+            // The instances are resolved already
+            let StmtKind::Decl(decl) = &statements[index - 1].kind else {
+                continue;
+            };
+            let DeclKind::Function(function) = &decl.kind else {
+                continue;
+            };
+            if let Some(instances) = self.instances.remove(&(decl.span, function.name.clone())) {
+                let span = decl.span;
+                let count = instances.len();
+                statements.splice(index..index, instances.into_iter().map(|instance| declare(instance, span)));
+                index += count;
+            }
+        }
+    }
+
+    fn apply_stmt(&mut self, stmt: &mut Stmt) {
+        match &mut stmt.kind {
+            StmtKind::Expr(expr) | StmtKind::Print(expr) | StmtKind::Println(expr) => self.apply_expr(expr),
+            StmtKind::Decl(decl) => match &mut decl.kind {
+                DeclKind::Var(_, _, expr) | DeclKind::Const(_, _, expr) => self.apply_expr(expr),
+                DeclKind::Function(function) => self.apply_stmts(&mut function.body),
+                DeclKind::Impl(impl_decl) => {
+                    impl_decl.methods.iter_mut().for_each(|method| self.apply_stmts(&mut method.body));
+                }
+                DeclKind::Record(_) | DeclKind::Union(_) | DeclKind::Trait(_) => {}
+            },
+            StmtKind::Assign(_, expr) | StmtKind::FieldAssign(_, _, expr) => self.apply_expr(expr),
+            StmtKind::Block(statements) => self.apply_stmts(statements),
+            StmtKind::If(condition, then_branch, else_branch) => {
+                self.apply_expr(condition);
+                self.apply_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.apply_stmt(else_branch);
+                }
+            }
+            StmtKind::For(_, iterable, body) => {
+                self.apply_expr(iterable);
+                self.apply_stmt(body);
+            }
+            StmtKind::While(condition, body) => {
+                self.apply_expr(condition);
+                self.apply_stmt(body);
+            }
+            StmtKind::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.apply_expr(expr);
+                }
+            }
+            StmtKind::Match(subject, arms) => {
+                self.apply_expr(subject);
+                arms.iter_mut().for_each(|arm| self.apply_stmt(&mut arm.body));
+            }
+            StmtKind::Break | StmtKind::Continue => {}
+        }
+    }
+
+    fn apply_expr(&mut self, expr: &mut Expr) {
+        match &mut expr.kind {
+            ExprKind::Binary(_, lhs, rhs) => {
+                self.apply_expr(lhs);
+                self.apply_expr(rhs);
+            }
+            ExprKind::Unary(_, expr) => self.apply_expr(expr),
+            ExprKind::Interpolated(parts) => parts.iter_mut().for_each(|part| match part {
+                InterpolatedPart::Expr(expr) => self.apply_expr(expr),
+                InterpolatedPart::Text(_) => {}
+            }),
+            ExprKind::If(condition, then_branch, else_branch) => {
+                self.apply_expr(condition);
+                self.apply_expr(then_branch);
+                self.apply_expr(else_branch);
+            }
+            ExprKind::Block(statements, value) => {
+                self.apply_stmts(statements);
+                self.apply_expr(value);
+            }
+            ExprKind::Range(start, end, _) => {
+                self.apply_expr(start);
+                self.apply_expr(end);
+            }
+            ExprKind::Call(callee, args) => {
+                match self.calls.get(&expr.span) {
+                    Some(resolved) => callee.kind = resolved.clone(),
+                    None => self.apply_expr(callee),
+                }
+                args.iter_mut().for_each(|arg| self.apply_expr(arg));
+            }
+            ExprKind::Record(_, fields)
+            | ExprKind::Variant(_, _, fields)
+            | ExprKind::AnonymousRecord(fields)
+            | ExprKind::AnonymousVariant(_, fields) => fields.iter_mut().for_each(|field| self.apply_expr(&mut field.value)),
+            ExprKind::Match(subject, arms) => {
+                self.apply_expr(subject);
+                arms.iter_mut().for_each(|arm| self.apply_expr(&mut arm.body));
+            }
+            ExprKind::Field(expr, _) => self.apply_expr(expr),
+            ExprKind::Literal(_) | ExprKind::Variable(_) | ExprKind::Method(..) => {}
+        }
+    }
+}
+
+// An instance is declared like its function, with the span of its declaration
+fn declare(instance: Function, span: Span) -> Stmt {
+    let decl = Decl {
+        kind: DeclKind::Function(Box::new(instance)),
+        doc: None,
+        span,
+    };
+    Stmt {
+        kind: StmtKind::Decl(Box::new(decl)),
+        span,
+    }
+}